@@ -0,0 +1,7 @@
+//! Print the JSON Schema of the wire protocol.
+
+use concierge_api_rs::json_schema;
+
+fn main() {
+    print!("{}", json_schema::render());
+}
@@ -0,0 +1,67 @@
+//! Stable codes of the errors that the concierge responds with.
+//!
+//! Clients that negotiate `Capability::Errors` receive every error as a
+//! `PayloadOut::Error`, and can branch on its `code`. Other clients receive
+//! the legacy payload of the code instead, as listed below.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+/// Cause of a `PayloadOut::Error`.
+///
+/// Codes are never renamed nor reused, and new codes may be added, so
+/// clients should expect codes that they do not know.
+#[derive(Serialize, Deserialize, TS, JsonSchema, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// The payload could not be parsed. `desc` is the parser error.
+    ///
+    /// Legacy payload: `ERROR_PROTOCOL`.
+    Protocol,
+    /// The payload is valid, but not handled by the concierge, such as
+    /// `IDENTIFY` after identification.
+    ///
+    /// Legacy payload: `ERROR_UNSUPPORTED`.
+    Unsupported,
+    /// The concierge failed to handle the payload.
+    ///
+    /// Legacy payload: `ERROR_INTERNAL`.
+    Internal,
+    /// No client exists by the name in `ref`.
+    ///
+    /// Legacy payload: `INVALID_NAME`.
+    InvalidName,
+    /// No client exists by the uuid in `ref`.
+    ///
+    /// Legacy payload: `INVALID_UUID`.
+    InvalidUuid,
+    /// No service exists by the name in `ref`, or the name in `ref` can not
+    /// be used for that payload, such as a pattern when creating a service.
+    ///
+    /// Legacy payload: `INVALID_SERVICE`.
+    InvalidService,
+    /// Only subscribers of the service in `ref` can send messages to it.
+    ///
+    /// Legacy payload: `BAD`.
+    NotSubscribed,
+    /// Only the owner of the service in `ref` can do that.
+    ///
+    /// Legacy payload: `BAD`.
+    NotOwner,
+    /// A value does not satisfy its schema. `ref` is the JSON pointer of the
+    /// offending value, and `desc` describes the violation.
+    ///
+    /// Legacy payload: `ERROR_SCHEMA`.
+    Schema,
+    /// The client by the name in `ref` is offline, and its queue of messages
+    /// is full.
+    ///
+    /// Legacy payload: `INVALID_NAME`.
+    QueueFull,
+    /// Codes unknown to this version of the protocol.
+    #[serde(other)]
+    #[ts(skip)]
+    #[schemars(skip)]
+    Unknown,
+}
@@ -0,0 +1,69 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ts_rs::TS;
+
+/// A predicate on the `data` field of messages, which subscribers can
+/// attach to their subscription. The concierge only relays the messages
+/// of a service to a subscriber if they satisfy its filter.
+///
+/// Fields are located with [JSON pointers](https://tools.ietf.org/html/rfc6901),
+/// such as `/type` or `/entity/position/0`. A pointer to a missing field
+/// fails every test except `NOT_EXISTS`.
+#[derive(Serialize, Deserialize, TS, JsonSchema, Clone, Debug)]
+#[serde(tag = "op", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Filter {
+    /// The field is equal to the value.
+    Eq { pointer: String, value: Value },
+    /// The field is not equal to the value.
+    Ne { pointer: String, value: Value },
+    /// The field is equal to one of the values.
+    In { pointer: String, values: Vec<Value> },
+    /// The field is a number within the inclusive bounds.
+    /// Missing bounds are unbounded.
+    Range {
+        pointer: String,
+        #[serde(default)]
+        #[ts(optional)]
+        min: Option<f64>,
+        #[serde(default)]
+        #[ts(optional)]
+        max: Option<f64>,
+    },
+    /// The field exists.
+    Exists { pointer: String },
+    /// The field does not exist.
+    NotExists { pointer: String },
+    /// Every filter is satisfied.
+    All { filters: Vec<Filter> },
+    /// At least one filter is satisfied.
+    Any { filters: Vec<Filter> },
+    /// The filter is not satisfied.
+    Not { filter: Box<Filter> },
+}
+
+impl Filter {
+    /// Check if the data of a message satisfies the filter.
+    pub fn matches(&self, data: &Value) -> bool {
+        match self {
+            Filter::Eq { pointer, value } => data.pointer(pointer) == Some(value),
+            Filter::Ne { pointer, value } => {
+                data.pointer(pointer).map_or(false, |field| field != value)
+            }
+            Filter::In { pointer, values } => data
+                .pointer(pointer)
+                .map_or(false, |field| values.contains(field)),
+            Filter::Range { pointer, min, max } => data
+                .pointer(pointer)
+                .and_then(Value::as_f64)
+                .map_or(false, |field| {
+                    min.map_or(true, |min| field >= min) && max.map_or(true, |max| field <= max)
+                }),
+            Filter::Exists { pointer } => data.pointer(pointer).is_some(),
+            Filter::NotExists { pointer } => data.pointer(pointer).is_none(),
+            Filter::All { filters } => filters.iter().all(|filter| filter.matches(data)),
+            Filter::Any { filters } => filters.iter().any(|filter| filter.matches(data)),
+            Filter::Not { filter } => !filter.matches(data),
+        }
+    }
+}
@@ -0,0 +1,23 @@
+//! Checks of the generated files that are committed to the repository.
+
+use std::{fs, io, path::Path};
+
+/// Check if the file at `path` has the `rendered` contents. If it does not
+/// and the environment variable `update_var` is set, the file is rewritten
+/// and considered up to date.
+pub(crate) fn verify(path: &Path, rendered: &str, update_var: &str) -> io::Result<bool> {
+    let current = match fs::read_to_string(path) {
+        Ok(current) => current,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err),
+    };
+
+    if current == rendered {
+        Ok(true)
+    } else if std::env::var_os(update_var).is_some() {
+        fs::write(path, rendered)?;
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
@@ -1,9 +1,59 @@
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use uuid::Uuid;
 use std::borrow::Cow;
+use schemars::JsonSchema;
+use ts_rs::TS;
+
+/// Presence status of a client.
+#[derive(Serialize, Deserialize, TS, JsonSchema, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Status {
+    Online,
+    Away,
+    Busy,
+}
+
+impl Default for Status {
+    fn default() -> Self {
+        Status::Online
+    }
+}
+
+/// Presence of a client, as set by `SELF_UPDATE`.
+#[derive(Serialize, Deserialize, TS, JsonSchema, Clone, Debug, Default)]
+pub struct Presence<'a> {
+    /// Presence status of the client.
+    #[serde(default)]
+    #[ts(as = "Option<Status>", optional)]
+    pub status: Status,
+    /// Arbitrary JSON attached by the client, such as an activity description.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom: Option<Cow<'a, Value>>,
+}
+
+impl Presence<'_> {
+    /// Uncouple the information from the original borrowed lifetime
+    /// and return a struct that has fully owned references.
+    pub fn owned(&self) -> Presence<'static> {
+        Presence {
+            status: self.status,
+            custom: self.custom.as_deref().cloned().map(Cow::Owned),
+        }
+    }
+}
+
+/// Latency of a client, measured from the heartbeats of its connection.
+#[derive(Serialize, Deserialize, TS, JsonSchema, Copy, Clone, Debug, PartialEq)]
+pub struct Latency {
+    /// Smoothed round-trip time, in milliseconds.
+    pub rtt: f64,
+    /// Smoothed variation of the round-trip time, in milliseconds.
+    pub jitter: f64,
+}
 
 /// A client payload.
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, TS, JsonSchema, Clone, Debug)]
 pub struct Client<'a> {
     /// Client name.
     #[serde(borrow)]
@@ -12,9 +62,17 @@ pub struct Client<'a> {
     #[serde(borrow)]
     pub nickname: Option<Cow<'a, str>>,
     /// Uuid of the client.
+    #[ts(as = "crate::typescript::Uuid")]
     pub uuid: Uuid,
-    /// Tags of the client.
-    pub tags: Vec<Cow<'a, str>>,
+    /// Tags of the client. Omitted if excluded by a field projection.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<Cow<'a, str>>>,
+    /// Presence of the client.
+    #[serde(default)]
+    pub presence: Presence<'a>,
+    /// Latency of the client, once measured. Only present if requested by a field projection.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency: Option<Latency>,
 }
 
 impl<'a> Client<'a> {
@@ -23,6 +81,7 @@ impl<'a> Client<'a> {
         Origin {
             client: self,
             service: None,
+            received: None,
         }
     }
 
@@ -35,16 +94,16 @@ impl<'a> Client<'a> {
             uuid: self.uuid,
             tags: self
                 .tags
-                .iter()
-                .map(|s| s.to_string())
-                .map(Cow::Owned)
-                .collect(),
+                .as_ref()
+                .map(|tags| tags.iter().map(|s| s.to_string()).map(Cow::Owned).collect()),
+            presence: self.presence.owned(),
+            latency: self.latency,
         }
     }
 }
 
 /// A service payload.
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, TS, JsonSchema, Clone, Debug)]
 pub struct Service<'a> {
     /// Service name.
     #[serde(borrow)]
@@ -53,9 +112,15 @@ pub struct Service<'a> {
     #[serde(borrow)]
     pub nickname: Option<Cow<'a, str>>,
     /// Uuid of the service's owner.
+    #[ts(as = "crate::typescript::Uuid")]
     pub owner_uuid: Uuid,
-    /// Subscribers
-    pub subscribers: Vec<Uuid>,
+    /// Subscribers. Omitted if excluded by a field projection.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(as = "Option<Vec<crate::typescript::Uuid>>")]
+    pub subscribers: Option<Vec<Uuid>>,
+    /// Number of subscribers. Only present if requested by a field projection.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subscriber_count: Option<usize>,
 }
 
 impl Service<'_> {
@@ -67,12 +132,13 @@ impl Service<'_> {
             nickname: self.nickname.as_deref().map(str::to_string).map(Cow::Owned),
             owner_uuid: self.owner_uuid,
             subscribers: self.subscribers.clone(),
+            subscriber_count: self.subscriber_count,
         }
     }
 }
 
 /// An origin receipt for certain payloads.
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, TS, JsonSchema, Clone, Debug)]
 pub struct Origin<'a> {
     #[serde(flatten)]
     pub client: Client<'a>,
@@ -81,6 +147,11 @@ pub struct Origin<'a> {
     /// was originally sent to.
     #[serde(borrow)]
     pub service: Option<Service<'a>>,
+    /// Only available on MESSAGE payloads.
+    /// Milliseconds since the Unix epoch at which the server received
+    /// the message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub received: Option<f64>,
 }
 
 impl<'a> Origin<'a> {
@@ -89,4 +160,20 @@ impl<'a> Origin<'a> {
         self.service = Some(service_info);
         self
     }
+
+    /// Attaches the time at which the server received the message.
+    pub fn with_received(mut self, received: f64) -> Origin<'a> {
+        self.received = Some(received);
+        self
+    }
+
+    /// Uncouple the information from the original borrowed lifetime
+    /// and return a struct that has fully owned references.
+    pub fn owned(&self) -> Origin<'static> {
+        Origin {
+            client: self.client.owned(),
+            service: self.service.as_ref().map(Service::owned),
+            received: self.received,
+        }
+    }
 }
\ No newline at end of file
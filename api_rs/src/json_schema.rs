@@ -0,0 +1,53 @@
+//! JSON Schema of the wire protocol, for clients that are not written in Rust.
+//!
+//! The schema is derived from the Rust types with `schemars`. Every payload
+//! shape is a definition, and the root schema accepts any payload. The copy
+//! committed to `docs/protocol.schema.json` is checked by the tests, and
+//! `UPDATE_SCHEMA=1 cargo test` rewrites it when it is stale. The schema can
+//! also be printed with `cargo run --bin export_schema`.
+
+use crate::{
+    payload::SequencedPayloadOut, PayloadIn, PayloadMessage, PayloadOut, RequestId, Target,
+};
+use schemars::gen::SchemaSettings;
+use serde_json::{json, Value};
+use std::{io, path::Path};
+
+/// Environment variable that lets `verify` rewrite a stale file.
+pub const UPDATE_VAR: &str = "UPDATE_SCHEMA";
+
+/// JSON Schema of every payload of the concierge, in draft 7. The shapes are
+/// available under `#/definitions`, such as `#/definitions/PayloadIn`.
+pub fn protocol() -> Value {
+    let mut generator = SchemaSettings::draft07().into_generator();
+    let payloads = vec![
+        generator.subschema_for::<PayloadIn>(),
+        generator.subschema_for::<PayloadMessage<Value>>(),
+        generator.subschema_for::<SequencedPayloadOut>(),
+        generator.subschema_for::<PayloadOut>(),
+    ];
+    generator.subschema_for::<Target>();
+    generator.subschema_for::<RequestId>();
+
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "Concierge protocol",
+        "description": "Any payload sent to or from the concierge.",
+        "anyOf": payloads,
+        "definitions": generator.definitions(),
+    })
+}
+
+/// Render the schema as pretty printed JSON.
+pub fn render() -> String {
+    let mut rendered = serde_json::to_string_pretty(&protocol()).unwrap();
+    rendered.push('\n');
+    rendered
+}
+
+/// Check if the file at `path` is up to date. If it is stale and the
+/// `UPDATE_SCHEMA` environment variable is set, the file is rewritten
+/// and considered up to date.
+pub fn verify(path: impl AsRef<Path>) -> io::Result<bool> {
+    crate::generated::verify(path.as_ref(), &render(), UPDATE_VAR)
+}
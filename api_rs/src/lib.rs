@@ -1,16 +1,29 @@
 pub mod payload;
+pub mod error;
 pub mod info;
 pub mod message;
+pub mod filter;
+pub mod query;
+pub mod record;
+pub mod schema;
+pub mod topic;
+pub mod webhook;
+pub mod typescript;
+pub mod json_schema;
+mod generated;
 
-pub use payload::{PayloadIn, PayloadOut};
+pub use payload::{Capability, DeliveryStatus, EventClass, PayloadIn, PayloadOut, Reply, Request, RequestId};
 pub use message::{PayloadMessage, Target};
-pub use info::{Client, Service, Origin};
+pub use filter::Filter;
+pub use error::ErrorCode;
+pub use info::{Client, Service, Origin, Presence, Status};
 
 use std::borrow::Cow;
 
 /// Identification used by services.
 ///
-/// For now, they are identified by names.
+/// For now, they are identified by names. Names are hierarchical,
+/// see the `topic` module.
 pub type ServiceId<'a> = &'a str;
 
 /// Close reasons, as used by the central server.
@@ -39,7 +52,20 @@ impl<'a> CloseReason<'a> {
     pub const BAD_AUTH: CloseReason<'static> = CloseReason::new_const(4008, "Name must be alphanumeric");
     /// Heartbeat failed
     pub const HB_FAILED: CloseReason<'static> = CloseReason::new_const(4009, "Heartbeat failed");
-    
+
+    /// Every close reason, along with its name.
+    pub const ALL: [(&'static str, CloseReason<'static>); 9] = [
+        ("UNKNOWN", Self::UNKNOWN),
+        ("FATAL_DECODE", Self::FATAL_DECODE),
+        ("NO_AUTH", Self::NO_AUTH),
+        ("AUTH_FAILED", Self::AUTH_FAILED),
+        ("DUPLICATE_AUTH", Self::DUPLICATE_AUTH),
+        ("BAD_SECRET", Self::BAD_SECRET),
+        ("BAD_VERSION", Self::BAD_VERSION),
+        ("BAD_AUTH", Self::BAD_AUTH),
+        ("HB_FAILED", Self::HB_FAILED),
+    ];
+
     const fn new_const(code: u16, reason: &'static str) -> Self {
         Self { code, reason: Cow::Borrowed(reason) }
     }
@@ -1,21 +1,29 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use crate::{ServiceId, info::Origin};
+use schemars::JsonSchema;
+use ts_rs::TS;
 
 /// Targetting directive for message payloads.
-#[derive(Serialize, Deserialize, Copy, Clone, Debug)]
+#[derive(Serialize, Deserialize, TS, JsonSchema, Copy, Clone, Debug)]
 #[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum Target<'a> {
     /// Target a client by their name.
     ///
     /// ### Response
     /// * `INVALID_NAME`: The target client does not exist by that name.
+    /// * `MESSAGE_STATUS`: The target client is offline, and the message
+    ///   was queued for it.
+    /// * `QUEUE_FULL`: The target client is offline, and its queue is full.
     Name { name: &'a str },
     /// Target a client by their uuid.
     ///
     /// ### Response
     /// * `INVALID_UUID`: The target client does not exist by that uuid.
-    Uuid { uuid: Uuid },
+    Uuid {
+        #[ts(as = "crate::typescript::Uuid")]
+        uuid: Uuid,
+    },
     /// Target a service by its name.
     ///
     /// ### Response
@@ -28,13 +36,17 @@ pub enum Target<'a> {
     /// ### Response
     /// * `BAD`: Insufficient permission to use this target.
     /// * `INVALID_SERVICE`: The service does not exist by that name.
-    ServiceClientUuid { service: ServiceId<'a>, uuid: Uuid },
+    ServiceClientUuid {
+        service: ServiceId<'a>,
+        #[ts(as = "crate::typescript::Uuid")]
+        uuid: Uuid,
+    },
     /// Target every client connected to the concierge.
     All,
 }
 
 /// Constant field for type safe deserialization of messages.
-#[derive(Serialize, Deserialize, Copy, Clone, Debug)]
+#[derive(Serialize, Deserialize, TS, JsonSchema, Copy, Clone, Debug)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 enum PayloadMessageType {
     /// Message enum.
@@ -51,11 +63,15 @@ enum PayloadMessageType {
 /// * `INVALID_NAME`: The target client does not exist by that name.
 /// * `INVALID_UUID`: The target client does not exist by that uuid.
 /// * `INVALID_SERVICE`: The service does not exist by that name.
-#[derive(Serialize, Deserialize, Clone, Debug)]
+/// * `ERROR_SCHEMA`: The data does not satisfy the schema of the target service.
+/// * `MESSAGE_STATUS`: The message was queued for an offline client.
+#[derive(Serialize, Deserialize, TS, JsonSchema, Clone, Debug)]
+#[schemars(rename = "PayloadMessage")]
 pub struct PayloadMessage<'a, T> {
     /// Mimics the "type" tag of Payload. This should always be PayloadRawType::Message.
     r#type: PayloadMessageType,
     /// Origin of the message.
+    #[ts(optional = nullable)]
     pub origin: Option<Origin<'a>>,
     /// Target of the message. This can be a single user
     /// (using name or uuid), or a service.
@@ -64,6 +80,11 @@ pub struct PayloadMessage<'a, T> {
     /// Data field. This is a raw JSON value field that borrows the JSON
     /// string directly from the deserialized buffer.
     pub data: T,
+    /// Hold the message if the client targeted by name is offline, and
+    /// deliver it when that name next identifies. Other targets ignore it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    #[ts(as = "Option<bool>", optional)]
+    pub queue: bool,
 }
 
 impl<'a, T> PayloadMessage<'a, T> {
@@ -74,6 +95,7 @@ impl<'a, T> PayloadMessage<'a, T> {
             origin: None,
             target,
             data,
+            queue: false,
         }
     }
 
@@ -82,4 +104,10 @@ impl<'a, T> PayloadMessage<'a, T> {
         self.origin = Some(origin);
         self
     }
+
+    /// Queue the message if its target is offline.
+    pub fn queued(mut self) -> Self {
+        self.queue = true;
+        self
+    }
 }
\ No newline at end of file
@@ -1,20 +1,101 @@
 use crate::{
-    info::{Client, Service},
+    error::ErrorCode,
+    filter::Filter,
+    info::{Client, Presence, Service},
+    query::{ClientProjection, ServiceProjection},
+    schema::ServiceSchemas,
     ServiceId,
 };
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+use ts_rs::TS;
 use uuid::Uuid;
 
+/// Classes of global events that the concierge broadcasts to every client.
+/// Clients can choose which of these they want to receive.
+#[derive(Serialize, Deserialize, TS, JsonSchema, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum EventClass {
+    /// See `PayloadOut::ClientJoined`.
+    ClientJoined,
+    /// See `PayloadOut::ClientLeft`.
+    ClientLeft,
+    /// See `PayloadOut::ClientUpdated`.
+    ClientUpdated,
+    /// See `PayloadOut::ServiceCreateResult`.
+    ServiceCreated,
+    /// See `PayloadOut::ServiceDeleteResult`.
+    ServiceDeleted,
+}
+
+impl EventClass {
+    /// Every event class. Clients receive all of them unless they state otherwise.
+    pub const ALL: [EventClass; 5] = [
+        EventClass::ClientJoined,
+        EventClass::ClientLeft,
+        EventClass::ClientUpdated,
+        EventClass::ServiceCreated,
+        EventClass::ServiceDeleted,
+    ];
+}
+
+/// What became of a message queued for an offline client.
+#[derive(Serialize, Deserialize, TS, JsonSchema, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DeliveryStatus {
+    /// The message is held until the client identifies.
+    Queued,
+    /// The message was delivered to the client.
+    Delivered,
+    /// The client did not identify before the message expired.
+    Expired,
+}
+
+/// Optional protocol features that a client can request in `IDENTIFY`.
+/// The server replies with the subset that it supports in `HELLO`, and
+/// only the negotiated features may be used on the connection.
+#[derive(Serialize, Deserialize, TS, JsonSchema, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Capability {
+    /// Payloads may be sent in binary frames.
+    Binary,
+    /// A text frame may carry a JSON array of payloads, handled in order.
+    Batching,
+    /// Frames may be compressed.
+    Compression,
+    /// Services may be called with request/response semantics.
+    Rpc,
+    /// Sessions may be resumed after a disconnection.
+    Resume,
+    /// Errors are sent as `PayloadOut::Error` instead of the legacy payloads.
+    Errors,
+    /// Capabilities unknown to this version of the protocol,
+    /// which are never negotiated.
+    #[serde(other)]
+    #[ts(skip)]
+    #[schemars(skip)]
+    Unknown,
+}
+
 /// `PayloadIn` represents the types of payloads that the central server
 /// is expected to respond to. All payloads are expected to be tagged with
 /// a `type` field indicating it's nature, with the value being in
 /// SCREAMING_SNAKE_CASE.
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, TS, JsonSchema, Clone, Debug)]
 #[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
+#[ts(optional_fields)]
 pub enum PayloadIn<'a> {
     /// The server expects this payload to be sent within 5 seconds of
     /// establishing the socket connection, to avoid the connection be dropped.
     ///
+    /// The optional `events` field lists the global event classes that the
+    /// client wants to receive. If it is missing, every class is received.
+    ///
+    /// The optional `capabilities` field lists the protocol features that
+    /// the client requests (see `Capability`). Unknown ones are ignored.
+    ///
     /// ### Responses
     /// * `HELLO`: Upon successful identification.
     /// * Socket close: Upon unsuccessful identification.
@@ -24,7 +105,13 @@ pub enum PayloadIn<'a> {
         version: &'a str,
         secret: Option<&'a str>,
         #[serde(default)]
+        #[ts(as = "Option<Vec<&'a str>>")]
         tags: Vec<&'a str>,
+        #[serde(default)]
+        events: Option<Vec<EventClass>>,
+        #[serde(default)]
+        #[ts(as = "Option<Vec<Capability>>")]
+        capabilities: Vec<Capability>,
     },
     /// The client sends this to subscribe to a specific service.
     /// Being subscribed to a service means receiving all messages the owner
@@ -32,15 +119,34 @@ pub enum PayloadIn<'a> {
     /// can also send a message to the service, which will be relayed to the
     /// owner only.
     ///
+    /// The service can also be a wildcard pattern (see the `topic` module),
+    /// which subscribes the client to every matching service, including
+    /// services that are created later.
+    ///
+    /// The optional `filter` only lets the messages of the service through to
+    /// the client if their `data` satisfies it (see `Filter`). Subscribing again
+    /// replaces the filter of the subscription.
+    ///
     /// ### Responses
-    /// * `INVALID_SERVICE`: The service does not exist by that name.
+    /// * `INVALID_SERVICE`: The service does not exist by that name,
+    ///   or the pattern is malformed.
     /// * `SELF_SUBSCRIBE_RESULT`: See `PayloadOut::SelfSubscribeResult`.
-    SelfSubscribe { service: ServiceId<'a> },
+    /// * `SELF_SUBSCRIBE_PATTERN_RESULT`: See `PayloadOut::SelfSubscribePatternResult`.
+    SelfSubscribe {
+        service: ServiceId<'a>,
+        #[serde(default)]
+        filter: Option<Filter>,
+    },
     /// The client sends this to unsubscribe from a specific service.
     ///
+    /// The service can also be a wildcard pattern, which removes the pattern
+    /// and unsubscribes the client from every matching service.
+    ///
     /// ### Responses
-    /// * `INVALID_SERVICE`: The service does not exist by that name.
+    /// * `INVALID_SERVICE`: The service does not exist by that name,
+    ///   or the pattern is malformed.
     /// * `SELF_UNSUBSCRIBE_RESULT`: See `PayloadOut::SelfUnsubscribeResult`.
+    /// * `SELF_UNSUBSCRIBE_PATTERN_RESULT`: See `PayloadOut::SelfUnsubscribePatternResult`.
     SelfUnsubscribe { service: ServiceId<'a> },
     /// The client sends this to fetch information about its state
     /// on the central server.
@@ -54,20 +160,53 @@ pub enum PayloadIn<'a> {
     /// ### Responses
     /// * `OK`: Successfully reset the sequence number.
     SelfSetSeq { seq: usize },
+    /// The client sends this to replace the global event classes that
+    /// it receives.
+    ///
+    /// ### Responses
+    /// * `OK`: Successfully set the event classes.
+    SelfSetEvents { events: Vec<EventClass> },
+    /// The client sends this to update its nickname, tags or presence.
+    /// Fields that are missing are left unchanged.
+    ///
+    /// ### Responses
+    /// * `CLIENT_UPDATED`: See `PayloadOut::ClientUpdated`.
+    SelfUpdate {
+        nickname: Option<&'a str>,
+        tags: Option<Vec<&'a str>>,
+        presence: Option<Presence<'a>>,
+    },
+    /// The client sends this to estimate the offset of its clock from the
+    /// server clock, like NTP does. `client_time` is the time of the client
+    /// when it sends this, in milliseconds.
+    ///
+    /// ### Responses
+    /// * `TIME_SYNC_RESULT`: See `PayloadOut::TimeSyncResult`.
+    TimeSync { client_time: f64 },
     /// The client sends this to create a service.
+    /// Service names can not contain wildcards.
+    ///
+    /// The optional `schemas` are JSON Schemas for the data of messages sent
+    /// to the service by subscribers (`inbound`) and by the owner (`outbound`).
+    /// The concierge rejects messages that do not satisfy them with `ERROR_SCHEMA`.
     ///
     /// ### Responses
+    /// * `INVALID_SERVICE`: The service name contains wildcards.
+    /// * `ERROR_SCHEMA`: One of the schemas is not an object or a boolean.
     /// * `SERVICE_CREATE_RESULT`: See `PayloadOut::ServiceCreateResult`.
     ServiceCreate {
         service: ServiceId<'a>,
         nickname: Option<&'a str>,
+        #[serde(default)]
+        #[ts(as = "Option<ServiceSchemas>")]
+        schemas: ServiceSchemas,
     },
-    /// The client sends this to create a service.
+    /// The client sends this to delete a service.
     ///
     /// ### Responses
     /// * `INVALID_SERVICE`: The service does not exist by that name.
     /// * `BAD`: The client is not the owner of that service.
-    /// * `SERVICE_CREATE_RESULT`: See `PayloadOut::ServiceCreateResult`.
+    /// * `SERVICE_DELETE_RESULT`: See `PayloadOut::ServiceDeleteResult`.
     ServiceDelete { service: ServiceId<'a> },
     /// The client sends this to fetch information of a specific service.
     ///
@@ -75,21 +214,66 @@ pub enum PayloadIn<'a> {
     /// * `INVALID_SERVICE`: The service does not exist by that name.
     /// * `SERVICE_FETCH_RESULT`: See `PayloadOut::ServiceFetchResult`.
     ServiceFetch { service: ServiceId<'a> },
+    /// The client sends this to fetch the JSON Schemas of a specific service.
+    ///
+    /// ### Responses
+    /// * `INVALID_SERVICE`: The service does not exist by that name.
+    /// * `SERVICE_FETCH_SCHEMAS_RESULT`: See `PayloadOut::ServiceFetchSchemasResult`.
+    ServiceFetchSchemas { service: ServiceId<'a> },
     /// The client sends this to fetch information of all services on the server.
     ///
+    /// ### Notes
+    /// Every field is optional. Services are listed in order of their names.
+    /// * `name_prefix`: Only list services whose name starts with this prefix.
+    /// * `owner_uuid`: Only list services owned by this client.
+    /// * `cursor`: Only list services after this name, as returned by `next`.
+    /// * `limit`: Maximum number of services to list.
+    /// * `fields`: Field projection of the service information objects.
+    ///
     /// ### Responses
     /// * `SERVICE_FETCH_ALL_RESULT`: See `PayloadOut::ServiceFetchAllResult`.
-    ServiceFetchAll,
+    ServiceFetchAll {
+        name_prefix: Option<&'a str>,
+        #[ts(as = "Option<crate::typescript::Uuid>")]
+        owner_uuid: Option<Uuid>,
+        cursor: Option<ServiceId<'a>>,
+        limit: Option<usize>,
+        #[serde(default)]
+        #[ts(as = "Option<ServiceProjection>")]
+        fields: ServiceProjection,
+    },
     /// The client sends this to fetch information about another client by their UUID.
     ///
     /// ### Responses
+    /// * `INVALID_UUID`: The client does not exist by that uuid.
     /// * `CLIENT_FETCH_RESULT`: See `PayloadOut::ClientFetchResult`.
-    ClientFetch { uuid: Uuid } ,
+    ClientFetch {
+        #[ts(as = "crate::typescript::Uuid")]
+        uuid: Uuid,
+    },
     /// The client sends this to fetch information of all clients on the server.
     ///
+    /// ### Notes
+    /// Every field is optional. Clients are listed in order of their names.
+    /// * `tag`: Only list clients that have this tag.
+    /// * `name_prefix`: Only list clients whose name starts with this prefix.
+    /// * `service`: Only list clients subscribed to this service.
+    /// * `cursor`: Only list clients after this name, as returned by `next`.
+    /// * `limit`: Maximum number of clients to list.
+    /// * `fields`: Field projection of the client information objects.
+    ///
     /// ### Responses
     /// * `CLIENT_FETCH_ALL_RESULT`: See `PayloadOut::ClientFetchAllResult`.
-    ClientFetchAll,
+    ClientFetchAll {
+        tag: Option<&'a str>,
+        name_prefix: Option<&'a str>,
+        service: Option<ServiceId<'a>>,
+        cursor: Option<&'a str>,
+        limit: Option<usize>,
+        #[serde(default)]
+        #[ts(as = "Option<ClientProjection>")]
+        fields: ClientProjection,
+    },
 }
 
 /// `PayloadOut` represents the types of payloads sent from the server.
@@ -98,18 +282,28 @@ pub enum PayloadIn<'a> {
 ///
 /// Many of these payloads are also attached with a sequence number.
 /// For more information, see `SequencedPayloadOut`.
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, TS, JsonSchema, Clone, Debug)]
 #[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum PayloadOut<'a> {
     /// Generic OK payload.
     Ok,
     /// Generic BAD payload.
+    ///
+    /// Legacy error, see `ErrorCode::NotSubscribed` and `ErrorCode::NotOwner`.
     Bad,
     /// This server sends this upon successful identification.
     /// The payload will also contain a universally unique identifier
     /// that acts as a file server key. The payload also returns
-    /// the server's version.
-    Hello { uuid: Uuid, version: &'a str },
+    /// the server's version, and the capabilities negotiated for the
+    /// connection, which are omitted if none were.
+    Hello {
+        #[ts(as = "crate::typescript::Uuid")]
+        uuid: Uuid,
+        version: &'a str,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        #[ts(as = "Option<Vec<Capability>>", optional)]
+        capabilities: Vec<Capability>,
+    },
     /// The server sends this in response to `SELF_SUBSCRIBE`.
     ///
     /// ### Notes
@@ -132,6 +326,35 @@ pub enum PayloadOut<'a> {
         #[serde(borrow)]
         service: Service<'a>,
     },
+    /// The server sends this in response to `SELF_SUBSCRIBE` with a pattern.
+    ///
+    /// ### Notes
+    /// The payload contains a `successful` boolean flag that indicates
+    /// if the client subscribed to a new pattern (true) or is already
+    /// subscribed to that pattern (false). The existing services that match
+    /// the pattern are available as an array of service information objects.
+    ///
+    /// When a matching service is created later, the client is sent an
+    /// unsequenced `SELF_SUBSCRIBE_RESULT` for that service.
+    SelfSubscribePatternResult {
+        successful: bool,
+        pattern: &'a str,
+        #[serde(borrow)]
+        services: Vec<Service<'a>>,
+    },
+    /// The server sends this in response to `SELF_UNSUBSCRIBE` with a pattern.
+    ///
+    /// ### Notes
+    /// The payload contains a `successful` boolean flag that indicates
+    /// if the client unsubscribed from a pattern (true) or was not subscribed
+    /// to that pattern (false). The services that the client was unsubscribed
+    /// from are available as an array of service information objects.
+    SelfUnsubscribePatternResult {
+        successful: bool,
+        pattern: &'a str,
+        #[serde(borrow)]
+        services: Vec<Service<'a>>,
+    },
     /// The server sends this in response to `SELF_FETCH`.
     ///
     /// ### Notes
@@ -143,6 +366,21 @@ pub enum PayloadOut<'a> {
         #[serde(borrow)]
         subscriptions: Vec<Service<'a>>,
     },
+    /// The server sends this in response to `TIME_SYNC`.
+    ///
+    /// ### Notes
+    /// `client_time` is echoed from the request, and `server_receive` and
+    /// `server_transmit` are the times at which the server received the
+    /// request and sent this, in milliseconds since the Unix epoch. With
+    /// `client_receive` the time at which the client receives this, the offset
+    /// of the server clock is `((server_receive - client_time) + (server_transmit
+    /// - client_receive)) / 2`, and the round-trip time is `(client_receive -
+    /// client_time) - (server_transmit - server_receive)`.
+    TimeSyncResult {
+        client_time: f64,
+        server_receive: f64,
+        server_transmit: f64,
+    },
     /// The server sends this in response to `SERVICE_CREATE`.
     ///
     /// The payload contains a `successful` boolean flag that indicates
@@ -172,13 +410,25 @@ pub enum PayloadOut<'a> {
         #[serde(borrow)]
         service: Service<'a>,
     },
+    /// The server sends this in response to `SERVICE_FETCH_SCHEMAS`.
+    ///
+    /// ### Notes
+    /// Schemas that the service did not register are missing.
+    ServiceFetchSchemasResult {
+        service: ServiceId<'a>,
+        schemas: ServiceSchemas,
+    },
     /// The server sends this in response to `SERVICE_FETCH_ALL`.
     ///
     /// ### Notes
     /// The services are available as an array of service information objects.
+    /// If more services remain after the limit, `next` is the cursor to
+    /// fetch the following page with.
     ServiceFetchAllResult {
         #[serde(borrow)]
         services: Vec<Service<'a>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        next: Option<&'a str>,
     },
     /// The server sends this in response to `CLIENT_FETCH`.
     ///
@@ -195,9 +445,13 @@ pub enum PayloadOut<'a> {
     ///
     /// ### Notes
     /// The clients are available as an array of client information objects.
+    /// If more clients remain after the limit, `next` is the cursor to
+    /// fetch the following page with.
     ClientFetchAllResult {
         #[serde(borrow)]
         clients: Vec<Client<'a>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        next: Option<&'a str>,
     },
     /// A payload broadcasted whenever a new client joins.
     ClientJoined {
@@ -209,6 +463,17 @@ pub enum PayloadOut<'a> {
         #[serde(borrow)]
         client: Client<'a>,
     },
+    /// A payload broadcasted whenever a client updates its nickname,
+    /// tags or presence. This is also sent in response to `SELF_UPDATE`.
+    ///
+    /// ### Notes
+    /// If the concierge has many clients connected, this payload is only
+    /// broadcasted to the clients of the services that the updated client
+    /// is subscribed to.
+    ClientUpdated {
+        #[serde(borrow)]
+        client: Client<'a>,
+    },
     /// A payload broadcasted to the service when a new client subscribes
     /// to the service.
     ///
@@ -233,6 +498,21 @@ pub enum PayloadOut<'a> {
         #[serde(borrow)]
         service: Service<'a>,
     },
+    /// The server sends this about a `MESSAGE` with `queue` set, targeting a
+    /// client by name while it is offline. It is sent in response to the
+    /// message once queued, then unsequenced once delivered or expired.
+    ///
+    /// ### Notes
+    /// `message_seq` and `message_id` are the sequence number and id of the
+    /// `MESSAGE`, so that the later statuses can be matched with it.
+    MessageStatus {
+        name: &'a str,
+        status: DeliveryStatus,
+        message_seq: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none", borrow)]
+        #[ts(optional)]
+        message_id: Option<RequestId<'a>>,
+    },
     /// Internal error payload.
     ErrorInternal { desc: &'a str },
     /// Indicates that the "type" of the incoming payload is not supported.
@@ -240,17 +520,56 @@ pub enum PayloadOut<'a> {
     /// Indicates that the concierge has failed to decode the payload
     /// due to some reason (as reported by `serde`).
     ErrorProtocol { desc: &'a str },
+    /// Indicates that the data of a message does not satisfy the JSON Schema
+    /// registered by the service. The `path` is a JSON pointer to the offending
+    /// value within the data.
+    ErrorSchema { path: &'a str, desc: &'a str },
     /// Indicates that no such name exists in the namespace of the conciergee.
     InvalidName { name: &'a str },
     /// Indicates that the Uuid is unrecognized by the concierge.
-    InvalidUuid { uuid: Uuid },
-    /// Indicates that the service name is not registered with the concierge.
+    InvalidUuid {
+        #[ts(as = "crate::typescript::Uuid")]
+        uuid: Uuid,
+    },
+    /// Indicates that the service name is not registered with the concierge,
+    /// or that it is not a valid service name or pattern.
     InvalidService { service: ServiceId<'a> },
+    /// An error in response to a payload of the client, sent instead of the
+    /// legacy error payloads if the client negotiated `Capability::Errors`.
+    Error {
+        /// Stable cause of the error.
+        code: ErrorCode,
+        /// Human readable description, which may change between versions.
+        #[serde(borrow)]
+        desc: Cow<'a, str>,
+        /// The name, uuid, service or JSON pointer that the error refers to,
+        /// depending on the `code`.
+        #[serde(rename = "ref", default, borrow, skip_serializing_if = "Option::is_none")]
+        #[ts(optional)]
+        reference: Option<Cow<'a, str>>,
+        /// Additional data of the error, depending on the `code`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        #[ts(optional)]
+        details: Option<Value>,
+    },
 }
 
 impl<'a> PayloadOut<'a> {
     pub const fn seq(self, seq: usize) -> SequencedPayloadOut<'a> {
-        SequencedPayloadOut { seq, payload: self }
+        SequencedPayloadOut {
+            seq,
+            id: None,
+            payload: self,
+        }
+    }
+
+    /// Sequence the payload as the response to a payload of the client.
+    pub fn reply(self, reply: Reply<'a>) -> SequencedPayloadOut<'a> {
+        SequencedPayloadOut {
+            seq: reply.seq,
+            id: reply.id.cloned(),
+            payload: self,
+        }
     }
 
     pub const fn self_subscribe_result(successful: bool, service: Service<'_>) -> PayloadOut {
@@ -292,24 +611,110 @@ impl<'a> PayloadOut<'a> {
         PayloadOut::ServiceClientUnsubscribed { client, service }
     }
 
-    pub const fn error_internal(desc: &str) -> PayloadOut {
-        PayloadOut::ErrorInternal { desc }
+    /// An error of that code, referring to `reference`.
+    pub fn error(
+        code: ErrorCode,
+        desc: impl Into<Cow<'a, str>>,
+        reference: Option<Cow<'a, str>>,
+    ) -> Self {
+        PayloadOut::Error {
+            code,
+            desc: desc.into(),
+            reference,
+            details: None,
+        }
+    }
+
+    pub fn error_internal(desc: &str) -> PayloadOut {
+        PayloadOut::error(ErrorCode::Internal, desc, None)
+    }
+
+    pub fn error_unsupported() -> PayloadOut<'static> {
+        PayloadOut::error(ErrorCode::Unsupported, "Unsupported payload", None)
+    }
+
+    pub fn error_protocol(desc: &str) -> PayloadOut {
+        PayloadOut::error(ErrorCode::Protocol, desc, None)
+    }
+
+    pub fn error_schema<'b>(path: &'b str, desc: &'b str) -> PayloadOut<'b> {
+        PayloadOut::error(ErrorCode::Schema, desc, Some(path.into()))
+    }
+
+    pub fn invalid_name(name: &str) -> PayloadOut {
+        PayloadOut::error(ErrorCode::InvalidName, "No client by that name", Some(name.into()))
+    }
+
+    pub fn invalid_uuid(uuid: Uuid) -> PayloadOut<'static> {
+        PayloadOut::error(
+            ErrorCode::InvalidUuid,
+            "No client by that uuid",
+            Some(uuid.to_string().into()),
+        )
     }
 
-    pub const fn error_protocol(desc: &str) -> PayloadOut {
-        PayloadOut::ErrorProtocol { desc }
+    pub fn invalid_group(service: &str) -> PayloadOut {
+        PayloadOut::error(ErrorCode::InvalidService, "No service by that name", Some(service.into()))
     }
 
-    pub const fn invalid_name(name: &str) -> PayloadOut {
-        PayloadOut::InvalidName { name }
+    pub fn invalid_pattern(pattern: &str) -> PayloadOut<'_> {
+        PayloadOut::error(
+            ErrorCode::InvalidService,
+            "Not a valid service name or pattern",
+            Some(pattern.into()),
+        )
     }
 
-    pub const fn invalid_uuid(uuid: Uuid) -> PayloadOut<'static> {
-        PayloadOut::InvalidUuid { uuid }
+    pub fn not_subscribed(service: &str) -> PayloadOut<'_> {
+        PayloadOut::error(
+            ErrorCode::NotSubscribed,
+            "Not subscribed to the service",
+            Some(service.into()),
+        )
     }
 
-    pub const fn invalid_group(service: &str) -> PayloadOut {
-        PayloadOut::InvalidService { service }
+    pub fn not_owner(service: &str) -> PayloadOut<'_> {
+        PayloadOut::error(ErrorCode::NotOwner, "Not the owner of the service", Some(service.into()))
+    }
+
+    pub fn queue_full(name: &str) -> PayloadOut<'_> {
+        PayloadOut::error(
+            ErrorCode::QueueFull,
+            "The queue of the offline client is full",
+            Some(name.into()),
+        )
+    }
+
+    /// The legacy payload of an `Error`, as sent to clients that did not
+    /// negotiate `Capability::Errors`. Other payloads have no legacy form.
+    pub fn legacy(&self) -> Option<PayloadOut<'_>> {
+        let (code, desc, reference) = match self {
+            PayloadOut::Error {
+                code,
+                desc,
+                reference,
+                ..
+            } => (*code, desc.as_ref(), reference.as_deref().unwrap_or_default()),
+            _ => return None,
+        };
+        Some(match code {
+            ErrorCode::Protocol => PayloadOut::ErrorProtocol { desc },
+            ErrorCode::Unsupported => PayloadOut::ErrorUnsupported,
+            ErrorCode::Internal | ErrorCode::Unknown => PayloadOut::ErrorInternal { desc },
+            ErrorCode::InvalidName | ErrorCode::QueueFull => {
+                PayloadOut::InvalidName { name: reference }
+            }
+            ErrorCode::InvalidUuid => match reference.parse() {
+                Ok(uuid) => PayloadOut::InvalidUuid { uuid },
+                Err(_) => PayloadOut::Bad,
+            },
+            ErrorCode::InvalidService => PayloadOut::InvalidService { service: reference },
+            ErrorCode::NotSubscribed | ErrorCode::NotOwner => PayloadOut::Bad,
+            ErrorCode::Schema => PayloadOut::ErrorSchema {
+                path: reference,
+                desc,
+            },
+        })
     }
 }
 
@@ -317,11 +722,50 @@ impl<'a> PayloadOut<'a> {
 /// are in response to a payload from the client. This may not always be
 /// applicable since payloads are sometimes sent automatically and not in
 /// response to an input from the socket.
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, TS, JsonSchema, Clone, Debug)]
+#[ts(optional_fields)]
 pub struct SequencedPayloadOut<'a> {
     /// Sequence number.
     seq: usize,
+    /// Id of the payload that this responds to, if the client chose one.
+    #[serde(default, skip_serializing_if = "Option::is_none", borrow)]
+    id: Option<RequestId<'a>>,
     /// The actual payload object.
     #[serde(flatten, borrow)]
     payload: PayloadOut<'a>,
 }
+
+/// Identifier that the client chooses for one of its payloads. The concierge
+/// echoes it in the `id` field of the single sequenced payload that responds.
+#[derive(Serialize, Deserialize, TS, JsonSchema, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum RequestId<'a> {
+    Number(#[ts(type = "number")] u64),
+    // Borrowed from the JSON text, unless the id contains escapes.
+    String(#[serde(borrow)] Cow<'a, str>),
+}
+
+/// A payload from the client, along with the id that the client chose for it.
+///
+/// Every payload accepts an `id`, so this only adds the field to `payload`.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct Request<'a, T> {
+    #[serde(default, skip_serializing_if = "Option::is_none", borrow)]
+    pub id: Option<RequestId<'a>>,
+    #[serde(flatten)]
+    pub payload: T,
+}
+
+impl<'a, T> Request<'a, T> {
+    pub const fn new(id: Option<RequestId<'a>>, payload: T) -> Self {
+        Self { id, payload }
+    }
+}
+
+/// What a payload from the server responds to: the sequence number of the
+/// client's payload, along with its id.
+#[derive(Copy, Clone, Debug)]
+pub struct Reply<'a> {
+    pub seq: usize,
+    pub id: Option<&'a RequestId<'a>>,
+}
@@ -0,0 +1,56 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+fn default_true() -> bool {
+    true
+}
+
+/// Field projection for client information objects.
+#[derive(Serialize, Deserialize, TS, JsonSchema, Copy, Clone, Debug)]
+pub struct ClientProjection {
+    /// Include the tags of each client.
+    #[serde(default = "default_true")]
+    #[ts(as = "Option<bool>", optional)]
+    pub tags: bool,
+    /// Include the latency of each client, once measured.
+    #[serde(default)]
+    #[ts(as = "Option<bool>", optional)]
+    pub latency: bool,
+}
+
+impl Default for ClientProjection {
+    fn default() -> Self {
+        ClientProjection {
+            tags: true,
+            latency: false,
+        }
+    }
+}
+
+/// Representation of the subscribers of a service.
+#[derive(Serialize, Deserialize, TS, JsonSchema, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SubscribersProjection {
+    /// The full list of subscriber uuids, as `subscribers`.
+    List,
+    /// Only the number of subscribers, as `subscriber_count`.
+    Count,
+    /// Neither the list nor the number of subscribers.
+    Omit,
+}
+
+impl Default for SubscribersProjection {
+    fn default() -> Self {
+        SubscribersProjection::List
+    }
+}
+
+/// Field projection for service information objects.
+#[derive(Serialize, Deserialize, TS, JsonSchema, Copy, Clone, Debug, Default)]
+pub struct ServiceProjection {
+    /// Representation of the subscribers of each service.
+    #[serde(default)]
+    #[ts(as = "Option<SubscribersProjection>", optional)]
+    pub subscribers: SubscribersProjection,
+}
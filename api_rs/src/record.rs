@@ -0,0 +1,75 @@
+//! Recordings of the frames that the concierge exchanges with its clients.
+//!
+//! A recording holds one JSON `Entry` per line, in the order that the
+//! concierge handled them:
+//!
+//! ```text
+//! {"time":1520,"conn":0,"kind":"OPEN"}
+//! {"time":1873,"conn":0,"kind":"IN","text":"{\"type\":\"IDENTIFY\",...}"}
+//! {"time":2011,"conn":0,"kind":"OUT","text":"{\"type\":\"HELLO\",...}"}
+//! {"time":9052,"conn":0,"kind":"CLOSE","code":1000}
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// A recorded frame, or a change of state of a connection.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Entry<'a> {
+    /// Microseconds since the recording started.
+    pub time: u64,
+    /// Connection of the entry, numbered in order of connection.
+    pub conn: u64,
+    #[serde(flatten, borrow)]
+    pub event: Event<'a>,
+}
+
+/// What happened on a connection.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Event<'a> {
+    /// The connection was opened.
+    Open,
+    /// A text frame sent by the client.
+    In {
+        #[serde(borrow)]
+        text: Cow<'a, str>,
+    },
+    /// A text frame sent by the concierge.
+    Out {
+        #[serde(borrow)]
+        text: Cow<'a, str>,
+    },
+    /// The connection was closed, with the code of its close frame if any.
+    Close {
+        #[serde(default)]
+        code: Option<u16>,
+    },
+}
+
+impl Entry<'_> {
+    /// Clone the entry, owning its text.
+    pub fn owned(&self) -> Entry<'static> {
+        Entry {
+            time: self.time,
+            conn: self.conn,
+            event: self.event.owned(),
+        }
+    }
+}
+
+impl Event<'_> {
+    /// Clone the event, owning its text.
+    pub fn owned(&self) -> Event<'static> {
+        match self {
+            Event::Open => Event::Open,
+            Event::In { text } => Event::In {
+                text: Cow::Owned(text.to_string()),
+            },
+            Event::Out { text } => Event::Out {
+                text: Cow::Owned(text.to_string()),
+            },
+            Event::Close { code } => Event::Close { code: *code },
+        }
+    }
+}
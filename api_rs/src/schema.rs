@@ -0,0 +1,558 @@
+//! Validation of message data against [JSON Schemas](https://json-schema.org/).
+//!
+//! Only the structural subset of draft 7 is supported: `type`, `enum`, `const`,
+//! `properties`, `required`, `additionalProperties`, `items`, `minItems`,
+//! `maxItems`, `minimum`, `maximum`, `exclusiveMinimum`, `exclusiveMaximum`,
+//! `minLength`, `maxLength`, `allOf`, `anyOf`, `oneOf`, `not` and `$ref` to
+//! JSON pointers within the schema (such as `#/definitions/Client`), along
+//! with the annotations `$schema`, `$comment`, `title`, `description`,
+//! `default`, `examples`, `format`, `readOnly`, `writeOnly` and
+//! `definitions`. Schemas with other keywords, or keywords with values of the
+//! wrong type, are rejected by [`check`], rather than validating less than
+//! they claim.
+//!
+//! References may be recursive, but validation gives up with an error once
+//! subschemas nest deeper than [`MAX_DEPTH`] or more than [`MAX_STEPS`] of
+//! them are evaluated, so that cyclic references such as `{"$ref": "#"}`
+//! can not exhaust the stack or keep the validation running.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+};
+use ts_rs::TS;
+
+/// Schemas that a service registers for the data of its messages.
+#[derive(Serialize, Deserialize, TS, JsonSchema, Clone, Debug, Default)]
+pub struct ServiceSchemas {
+    /// Schema for the data of messages sent to the service by its subscribers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inbound: Option<Value>,
+    /// Schema for the data of messages sent by the owner of the service.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outbound: Option<Value>,
+}
+
+/// A failed validation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SchemaError {
+    /// JSON pointer to the offending value.
+    pub path: String,
+    /// Description of the failure.
+    pub desc: String,
+}
+
+impl SchemaError {
+    fn new(path: &str, desc: impl Into<String>) -> Self {
+        Self {
+            path: path.to_owned(),
+            desc: desc.into(),
+        }
+    }
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at \"{}\")", self.desc, self.path)
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+/// Maximum nesting of subschemas evaluated for a value.
+pub const MAX_DEPTH: usize = 256;
+/// Maximum number of subschemas evaluated for a value.
+pub const MAX_STEPS: usize = 100_000;
+
+/// Keywords that are validated.
+const KEYWORDS: &[&str] = &[
+    "type",
+    "enum",
+    "const",
+    "properties",
+    "required",
+    "additionalProperties",
+    "items",
+    "minItems",
+    "maxItems",
+    "minimum",
+    "maximum",
+    "exclusiveMinimum",
+    "exclusiveMaximum",
+    "minLength",
+    "maxLength",
+    "allOf",
+    "anyOf",
+    "oneOf",
+    "not",
+    "$ref",
+];
+
+/// Keywords that do not take part in validation.
+const ANNOTATIONS: &[&str] = &[
+    "$schema",
+    "$comment",
+    "title",
+    "description",
+    "default",
+    "examples",
+    "format",
+    "readOnly",
+    "writeOnly",
+    "definitions",
+];
+
+/// Names accepted by the `type` keyword.
+const TYPES: &[&str] = &[
+    "null", "boolean", "object", "array", "number", "integer", "string",
+];
+
+/// Check that a value can be used as a schema, which must be an object or a
+/// boolean, and only use the supported keywords with values of the expected
+/// type in it and its subschemas.
+pub fn check(schema: &Value) -> Result<(), SchemaError> {
+    check_subschema(schema, schema, "")
+}
+
+fn check_subschema(root: &Value, schema: &Value, path: &str) -> Result<(), SchemaError> {
+    let schema = match schema {
+        Value::Object(schema) => schema,
+        Value::Bool(_) => return Ok(()),
+        _ => {
+            return Err(SchemaError::new(
+                path,
+                "Schema must be an object or a boolean",
+            ))
+        }
+    };
+
+    for (keyword, value) in schema {
+        let path = format!("{}/{}", path, escape(keyword));
+        match keyword.as_str() {
+            "properties" | "definitions" => match value {
+                Value::Object(schemas) => {
+                    for (key, schema) in schemas {
+                        check_subschema(root, schema, &format!("{}/{}", path, escape(key)))?;
+                    }
+                }
+                _ => return Err(SchemaError::new(&path, "Expected an object of schemas")),
+            },
+            "allOf" | "anyOf" | "oneOf" => match value {
+                Value::Array(schemas) => {
+                    for (index, schema) in schemas.iter().enumerate() {
+                        check_subschema(root, schema, &format!("{}/{}", path, index))?;
+                    }
+                }
+                _ => return Err(SchemaError::new(&path, "Expected an array of schemas")),
+            },
+            "items" => match value {
+                Value::Array(schemas) => {
+                    for (index, schema) in schemas.iter().enumerate() {
+                        check_subschema(root, schema, &format!("{}/{}", path, index))?;
+                    }
+                }
+                schema => check_subschema(root, schema, &path)?,
+            },
+            "additionalProperties" | "not" => check_subschema(root, value, &path)?,
+            "$ref" => match value
+                .as_str()
+                .and_then(|reference| resolve(root, reference))
+            {
+                Some(Value::Object(_)) | Some(Value::Bool(_)) => {}
+                _ => {
+                    return Err(SchemaError::new(
+                        &path,
+                        format!("Unresolved reference {}", value),
+                    ))
+                }
+            },
+            "type" => {
+                let is_type = |name: &Value| name.as_str().map_or(false, |n| TYPES.contains(&n));
+                let valid = match value {
+                    Value::Array(names) => !names.is_empty() && names.iter().all(is_type),
+                    name => is_type(name),
+                };
+                if !valid {
+                    return Err(SchemaError::new(
+                        &path,
+                        format!("Expected a type name or an array of them, found {}", value),
+                    ));
+                }
+            }
+            "enum" if !value.is_array() => {
+                return Err(SchemaError::new(&path, "Expected an array of values"))
+            }
+            "required" => match value {
+                Value::Array(keys) if keys.iter().all(Value::is_string) => {}
+                _ => return Err(SchemaError::new(&path, "Expected an array of strings")),
+            },
+            "minItems" | "maxItems" | "minLength" | "maxLength" if !value.is_u64() => {
+                return Err(SchemaError::new(&path, "Expected a non-negative integer"))
+            }
+            "minimum" | "maximum" | "exclusiveMinimum" | "exclusiveMaximum"
+                if !value.is_number() =>
+            {
+                return Err(SchemaError::new(&path, "Expected a number"))
+            }
+            keyword if KEYWORDS.contains(&keyword) || ANNOTATIONS.contains(&keyword) => {}
+            keyword => {
+                return Err(SchemaError::new(
+                    &path,
+                    format!("Unsupported keyword \"{}\"", keyword),
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Validate a value against a schema.
+pub fn validate(schema: &Value, value: &Value) -> Result<(), SchemaError> {
+    let validator = Validator {
+        root: schema,
+        depth: Cell::new(0),
+        steps: Cell::new(0),
+        aborted: RefCell::new(None),
+    };
+    let result = validator.validate(schema, value, "");
+    // Combinators must not turn an aborted validation into a success.
+    match validator.aborted.into_inner() {
+        Some(err) => Err(err),
+        None => result,
+    }
+}
+
+/// Resolve a reference of the form `#/json/pointer` against the root schema.
+fn resolve<'s>(root: &'s Value, reference: &str) -> Option<&'s Value> {
+    if reference.starts_with('#') {
+        root.pointer(&reference[1..])
+    } else {
+        None
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn is_type(value: &Value, name: &str) -> bool {
+    match (name, type_name(value)) {
+        ("number", "integer") => true,
+        ("integer", "number") => value.as_f64().map_or(false, |n| n.fract() == 0.0),
+        (name, actual) => name == actual,
+    }
+}
+
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Validation state, which resolves references against the root schema.
+struct Validator<'s> {
+    root: &'s Value,
+    /// Nesting of the subschema being evaluated.
+    depth: Cell<usize>,
+    /// Number of subschemas evaluated so far.
+    steps: Cell<usize>,
+    /// Error that ended the validation early.
+    aborted: RefCell<Option<SchemaError>>,
+}
+
+impl Validator<'_> {
+    fn validate(&self, schema: &Value, value: &Value, path: &str) -> Result<(), SchemaError> {
+        if let Some(err) = self.aborted.borrow().as_ref() {
+            return Err(err.clone());
+        }
+        if self.depth.get() >= MAX_DEPTH || self.steps.get() >= MAX_STEPS {
+            let err = SchemaError::new(path, "Schema is too deep or complex to validate");
+            *self.aborted.borrow_mut() = Some(err.clone());
+            return Err(err);
+        }
+
+        self.depth.set(self.depth.get() + 1);
+        self.steps.set(self.steps.get() + 1);
+        let result = self.validate_subschema(schema, value, path);
+        self.depth.set(self.depth.get() - 1);
+        result
+    }
+
+    fn validate_subschema(
+        &self,
+        schema: &Value,
+        value: &Value,
+        path: &str,
+    ) -> Result<(), SchemaError> {
+        let schema = match schema {
+            Value::Bool(true) => return Ok(()),
+            Value::Bool(false) => return Err(SchemaError::new(path, "No value is allowed")),
+            Value::Object(schema) => schema,
+            _ => return Ok(()),
+        };
+
+        // As in draft 7, other keywords next to a reference are ignored.
+        if let Some(Value::String(reference)) = schema.get("$ref") {
+            return match resolve(self.root, reference) {
+                Some(schema) => self.validate(schema, value, path),
+                None => Err(SchemaError::new(
+                    path,
+                    format!("Unresolved reference {}", reference),
+                )),
+            };
+        }
+
+        self.validate_type(schema, value, path)?;
+        self.validate_value(schema, value, path)?;
+        self.validate_combinators(schema, value, path)?;
+        match value {
+            Value::Object(object) => self.validate_object(schema, object, path),
+            Value::Array(array) => self.validate_array(schema, array, path),
+            Value::Number(_) => self.validate_number(schema, value, path),
+            Value::String(string) => self.validate_string(schema, string, path),
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_type(
+        &self,
+        schema: &Map<String, Value>,
+        value: &Value,
+        path: &str,
+    ) -> Result<(), SchemaError> {
+        let matches = match schema.get("type") {
+            Some(Value::String(name)) => is_type(value, name),
+            Some(Value::Array(names)) => names
+                .iter()
+                .filter_map(Value::as_str)
+                .any(|name| is_type(value, name)),
+            _ => true,
+        };
+
+        if matches {
+            Ok(())
+        } else {
+            Err(SchemaError::new(
+                path,
+                format!(
+                    "Expected type {}, found {}",
+                    schema["type"],
+                    type_name(value)
+                ),
+            ))
+        }
+    }
+
+    fn validate_value(
+        &self,
+        schema: &Map<String, Value>,
+        value: &Value,
+        path: &str,
+    ) -> Result<(), SchemaError> {
+        if let Some(Value::Array(values)) = schema.get("enum") {
+            if !values.contains(value) {
+                return Err(SchemaError::new(
+                    path,
+                    "Value is not one of the enumerated values",
+                ));
+            }
+        }
+        if let Some(constant) = schema.get("const") {
+            if constant != value {
+                return Err(SchemaError::new(
+                    path,
+                    format!("Expected constant {}", constant),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_combinators(
+        &self,
+        schema: &Map<String, Value>,
+        value: &Value,
+        path: &str,
+    ) -> Result<(), SchemaError> {
+        if let Some(Value::Array(schemas)) = schema.get("allOf") {
+            for schema in schemas {
+                self.validate(schema, value, path)?;
+            }
+        }
+        if let Some(Value::Array(schemas)) = schema.get("anyOf") {
+            if !schemas
+                .iter()
+                .any(|schema| self.validate(schema, value, path).is_ok())
+            {
+                return Err(SchemaError::new(path, "Value matches none of anyOf"));
+            }
+        }
+        if let Some(Value::Array(schemas)) = schema.get("oneOf") {
+            let count = schemas
+                .iter()
+                .filter(|schema| self.validate(schema, value, path).is_ok())
+                .count();
+            if count != 1 {
+                return Err(SchemaError::new(
+                    path,
+                    format!("Value matches {} of oneOf instead of exactly one", count),
+                ));
+            }
+        }
+        if let Some(schema) = schema.get("not") {
+            if self.validate(schema, value, path).is_ok() {
+                return Err(SchemaError::new(path, "Value matches not"));
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_object(
+        &self,
+        schema: &Map<String, Value>,
+        object: &Map<String, Value>,
+        path: &str,
+    ) -> Result<(), SchemaError> {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for key in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(key) {
+                    return Err(SchemaError::new(
+                        path,
+                        format!("Missing required property \"{}\"", key),
+                    ));
+                }
+            }
+        }
+
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (key, value) in object {
+            let child_path = format!("{}/{}", path, escape(key));
+            match properties.and_then(|properties| properties.get(key)) {
+                Some(schema) => self.validate(schema, value, &child_path)?,
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => {
+                        return Err(SchemaError::new(
+                            &child_path,
+                            format!("Unexpected property \"{}\"", key),
+                        ))
+                    }
+                    Some(schema) => self.validate(schema, value, &child_path)?,
+                    None => {}
+                },
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_array(
+        &self,
+        schema: &Map<String, Value>,
+        array: &[Value],
+        path: &str,
+    ) -> Result<(), SchemaError> {
+        if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+            if (array.len() as u64) < min {
+                return Err(SchemaError::new(
+                    path,
+                    format!("Expected at least {} items", min),
+                ));
+            }
+        }
+        if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+            if (array.len() as u64) > max {
+                return Err(SchemaError::new(
+                    path,
+                    format!("Expected at most {} items", max),
+                ));
+            }
+        }
+
+        match schema.get("items") {
+            Some(Value::Array(schemas)) => {
+                for (index, (schema, value)) in schemas.iter().zip(array).enumerate() {
+                    self.validate(schema, value, &format!("{}/{}", path, index))?;
+                }
+            }
+            Some(schema) => {
+                for (index, value) in array.iter().enumerate() {
+                    self.validate(schema, value, &format!("{}/{}", path, index))?;
+                }
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    fn validate_number(
+        &self,
+        schema: &Map<String, Value>,
+        value: &Value,
+        path: &str,
+    ) -> Result<(), SchemaError> {
+        let number = value.as_f64().unwrap_or_default();
+        let bound = |keyword: &str| schema.get(keyword).and_then(Value::as_f64);
+
+        if let Some(min) = bound("minimum") {
+            if number < min {
+                return Err(SchemaError::new(path, format!("Expected at least {}", min)));
+            }
+        }
+        if let Some(max) = bound("maximum") {
+            if number > max {
+                return Err(SchemaError::new(path, format!("Expected at most {}", max)));
+            }
+        }
+        if let Some(min) = bound("exclusiveMinimum") {
+            if number <= min {
+                return Err(SchemaError::new(
+                    path,
+                    format!("Expected more than {}", min),
+                ));
+            }
+        }
+        if let Some(max) = bound("exclusiveMaximum") {
+            if number >= max {
+                return Err(SchemaError::new(
+                    path,
+                    format!("Expected less than {}", max),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_string(
+        &self,
+        schema: &Map<String, Value>,
+        string: &str,
+        path: &str,
+    ) -> Result<(), SchemaError> {
+        let length = string.chars().count() as u64;
+        if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+            if length < min {
+                return Err(SchemaError::new(
+                    path,
+                    format!("Expected at least {} characters", min),
+                ));
+            }
+        }
+        if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+            if length > max {
+                return Err(SchemaError::new(
+                    path,
+                    format!("Expected at most {} characters", max),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+//! Service names are hierarchical, with levels separated by `/`, such as
+//! `physics/room1/state`. Subscriptions can use MQTT-like wildcard patterns:
+//! `+` matches exactly one level, and `#` matches every remaining level.
+
+/// Separator between the levels of a service name.
+pub const SEPARATOR: char = '/';
+/// Wildcard level that matches exactly one level.
+pub const SINGLE_WILDCARD: &str = "+";
+/// Wildcard level that matches every remaining level.
+pub const MULTI_WILDCARD: &str = "#";
+
+/// Check if a name contains wildcards, which makes it a pattern.
+pub fn is_pattern(name: &str) -> bool {
+    name.contains(|c| c == '+' || c == '#')
+}
+
+/// Check if a pattern is well-formed. Wildcards must occupy an entire level,
+/// and the multi-level wildcard can only be the last level.
+pub fn is_valid_pattern(pattern: &str) -> bool {
+    let mut levels = pattern.split(SEPARATOR).peekable();
+    while let Some(level) = levels.next() {
+        match level {
+            SINGLE_WILDCARD => {}
+            MULTI_WILDCARD if levels.peek().is_none() => {}
+            level if is_pattern(level) => return false,
+            _ => {}
+        }
+    }
+    true
+}
+
+/// Check if a service name matches a pattern.
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let mut names = name.split(SEPARATOR);
+    for level in pattern.split(SEPARATOR) {
+        match level {
+            MULTI_WILDCARD => return true,
+            SINGLE_WILDCARD => {
+                if names.next().is_none() {
+                    return false;
+                }
+            }
+            level => {
+                if names.next() != Some(level) {
+                    return false;
+                }
+            }
+        }
+    }
+    names.next().is_none()
+}
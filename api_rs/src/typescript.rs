@@ -0,0 +1,115 @@
+//! TypeScript definitions of the payloads, for web clients.
+//!
+//! The definitions are derived from the Rust types with `ts-rs` and rendered
+//! into a single declaration file. The copies committed to `babylonjs-web`
+//! are checked by the tests, and `UPDATE_TYPESCRIPT=1 cargo test` rewrites
+//! them when they are stale.
+
+use crate::{payload::SequencedPayloadOut, CloseReason, PayloadIn, PayloadMessage, PayloadOut};
+use serde_json::Value;
+use std::{collections::BTreeMap, io, path::Path};
+use ts_rs::{TypeVisitor, TS};
+
+/// Environment variable that lets `Declarations::verify` rewrite stale files.
+pub const UPDATE_VAR: &str = "UPDATE_TYPESCRIPT";
+
+const HEADER: &str =
+    "// This file is generated from the Rust definitions. Do not edit it by hand,\n\
+     // run `UPDATE_TYPESCRIPT=1 cargo test` to regenerate it instead.\n";
+
+/// Uuids are transmitted as strings. They are branded in TypeScript
+/// so that arbitrary strings are not mistaken for them.
+#[derive(TS)]
+#[ts(type = "string & { readonly __is_uuid: never }")]
+pub struct Uuid;
+
+/// A TypeScript declaration file. Types are declared in order of their
+/// names, since the order in which `ts-rs` visits them is not stable.
+#[derive(Default)]
+pub struct Declarations {
+    types: BTreeMap<String, String>,
+    sources: String,
+}
+
+impl Declarations {
+    /// Create an empty declaration file.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare a type, along with every type that it depends on.
+    pub fn with<T: TS + ?Sized + 'static>(mut self) -> Self {
+        self.declare::<T>();
+        self
+    }
+
+    /// Append TypeScript source verbatim, for definitions
+    /// that do not correspond to a Rust type.
+    pub fn with_source(mut self, source: &str) -> Self {
+        self.sources.push('\n');
+        self.sources.push_str(source);
+        self
+    }
+
+    fn declare<T: TS + ?Sized + 'static>(&mut self) {
+        // Primitives and wrappers can not be declared. Generic types
+        // are declared once, regardless of their parameters.
+        if T::output_path().is_none() || self.types.contains_key(&T::ident()) {
+            return;
+        }
+
+        let mut declaration = T::docs().unwrap_or_default();
+        declaration.push_str("export ");
+        declaration.push_str(&T::decl());
+        self.types.insert(T::ident(), declaration);
+
+        T::visit_dependencies(self);
+    }
+
+    /// Render the declaration file.
+    pub fn render(&self) -> String {
+        let mut rendered = String::from(HEADER);
+        for declaration in self.types.values() {
+            rendered.push('\n');
+            rendered.push_str(declaration);
+            rendered.push('\n');
+        }
+        rendered.push_str(&self.sources);
+        rendered
+    }
+
+    /// Check if the file at `path` is up to date. If it is stale and
+    /// the `UPDATE_TYPESCRIPT` environment variable is set, the file is
+    /// rewritten and considered up to date.
+    pub fn verify(&self, path: impl AsRef<Path>) -> io::Result<bool> {
+        crate::generated::verify(path.as_ref(), &self.render(), UPDATE_VAR)
+    }
+}
+
+impl TypeVisitor for Declarations {
+    fn visit<T: TS + 'static + ?Sized>(&mut self) {
+        self.declare::<T>();
+    }
+}
+
+/// Declarations of every payload of the concierge, and of its close reasons.
+pub fn api() -> Declarations {
+    let mut close_reasons = String::from(
+        "/** Close reasons, as used by the central server. */\n\
+         export declare const enum CloseReason {\n",
+    );
+    for (name, reason) in CloseReason::ALL.iter() {
+        close_reasons.push_str(&format!(
+            "    /** {} */\n    {} = {},\n",
+            reason.reason, name, reason.code
+        ));
+    }
+    close_reasons.push_str("}\n");
+
+    Declarations::new()
+        .with::<PayloadIn>()
+        .with::<PayloadOut>()
+        .with::<SequencedPayloadOut>()
+        .with::<PayloadMessage<Value>>()
+        .with_source(&close_reasons)
+}
@@ -0,0 +1,184 @@
+//! Webhooks, through which the concierge notifies HTTP endpoints of events.
+//!
+//! Every event is posted as a JSON `Delivery`, such as:
+//!
+//! ```text
+//! {"id":"...","time":1598918400000,"event":"CLIENT_JOINED","client":{"name":"alice",...}}
+//! ```
+//!
+//! The body is signed with the secret of the webhook: the `SIGNATURE_HEADER`
+//! holds `sha256=` followed by the hex-encoded HMAC-SHA256 of the body.
+//! Deliveries that fail are retried, so endpoints should deduplicate them
+//! by their `id`.
+
+use crate::info::{Client, Service};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use uuid::Uuid;
+
+/// Header holding the signature of a delivery.
+pub const SIGNATURE_HEADER: &str = "x-concierge-signature";
+/// Header holding the event of a delivery.
+pub const EVENT_HEADER: &str = "x-concierge-event";
+
+/// Events that webhooks can be registered for.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum WebhookEvent {
+    /// A client identified.
+    ClientJoined,
+    /// A client disconnected.
+    ClientLeft,
+    /// A service was created.
+    ServiceCreated,
+    /// A service was deleted.
+    ServiceDeleted,
+    /// A client subscribed to a service.
+    ServiceSubscribed,
+    /// A client unsubscribed from a service, or left while subscribed.
+    ServiceUnsubscribed,
+    /// A client uploaded a file.
+    FileUploaded,
+}
+
+impl WebhookEvent {
+    /// Events regarding a service, which its owner can register webhooks for.
+    pub const SERVICE: [WebhookEvent; 3] = [
+        WebhookEvent::ServiceDeleted,
+        WebhookEvent::ServiceSubscribed,
+        WebhookEvent::ServiceUnsubscribed,
+    ];
+}
+
+/// What happened, along with the client or service it happened to.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "event", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum WebhookPayload<'a> {
+    ClientJoined {
+        #[serde(borrow)]
+        client: Client<'a>,
+    },
+    ClientLeft {
+        #[serde(borrow)]
+        client: Client<'a>,
+    },
+    ServiceCreated {
+        #[serde(borrow)]
+        service: Service<'a>,
+    },
+    ServiceDeleted {
+        #[serde(borrow)]
+        service: Service<'a>,
+    },
+    ServiceSubscribed {
+        #[serde(borrow)]
+        client: Client<'a>,
+        #[serde(borrow)]
+        service: Service<'a>,
+    },
+    ServiceUnsubscribed {
+        #[serde(borrow)]
+        client: Client<'a>,
+        #[serde(borrow)]
+        service: Service<'a>,
+    },
+    FileUploaded {
+        #[serde(borrow)]
+        client: Client<'a>,
+        /// Name of the file, under the files of the client.
+        #[serde(borrow)]
+        file: Cow<'a, str>,
+    },
+}
+
+impl WebhookPayload<'_> {
+    /// The event of the payload.
+    pub fn event(&self) -> WebhookEvent {
+        match self {
+            WebhookPayload::ClientJoined { .. } => WebhookEvent::ClientJoined,
+            WebhookPayload::ClientLeft { .. } => WebhookEvent::ClientLeft,
+            WebhookPayload::ServiceCreated { .. } => WebhookEvent::ServiceCreated,
+            WebhookPayload::ServiceDeleted { .. } => WebhookEvent::ServiceDeleted,
+            WebhookPayload::ServiceSubscribed { .. } => WebhookEvent::ServiceSubscribed,
+            WebhookPayload::ServiceUnsubscribed { .. } => WebhookEvent::ServiceUnsubscribed,
+            WebhookPayload::FileUploaded { .. } => WebhookEvent::FileUploaded,
+        }
+    }
+
+    /// Name of the service that the payload regards, if any.
+    pub fn service(&self) -> Option<&str> {
+        match self {
+            WebhookPayload::ServiceCreated { service }
+            | WebhookPayload::ServiceDeleted { service }
+            | WebhookPayload::ServiceSubscribed { service, .. }
+            | WebhookPayload::ServiceUnsubscribed { service, .. } => Some(&service.name),
+            _ => None,
+        }
+    }
+}
+
+/// Body posted to webhooks.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Delivery<'a> {
+    /// Id of the event, which is the same across retries and webhooks.
+    pub id: Uuid,
+    /// Milliseconds since the Unix epoch at which the event happened.
+    pub time: u64,
+    #[serde(flatten, borrow)]
+    pub payload: WebhookPayload<'a>,
+}
+
+/// Request to register a webhook.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Registration<'a> {
+    /// URL that deliveries are posted to.
+    #[serde(borrow)]
+    pub url: Cow<'a, str>,
+    /// Events that are delivered.
+    pub events: Vec<WebhookEvent>,
+    /// Only deliver the events regarding this service. Required for
+    /// webhooks registered by the owner of the service.
+    #[serde(default, borrow, skip_serializing_if = "Option::is_none")]
+    pub service: Option<Cow<'a, str>>,
+}
+
+impl Registration<'_> {
+    /// Uncouple the registration from the original borrowed lifetime
+    /// and return a struct that has fully owned references.
+    pub fn owned(&self) -> Registration<'static> {
+        Registration {
+            url: Cow::Owned(self.url.to_string()),
+            events: self.events.clone(),
+            service: self.service.as_deref().map(str::to_string).map(Cow::Owned),
+        }
+    }
+}
+
+/// A registered webhook.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Webhook<'a> {
+    pub id: Uuid,
+    #[serde(borrow)]
+    pub url: Cow<'a, str>,
+    pub events: Vec<WebhookEvent>,
+    #[serde(default, borrow, skip_serializing_if = "Option::is_none")]
+    pub service: Option<Cow<'a, str>>,
+    /// Secret with which deliveries are signed. Only included in the
+    /// response to the registration.
+    #[serde(default, borrow, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<Cow<'a, str>>,
+}
+
+impl Webhook<'_> {
+    /// Uncouple the webhook from the original borrowed lifetime
+    /// and return a struct that has fully owned references.
+    pub fn owned(&self) -> Webhook<'static> {
+        Webhook {
+            id: self.id,
+            url: Cow::Owned(self.url.to_string()),
+            events: self.events.clone(),
+            service: self.service.as_deref().map(str::to_string).map(Cow::Owned),
+            secret: self.secret.as_deref().map(str::to_string).map(Cow::Owned),
+        }
+    }
+}
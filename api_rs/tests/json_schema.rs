@@ -0,0 +1,296 @@
+use concierge_api_rs::{
+    info::{Client, Latency, Origin, Presence, Service, Status},
+    json_schema, schema, Capability, ErrorCode, PayloadIn, PayloadMessage, PayloadOut, Reply,
+    Request, RequestId, Target,
+};
+use serde_json::{json, Value};
+use std::{borrow::Cow, path::Path};
+use uuid::Uuid;
+
+/// The protocol schema, validating against one of its definitions.
+fn definition(name: &str) -> Value {
+    let mut schema = json_schema::protocol();
+    schema["$ref"] = json!(format!("#/definitions/{}", name));
+    schema
+}
+
+fn client() -> Client<'static> {
+    Client {
+        name: Cow::Borrowed("alice"),
+        nickname: None,
+        uuid: Uuid::new_v4(),
+        tags: Some(vec![Cow::Borrowed("viewer")]),
+        presence: Presence {
+            status: Status::Away,
+            custom: Some(Cow::Owned(json!({ "activity": "reading" }))),
+        },
+        latency: Some(Latency {
+            rtt: 12.5,
+            jitter: 1.25,
+        }),
+    }
+}
+
+fn service() -> Service<'static> {
+    Service {
+        name: Cow::Borrowed("physics_engine"),
+        nickname: Some(Cow::Borrowed("Physics Engine")),
+        owner_uuid: Uuid::new_v4(),
+        subscribers: Some(vec![Uuid::new_v4()]),
+        subscriber_count: None,
+    }
+}
+
+#[test]
+fn protocol_schema_is_up_to_date() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../docs/protocol.schema.json");
+    assert!(
+        json_schema::verify(&path).unwrap(),
+        "{} is stale, run `{}=1 cargo test` to regenerate it",
+        path.display(),
+        json_schema::UPDATE_VAR
+    );
+}
+
+#[test]
+fn schema_accepts_what_serde_accepts() {
+    let schema = definition("PayloadIn");
+    let samples = [
+        r#"{"type":"IDENTIFY","name":"alice","version":"0.2.0","tags":["viewer"]}"#,
+        r#"{"type":"IDENTIFY","name":"alice","version":"0.2.0","secret":null,"events":["CLIENT_JOINED"]}"#,
+        r#"{"type":"IDENTIFY","name":"alice","version":"0.3.0","capabilities":["BATCHING","RESUME"]}"#,
+        r#"{"type":"SELF_SUBSCRIBE","service":"chat.*","filter":{"op":"RANGE","pointer":"/x","min":0}}"#,
+        r#"{"type":"SELF_FETCH"}"#,
+        r#"{"type":"SELF_FETCH","id":"fetch-1"}"#,
+        r#"{"type":"SELF_SET_SEQ","seq":3,"id":4}"#,
+        r#"{"type":"SELF_UPDATE","presence":{"status":"BUSY","custom":[1,2]}}"#,
+        r#"{"type":"SERVICE_CREATE","service":"chat","schemas":{"inbound":{"type":"string"}}}"#,
+        r#"{"type":"SERVICE_FETCH_ALL","limit":10,"fields":{"subscribers":"COUNT"}}"#,
+        r#"{"type":"CLIENT_FETCH","uuid":"a9b4c1a0-7b3c-4e34-9f41-0c8a1f27d8e1"}"#,
+        r#"{"type":"CLIENT_FETCH_ALL","tag":"viewer","fields":{"tags":false}}"#,
+        r#"{"type":"CLIENT_FETCH_ALL","fields":{"latency":true}}"#,
+        r#"{"type":"TIME_SYNC","client_time":1600000000000.5,"id":1}"#,
+    ];
+
+    for sample in samples.iter() {
+        serde_json::from_str::<PayloadIn>(sample)
+            .unwrap_or_else(|err| panic!("serde rejected {}: {}", sample, err));
+        let value: Value = serde_json::from_str(sample).unwrap();
+        if let Err(err) = schema::validate(&schema, &value) {
+            panic!("schema rejected {}: {}", sample, err);
+        }
+    }
+}
+
+#[test]
+fn schema_rejects_what_serde_rejects() {
+    let schema = definition("PayloadIn");
+    let samples = [
+        r#"{"type":"NOT_A_PAYLOAD"}"#,
+        r#"{"type":"IDENTIFY","name":"alice"}"#,
+        r#"{"type":"SELF_SET_SEQ","seq":-1}"#,
+        r#"{"type":"SELF_SET_EVENTS","events":["NOT_AN_EVENT"]}"#,
+        r#"{"type":"SELF_SUBSCRIBE","service":"chat","filter":{"op":"EQ","pointer":"/x"}}"#,
+        r#"{"type":"CLIENT_FETCH","name":"alice"}"#,
+        r#"{"type":"TIME_SYNC","client_time":"now"}"#,
+        r#"{"name":"alice","version":"0.2.0"}"#,
+    ];
+
+    for sample in samples.iter() {
+        assert!(
+            serde_json::from_str::<PayloadIn>(sample).is_err(),
+            "serde accepted {}",
+            sample
+        );
+        let value: Value = serde_json::from_str(sample).unwrap();
+        assert!(
+            schema::validate(&schema, &value).is_err(),
+            "schema accepted {}",
+            sample
+        );
+    }
+}
+
+#[test]
+fn schema_accepts_what_serde_produces() {
+    let uuid = Uuid::new_v4();
+    let payloads = vec![
+        PayloadOut::Ok,
+        PayloadOut::Hello {
+            uuid,
+            version: "0.2.0",
+            capabilities: vec![Capability::Batching],
+        },
+        PayloadOut::self_subscribe_result(true, service()),
+        PayloadOut::SelfFetchResult {
+            client: client(),
+            subscriptions: vec![service()],
+        },
+        PayloadOut::ServiceFetchAllResult {
+            services: vec![service()],
+            next: Some("physics_engine"),
+        },
+        PayloadOut::ClientJoined { client: client() },
+        PayloadOut::service_client_subscribed(client(), service()),
+        PayloadOut::error_schema("/x", "Expected type \"number\", found string"),
+        PayloadOut::invalid_uuid(uuid),
+        PayloadOut::TimeSyncResult {
+            client_time: 1_600_000_000_000.5,
+            server_receive: 1_600_000_000_010.25,
+            server_transmit: 1_600_000_000_010.5,
+        },
+        PayloadOut::Error {
+            code: ErrorCode::Schema,
+            desc: Cow::Borrowed("Expected type \"number\", found string"),
+            reference: Some(Cow::Borrowed("/x")),
+            details: Some(json!({ "expected": "number" })),
+        },
+        PayloadOut::ErrorUnsupported,
+        PayloadOut::Bad,
+    ];
+
+    let payload_out = definition("PayloadOut");
+    let sequenced = definition("SequencedPayloadOut");
+    let any = json_schema::protocol();
+    for (seq, payload) in payloads.into_iter().enumerate() {
+        let value = serde_json::to_value(&payload).unwrap();
+        schema::validate(&payload_out, &value)
+            .unwrap_or_else(|err| panic!("schema rejected {}: {}", value, err));
+        schema::validate(&any, &value)
+            .unwrap_or_else(|err| panic!("schema rejected {}: {}", value, err));
+
+        let value = serde_json::to_value(payload.clone().seq(seq)).unwrap();
+        schema::validate(&sequenced, &value)
+            .unwrap_or_else(|err| panic!("schema rejected {}: {}", value, err));
+
+        let id = if seq % 2 == 0 {
+            RequestId::Number(seq as u64)
+        } else {
+            RequestId::String(Cow::Borrowed("request"))
+        };
+        let value = serde_json::to_value(payload.reply(Reply { seq, id: Some(&id) })).unwrap();
+        schema::validate(&sequenced, &value)
+            .unwrap_or_else(|err| panic!("schema rejected {}: {}", value, err));
+    }
+
+    let schema = definition("PayloadMessage");
+    let targets = vec![
+        Target::Name { name: "alice" },
+        Target::Uuid { uuid },
+        Target::Service {
+            service: "physics_engine",
+        },
+        Target::ServiceClientUuid {
+            service: "physics_engine",
+            uuid,
+        },
+        Target::All,
+    ];
+    for target in targets {
+        let message = PayloadMessage::new(target, json!({ "type": "FETCH_ENTITIES" }));
+        let value = serde_json::to_value(&message).unwrap();
+        schema::validate(&schema, &value)
+            .unwrap_or_else(|err| panic!("schema rejected {}: {}", value, err));
+
+        let origin = Origin {
+            client: client(),
+            service: Some(service()),
+            received: Some(1_600_000_000_000.5),
+        };
+        let value = serde_json::to_value(message.with_origin(origin)).unwrap();
+        schema::validate(&schema, &value)
+            .unwrap_or_else(|err| panic!("schema rejected {}: {}", value, err));
+    }
+}
+
+#[test]
+fn request_ids_are_read_from_any_payload() {
+    let samples = [
+        (
+            r#"{"type":"SELF_FETCH","id":7}"#,
+            Some(RequestId::Number(7)),
+        ),
+        (
+            r#"{"id":"a","type":"SERVICE_FETCH","service":"chat"}"#,
+            Some(RequestId::String(Cow::Borrowed("a"))),
+        ),
+        (
+            r#"{"type":"SELF_FETCH","id":"say \"hi\" \u00e9"}"#,
+            Some(RequestId::String(Cow::Owned("say \"hi\" é".to_owned()))),
+        ),
+        (
+            r#"{"type":"MESSAGE","target":{"type":"ALL"},"data":{"id":3}}"#,
+            None,
+        ),
+    ];
+
+    for (sample, id) in samples.iter() {
+        let request: Request<serde::de::IgnoredAny> = serde_json::from_str(sample).unwrap();
+        assert_eq!(request.id, *id, "{}", sample);
+    }
+
+    // Ids without escapes are not copied.
+    let request: Request<serde::de::IgnoredAny> = serde_json::from_str(samples[1].0).unwrap();
+    assert!(matches!(
+        request.id,
+        Some(RequestId::String(Cow::Borrowed("a")))
+    ));
+
+    let request = Request::new(Some(RequestId::Number(1)), PayloadIn::SelfFetch);
+    let value = serde_json::to_value(&request).unwrap();
+    assert_eq!(value, json!({ "id": 1, "type": "SELF_FETCH" }));
+    schema::validate(&definition("PayloadIn"), &value).unwrap();
+}
+
+#[test]
+fn errors_have_legacy_payloads() {
+    let uuid = Uuid::new_v4();
+    let samples = vec![
+        (
+            PayloadOut::error_protocol("expected value"),
+            json!({ "type": "ERROR_PROTOCOL", "desc": "expected value" }),
+        ),
+        (
+            PayloadOut::error_unsupported(),
+            json!({ "type": "ERROR_UNSUPPORTED" }),
+        ),
+        (
+            PayloadOut::error_schema("/x", "Expected number"),
+            json!({ "type": "ERROR_SCHEMA", "path": "/x", "desc": "Expected number" }),
+        ),
+        (
+            PayloadOut::invalid_name("alice"),
+            json!({ "type": "INVALID_NAME", "name": "alice" }),
+        ),
+        (
+            PayloadOut::invalid_uuid(uuid),
+            json!({ "type": "INVALID_UUID", "uuid": uuid }),
+        ),
+        (
+            PayloadOut::invalid_pattern("chat/**/x"),
+            json!({ "type": "INVALID_SERVICE", "service": "chat/**/x" }),
+        ),
+        (PayloadOut::not_subscribed("chat"), json!({ "type": "BAD" })),
+        (PayloadOut::not_owner("chat"), json!({ "type": "BAD" })),
+    ];
+
+    for (payload, legacy) in samples {
+        assert_eq!(
+            serde_json::to_value(payload.legacy().unwrap()).unwrap(),
+            legacy
+        );
+    }
+    assert!(PayloadOut::Ok.legacy().is_none());
+
+    let value = serde_json::to_value(PayloadOut::not_owner("chat")).unwrap();
+    assert_eq!(value["type"], "ERROR");
+    assert_eq!(value["code"], "NOT_OWNER");
+    assert_eq!(value["ref"], "chat");
+    assert!(value.get("details").is_none());
+
+    let text = r#"{"type":"ERROR","code":"RATE_LIMITED","desc":"Slow down"}"#;
+    match serde_json::from_str(text).unwrap() {
+        PayloadOut::Error { code, .. } => assert_eq!(code, ErrorCode::Unknown),
+        payload => panic!("unexpected payload {:?}", payload),
+    }
+}
@@ -0,0 +1,54 @@
+use concierge_api_rs::record::{Entry, Event};
+use serde_json::json;
+use std::borrow::Cow;
+
+#[test]
+fn entries_round_trip() {
+    let entries = vec![
+        Entry {
+            time: 0,
+            conn: 3,
+            event: Event::Open,
+        },
+        Entry {
+            time: 12,
+            conn: 3,
+            event: Event::In {
+                text: Cow::Borrowed(r#"{"type":"SELF_FETCH"}"#),
+            },
+        },
+        Entry {
+            time: 40,
+            conn: 3,
+            event: Event::Out {
+                text: Cow::Borrowed(r#"{"type":"OK","seq":0}"#),
+            },
+        },
+        Entry {
+            time: 95,
+            conn: 3,
+            event: Event::Close { code: Some(4009) },
+        },
+    ];
+
+    for entry in entries {
+        let line = serde_json::to_string(&entry).unwrap();
+        assert!(!line.contains('\n'));
+        assert_eq!(serde_json::from_str::<Entry>(&line).unwrap(), entry);
+    }
+
+    assert_eq!(
+        serde_json::to_value(Entry {
+            time: 7,
+            conn: 1,
+            event: Event::In {
+                text: Cow::Borrowed("[]"),
+            },
+        })
+        .unwrap(),
+        json!({ "time": 7, "conn": 1, "kind": "IN", "text": "[]" })
+    );
+    // Connections dropped without a close frame have no code.
+    let entry: Entry = serde_json::from_str(r#"{"time":1,"conn":2,"kind":"CLOSE"}"#).unwrap();
+    assert_eq!(entry.event, Event::Close { code: None });
+}
@@ -0,0 +1,161 @@
+use concierge_api_rs::{
+    json_schema,
+    schema::{self, SchemaError},
+};
+use serde_json::json;
+
+#[test]
+fn supported_schemas_are_accepted() {
+    let schema = json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "Roll",
+        "type": "object",
+        "required": ["sides"],
+        "properties": {
+            "sides": { "type": "integer", "format": "uint8", "minimum": 2, "description": "Sides of the die" },
+            "tags": { "type": "array", "items": { "$ref": "#/definitions/Tag" } },
+        },
+        "additionalProperties": false,
+        "definitions": {
+            "Tag": { "anyOf": [{ "type": "string", "maxLength": 16 }, { "not": true }] },
+        },
+    });
+    assert_eq!(schema::check(&schema), Ok(()));
+    assert_eq!(schema::check(&json!(true)), Ok(()));
+}
+
+#[test]
+fn generated_schemas_are_accepted() {
+    assert_eq!(schema::check(&json_schema::protocol()), Ok(()));
+}
+
+#[test]
+fn unsupported_keywords_are_rejected() {
+    let cases = [
+        (json!({ "type": "string", "pattern": "^a" }), "/pattern"),
+        (
+            json!({ "properties": { "id": { "type": "number", "multipleOf": 2 } } }),
+            "/properties/id/multipleOf",
+        ),
+        (
+            json!({ "patternProperties": { "^x-": true } }),
+            "/patternProperties",
+        ),
+        (json!({ "dependencies": { "a": ["b"] } }), "/dependencies"),
+        (
+            json!({ "allOf": [{ "if": { "type": "string" }, "then": false }] }),
+            "/allOf/0/if",
+        ),
+        (
+            json!({ "items": [true, { "uniqueItems": true }] }),
+            "/items/1/uniqueItems",
+        ),
+        (
+            json!({ "definitions": { "a/b": { "multipleOf": 2 } } }),
+            "/definitions/a~1b/multipleOf",
+        ),
+    ];
+
+    for (schema, path) in &cases {
+        match schema::check(schema) {
+            Err(SchemaError { path: actual, desc }) => {
+                assert_eq!(&actual, path, "{}", schema);
+                assert!(desc.starts_with("Unsupported keyword"), "{}", desc);
+            }
+            Ok(()) => panic!("{} was accepted", schema),
+        }
+    }
+}
+
+#[test]
+fn malformed_subschemas_are_rejected() {
+    let cases = [
+        (json!(5), ""),
+        (json!({ "not": "string" }), "/not"),
+        (json!({ "properties": [] }), "/properties"),
+        (json!({ "anyOf": { "type": "string" } }), "/anyOf"),
+        (
+            json!({ "properties": { "a": { "additionalProperties": null } } }),
+            "/properties/a/additionalProperties",
+        ),
+    ];
+
+    for (schema, path) in &cases {
+        let err = schema::check(schema).expect_err("Malformed schema");
+        assert_eq!(&err.path, path, "{}", schema);
+    }
+}
+
+#[test]
+fn mistyped_keywords_are_rejected() {
+    let cases = [
+        (json!({ "type": "strng" }), "/type"),
+        (json!({ "type": ["string", 5] }), "/type"),
+        (json!({ "type": [] }), "/type"),
+        (json!({ "required": 5 }), "/required"),
+        (json!({ "required": ["a", null] }), "/required"),
+        (json!({ "enum": "a" }), "/enum"),
+        (json!({ "items": { "minItems": -1 } }), "/items/minItems"),
+        (json!({ "maxLength": 1.5 }), "/maxLength"),
+        (json!({ "anyOf": [{ "minimum": "0" }] }), "/anyOf/0/minimum"),
+    ];
+
+    for (schema, path) in &cases {
+        let err = schema::check(schema).expect_err("Malformed schema");
+        assert_eq!(&err.path, path, "{}", schema);
+    }
+}
+
+#[test]
+fn unresolved_references_are_rejected() {
+    let cases = [
+        (json!({ "$ref": "#/definitions/Missing" }), "/$ref"),
+        (json!({ "$ref": "other.json" }), "/$ref"),
+        (json!({ "not": { "$ref": 5 } }), "/not/$ref"),
+        (json!({ "$ref": "#/title", "title": "Title" }), "/$ref"),
+    ];
+
+    for (schema, path) in &cases {
+        let err = schema::check(schema).expect_err("Unresolved reference");
+        assert_eq!(&err.path, path, "{}", schema);
+    }
+}
+
+#[test]
+fn cyclic_references_are_bounded() {
+    let schemas = [
+        json!({ "$ref": "#" }),
+        json!({ "anyOf": [{ "$ref": "#" }, { "$ref": "#" }] }),
+        json!({ "not": { "$ref": "#" } }),
+        json!({
+            "$ref": "#/definitions/A",
+            "definitions": { "A": { "$ref": "#/definitions/B" }, "B": { "$ref": "#/definitions/A" } },
+        }),
+    ];
+
+    for schema in &schemas {
+        assert_eq!(schema::check(schema), Ok(()));
+        let err = schema::validate(schema, &json!({ "a": 1 })).expect_err("Cyclic reference");
+        assert_eq!(err.path, "");
+    }
+}
+
+#[test]
+fn recursive_references_are_followed() {
+    let schema = json!({
+        "type": "object",
+        "properties": { "children": { "type": "array", "items": { "$ref": "#" } } },
+        "additionalProperties": false,
+    });
+    assert_eq!(schema::check(&schema), Ok(()));
+
+    let mut value = json!({});
+    for _ in 0..32 {
+        value = json!({ "children": [value] });
+    }
+    assert_eq!(schema::validate(&schema, &value), Ok(()));
+
+    let err = schema::validate(&schema, &json!({ "children": [{ "other": 1 }] }))
+        .expect_err("Unexpected property");
+    assert_eq!(err.path, "/children/0/other");
+}
@@ -0,0 +1,14 @@
+use concierge_api_rs::typescript;
+use std::path::Path;
+
+#[test]
+fn typescript_definitions_are_up_to_date() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../babylonjs-web/src/concierge_api/generated.d.ts");
+    assert!(
+        typescript::api().verify(&path).unwrap(),
+        "{} is stale, run `{}=1 cargo test` to regenerate it",
+        path.display(),
+        typescript::UPDATE_VAR
+    );
+}
@@ -0,0 +1,358 @@
+// This file is generated from the Rust definitions. Do not edit it by hand,
+// run `UPDATE_TYPESCRIPT=1 cargo test` to regenerate it instead.
+
+/**
+ * Optional protocol features that a client can request in `IDENTIFY`.
+ * The server replies with the subset that it supports in `HELLO`, and
+ * only the negotiated features may be used on the connection.
+ */
+export type Capability = "BINARY" | "BATCHING" | "COMPRESSION" | "RPC" | "RESUME" | "ERRORS";
+
+/**
+ * A client payload.
+ */
+export type Client = { 
+/**
+ * Client name.
+ */
+name: string, 
+/**
+ * Client name.
+ */
+nickname: string | null, 
+/**
+ * Uuid of the client.
+ */
+uuid: Uuid, 
+/**
+ * Tags of the client. Omitted if excluded by a field projection.
+ */
+tags?: Array<string> | null, 
+/**
+ * Presence of the client.
+ */
+presence: Presence, 
+/**
+ * Latency of the client, once measured. Only present if requested by a field projection.
+ */
+latency?: Latency | null, };
+
+/**
+ * Field projection for client information objects.
+ */
+export type ClientProjection = { 
+/**
+ * Include the tags of each client.
+ */
+tags?: boolean, 
+/**
+ * Include the latency of each client, once measured.
+ */
+latency?: boolean, };
+
+/**
+ * What became of a message queued for an offline client.
+ */
+export type DeliveryStatus = "QUEUED" | "DELIVERED" | "EXPIRED";
+
+/**
+ * Cause of a `PayloadOut::Error`.
+ *
+ * Codes are never renamed nor reused, and new codes may be added, so
+ * clients should expect codes that they do not know.
+ */
+export type ErrorCode = "PROTOCOL" | "UNSUPPORTED" | "INTERNAL" | "INVALID_NAME" | "INVALID_UUID" | "INVALID_SERVICE" | "NOT_SUBSCRIBED" | "NOT_OWNER" | "SCHEMA" | "QUEUE_FULL";
+
+/**
+ * Classes of global events that the concierge broadcasts to every client.
+ * Clients can choose which of these they want to receive.
+ */
+export type EventClass = "CLIENT_JOINED" | "CLIENT_LEFT" | "CLIENT_UPDATED" | "SERVICE_CREATED" | "SERVICE_DELETED";
+
+/**
+ * A predicate on the `data` field of messages, which subscribers can
+ * attach to their subscription. The concierge only relays the messages
+ * of a service to a subscriber if they satisfy its filter.
+ *
+ * Fields are located with [JSON pointers](https://tools.ietf.org/html/rfc6901),
+ * such as `/type` or `/entity/position/0`. A pointer to a missing field
+ * fails every test except `NOT_EXISTS`.
+ */
+export type Filter = { "op": "EQ", pointer: string, value: JsonValue, } | { "op": "NE", pointer: string, value: JsonValue, } | { "op": "IN", pointer: string, values: Array<JsonValue>, } | { "op": "RANGE", pointer: string, min?: number, max?: number, } | { "op": "EXISTS", pointer: string, } | { "op": "NOT_EXISTS", pointer: string, } | { "op": "ALL", filters: Array<Filter>, } | { "op": "ANY", filters: Array<Filter>, } | { "op": "NOT", filter: Filter, };
+
+export type JsonValue = number | string | boolean | Array<JsonValue> | { [key in string]?: JsonValue } | null;
+
+/**
+ * Latency of a client, measured from the heartbeats of its connection.
+ */
+export type Latency = { 
+/**
+ * Smoothed round-trip time, in milliseconds.
+ */
+rtt: number, 
+/**
+ * Smoothed variation of the round-trip time, in milliseconds.
+ */
+jitter: number, };
+
+/**
+ * An origin receipt for certain payloads.
+ */
+export type Origin = { 
+/**
+ * Only available on MESSAGE payloads.
+ * This indicates the service that the message
+ * was originally sent to.
+ */
+service: Service | null, 
+/**
+ * Only available on MESSAGE payloads.
+ * Milliseconds since the Unix epoch at which the server received
+ * the message.
+ */
+received?: number | null, 
+/**
+ * Client name.
+ */
+name: string, 
+/**
+ * Client name.
+ */
+nickname: string | null, 
+/**
+ * Uuid of the client.
+ */
+uuid: Uuid, 
+/**
+ * Tags of the client. Omitted if excluded by a field projection.
+ */
+tags?: Array<string> | null, 
+/**
+ * Presence of the client.
+ */
+presence: Presence, 
+/**
+ * Latency of the client, once measured. Only present if requested by a field projection.
+ */
+latency?: Latency | null, };
+
+/**
+ * `PayloadIn` represents the types of payloads that the central server
+ * is expected to respond to. All payloads are expected to be tagged with
+ * a `type` field indicating it's nature, with the value being in
+ * SCREAMING_SNAKE_CASE.
+ */
+export type PayloadIn = { "type": "IDENTIFY", name: string, nickname?: string, version: string, secret?: string, tags?: Array<string>, events?: Array<EventClass>, capabilities?: Array<Capability>, } | { "type": "SELF_SUBSCRIBE", service: string, filter?: Filter, } | { "type": "SELF_UNSUBSCRIBE", service: string, } | { "type": "SELF_FETCH" } | { "type": "SELF_SET_SEQ", seq: number, } | { "type": "SELF_SET_EVENTS", events: Array<EventClass>, } | { "type": "SELF_UPDATE", nickname?: string, tags?: Array<string>, presence?: Presence, } | { "type": "TIME_SYNC", client_time: number, } | { "type": "SERVICE_CREATE", service: string, nickname?: string, schemas?: ServiceSchemas, } | { "type": "SERVICE_DELETE", service: string, } | { "type": "SERVICE_FETCH", service: string, } | { "type": "SERVICE_FETCH_SCHEMAS", service: string, } | { "type": "SERVICE_FETCH_ALL", name_prefix?: string, owner_uuid?: Uuid, cursor?: string, limit?: number, fields?: ServiceProjection, } | { "type": "CLIENT_FETCH", uuid: Uuid, } | { "type": "CLIENT_FETCH_ALL", tag?: string, name_prefix?: string, service?: string, cursor?: string, limit?: number, fields?: ClientProjection, };
+
+/**
+ * These payloads have special fields for targeting other users or services.
+ * The origin fields are ignored if they are sent to the server. The data
+ * is transmitted verbatim.
+ *
+ * ### Responses
+ * * `OK`: Message have been sent.
+ * * `BAD`: Insufficient permission to use this target.
+ * * `INVALID_NAME`: The target client does not exist by that name.
+ * * `INVALID_UUID`: The target client does not exist by that uuid.
+ * * `INVALID_SERVICE`: The service does not exist by that name.
+ * * `ERROR_SCHEMA`: The data does not satisfy the schema of the target service.
+ * * `MESSAGE_STATUS`: The message was queued for an offline client.
+ */
+export type PayloadMessage<T> = { 
+/**
+ * Mimics the "type" tag of Payload. This should always be PayloadRawType::Message.
+ */
+type: PayloadMessageType, 
+/**
+ * Origin of the message.
+ */
+origin?: Origin | null, 
+/**
+ * Target of the message. This can be a single user
+ * (using name or uuid), or a service.
+ */
+target: Target, 
+/**
+ * Data field. This is a raw JSON value field that borrows the JSON
+ * string directly from the deserialized buffer.
+ */
+data: T, 
+/**
+ * Hold the message if the client targeted by name is offline, and
+ * deliver it when that name next identifies. Other targets ignore it.
+ */
+queue?: boolean, };
+
+/**
+ * Constant field for type safe deserialization of messages.
+ */
+export type PayloadMessageType = "MESSAGE";
+
+/**
+ * `PayloadOut` represents the types of payloads sent from the server.
+ * All payloads are expected to be tagged with a `type` field indicating
+ * it's nature, with the value being in SCREAMING_SNAKE_CASE.
+ *
+ * Many of these payloads are also attached with a sequence number.
+ * For more information, see `SequencedPayloadOut`.
+ */
+export type PayloadOut = { "type": "OK" } | { "type": "BAD" } | { "type": "HELLO", uuid: Uuid, version: string, capabilities?: Array<Capability>, } | { "type": "SELF_SUBSCRIBE_RESULT", successful: boolean, service: Service, } | { "type": "SELF_UNSUBSCRIBE_RESULT", successful: boolean, service: Service, } | { "type": "SELF_SUBSCRIBE_PATTERN_RESULT", successful: boolean, pattern: string, services: Array<Service>, } | { "type": "SELF_UNSUBSCRIBE_PATTERN_RESULT", successful: boolean, pattern: string, services: Array<Service>, } | { "type": "SELF_FETCH_RESULT", client: Client, subscriptions: Array<Service>, } | { "type": "TIME_SYNC_RESULT", client_time: number, server_receive: number, server_transmit: number, } | { "type": "SERVICE_CREATE_RESULT", successful: boolean, service: Service, } | { "type": "SERVICE_DELETE_RESULT", service: Service, } | { "type": "SERVICE_FETCH_RESULT", service: Service, } | { "type": "SERVICE_FETCH_SCHEMAS_RESULT", service: string, schemas: ServiceSchemas, } | { "type": "SERVICE_FETCH_ALL_RESULT", services: Array<Service>, next?: string | null, } | { "type": "CLIENT_FETCH_RESULT", client: Client, subscriptions: Array<Service>, } | { "type": "CLIENT_FETCH_ALL_RESULT", clients: Array<Client>, next?: string | null, } | { "type": "CLIENT_JOINED", client: Client, } | { "type": "CLIENT_LEFT", client: Client, } | { "type": "CLIENT_UPDATED", client: Client, } | { "type": "SERVICE_CLIENT_SUBSCRIBED", client: Client, service: Service, } | { "type": "SERVICE_CLIENT_UNSUBSCRIBED", client: Client, service: Service, } | { "type": "MESSAGE_STATUS", name: string, status: DeliveryStatus, message_seq: number, message_id?: RequestId, } | { "type": "ERROR_INTERNAL", desc: string, } | { "type": "ERROR_UNSUPPORTED" } | { "type": "ERROR_PROTOCOL", desc: string, } | { "type": "ERROR_SCHEMA", path: string, desc: string, } | { "type": "INVALID_NAME", name: string, } | { "type": "INVALID_UUID", uuid: Uuid, } | { "type": "INVALID_SERVICE", service: string, } | { "type": "ERROR", 
+/**
+ * Stable cause of the error.
+ */
+code: ErrorCode, 
+/**
+ * Human readable description, which may change between versions.
+ */
+desc: string, 
+/**
+ * The name, uuid, service or JSON pointer that the error refers to,
+ * depending on the `code`.
+ */
+ref?: string, 
+/**
+ * Additional data of the error, depending on the `code`.
+ */
+details?: JsonValue, };
+
+/**
+ * Presence of a client, as set by `SELF_UPDATE`.
+ */
+export type Presence = { 
+/**
+ * Presence status of the client.
+ */
+status?: Status, 
+/**
+ * Arbitrary JSON attached by the client, such as an activity description.
+ */
+custom?: JsonValue | null, };
+
+/**
+ * Identifier that the client chooses for one of its payloads. The concierge
+ * echoes it in the `id` field of the single sequenced payload that responds.
+ */
+export type RequestId = number | string;
+
+/**
+ * In general, payloads from the server will usually be sequenced if they
+ * are in response to a payload from the client. This may not always be
+ * applicable since payloads are sometimes sent automatically and not in
+ * response to an input from the socket.
+ */
+export type SequencedPayloadOut = { 
+/**
+ * Sequence number.
+ */
+seq: number, 
+/**
+ * Id of the payload that this responds to, if the client chose one.
+ */
+id?: RequestId, } & ({ "type": "OK" } | { "type": "BAD" } | { "type": "HELLO", uuid: Uuid, version: string, capabilities?: Array<Capability>, } | { "type": "SELF_SUBSCRIBE_RESULT", successful: boolean, service: Service, } | { "type": "SELF_UNSUBSCRIBE_RESULT", successful: boolean, service: Service, } | { "type": "SELF_SUBSCRIBE_PATTERN_RESULT", successful: boolean, pattern: string, services: Array<Service>, } | { "type": "SELF_UNSUBSCRIBE_PATTERN_RESULT", successful: boolean, pattern: string, services: Array<Service>, } | { "type": "SELF_FETCH_RESULT", client: Client, subscriptions: Array<Service>, } | { "type": "TIME_SYNC_RESULT", client_time: number, server_receive: number, server_transmit: number, } | { "type": "SERVICE_CREATE_RESULT", successful: boolean, service: Service, } | { "type": "SERVICE_DELETE_RESULT", service: Service, } | { "type": "SERVICE_FETCH_RESULT", service: Service, } | { "type": "SERVICE_FETCH_SCHEMAS_RESULT", service: string, schemas: ServiceSchemas, } | { "type": "SERVICE_FETCH_ALL_RESULT", services: Array<Service>, next?: string | null, } | { "type": "CLIENT_FETCH_RESULT", client: Client, subscriptions: Array<Service>, } | { "type": "CLIENT_FETCH_ALL_RESULT", clients: Array<Client>, next?: string | null, } | { "type": "CLIENT_JOINED", client: Client, } | { "type": "CLIENT_LEFT", client: Client, } | { "type": "CLIENT_UPDATED", client: Client, } | { "type": "SERVICE_CLIENT_SUBSCRIBED", client: Client, service: Service, } | { "type": "SERVICE_CLIENT_UNSUBSCRIBED", client: Client, service: Service, } | { "type": "MESSAGE_STATUS", name: string, status: DeliveryStatus, message_seq: number, message_id?: RequestId, } | { "type": "ERROR_INTERNAL", desc: string, } | { "type": "ERROR_UNSUPPORTED" } | { "type": "ERROR_PROTOCOL", desc: string, } | { "type": "ERROR_SCHEMA", path: string, desc: string, } | { "type": "INVALID_NAME", name: string, } | { "type": "INVALID_UUID", uuid: Uuid, } | { "type": "INVALID_SERVICE", service: string, } | { "type": "ERROR", 
+/**
+ * Stable cause of the error.
+ */
+code: ErrorCode, 
+/**
+ * Human readable description, which may change between versions.
+ */
+desc: string, 
+/**
+ * The name, uuid, service or JSON pointer that the error refers to,
+ * depending on the `code`.
+ */
+ref?: string, 
+/**
+ * Additional data of the error, depending on the `code`.
+ */
+details?: JsonValue, });
+
+/**
+ * A service payload.
+ */
+export type Service = { 
+/**
+ * Service name.
+ */
+name: string, 
+/**
+ * Service name.
+ */
+nickname: string | null, 
+/**
+ * Uuid of the service's owner.
+ */
+owner_uuid: Uuid, 
+/**
+ * Subscribers. Omitted if excluded by a field projection.
+ */
+subscribers?: Array<Uuid> | null, 
+/**
+ * Number of subscribers. Only present if requested by a field projection.
+ */
+subscriber_count?: number | null, };
+
+/**
+ * Field projection for service information objects.
+ */
+export type ServiceProjection = { 
+/**
+ * Representation of the subscribers of each service.
+ */
+subscribers?: SubscribersProjection, };
+
+/**
+ * Schemas that a service registers for the data of its messages.
+ */
+export type ServiceSchemas = { 
+/**
+ * Schema for the data of messages sent to the service by its subscribers.
+ */
+inbound?: JsonValue | null, 
+/**
+ * Schema for the data of messages sent by the owner of the service.
+ */
+outbound?: JsonValue | null, };
+
+/**
+ * Presence status of a client.
+ */
+export type Status = "ONLINE" | "AWAY" | "BUSY";
+
+/**
+ * Representation of the subscribers of a service.
+ */
+export type SubscribersProjection = "LIST" | "COUNT" | "OMIT";
+
+/**
+ * Targetting directive for message payloads.
+ */
+export type Target = { "type": "NAME", name: string, } | { "type": "UUID", uuid: Uuid, } | { "type": "SERVICE", service: string, } | { "type": "SERVICE_CLIENT_UUID", service: string, uuid: Uuid, } | { "type": "ALL" };
+
+/**
+ * Uuids are transmitted as strings. They are branded in TypeScript
+ * so that arbitrary strings are not mistaken for them.
+ */
+export type Uuid = string & { readonly __is_uuid: never };
+
+/** Close reasons, as used by the central server. */
+export declare const enum CloseReason {
+    /** Unknown reason */
+    UNKNOWN = 4000,
+    /** Failed to critical payload */
+    FATAL_DECODE = 4002,
+    /** No identification payload sent */
+    NO_AUTH = 4003,
+    /** Authorization failed */
+    AUTH_FAILED = 4004,
+    /** Duplicate name in namespace */
+    DUPLICATE_AUTH = 4005,
+    /** Secret mismatch */
+    BAD_SECRET = 4006,
+    /** Version mismatch */
+    BAD_VERSION = 4007,
+    /** Name must be alphanumeric */
+    BAD_AUTH = 4008,
+    /** Heartbeat failed */
+    HB_FAILED = 4009,
+}
@@ -1,152 +1,97 @@
+import * as Generated from "./generated";
+
 /** Branded string that is assumed to be a valid UUID. */
-export type Uuid = string & { readonly __is_uuid: never };
+export type Uuid = Generated.Uuid;
 
+/**
+ * Payloads of the concierge. The definitions are generated from the
+ * Rust API crate into `generated.d.ts`, and only given names here.
+ */
 export module Payload {
-    interface ServiceField {
-        readonly service: string
-    }
+    export module Info {
+        export type Status = Generated.Status;
+        export type Presence = Generated.Presence;
+        export type Client = Generated.Client;
+        export type Origin = Generated.Origin;
+        export type Service = Generated.Service;
+        export type ClientProjection = Generated.ClientProjection;
+        export type ServiceProjection = Generated.ServiceProjection;
 
-    interface SuccessfulField {
-        readonly successful: boolean
+        export module Targets {
+            export type Any = Generated.Target;
+        }
     }
 
-    interface HasClientInfo {
-        readonly client: Info.Client
-    }
+    export type EventClass = Generated.EventClass;
+    export type Capability = Generated.Capability;
+    export type RequestId = Generated.RequestId;
+    export type ErrorCode = Generated.ErrorCode;
+    export type Filter = Generated.Filter;
+    export type ServiceSchemas = Generated.ServiceSchemas;
 
-    interface HasServiceInfo {
-        readonly service: Info.Service
-    }
+    /** Payload of the union `U` with the type tag `T`. */
+    type Variant<U, T> = Extract<U, { readonly type: T }>;
 
-    export module Info {
-        export interface Client {
-            readonly name: string,
-            readonly nickname?: string,
-            readonly uuid: Uuid,
-            readonly tags: ReadonlyArray<string>,
-        }
-        
-        export interface Origin extends Client {
-            readonly service?: Service,
-        }
-        
-        export interface Service {
-            readonly name: string,
-            readonly nickname?: string,
-            readonly owner_uuid: Uuid,
-            readonly subscribers: ReadonlyArray<Uuid>
-        }
-        
-        export module Targets {
-            export interface BaseTarget<T extends string> {
-                readonly type: T
-            }
-            export interface TargetName extends BaseTarget<"NAME"> {
-                readonly name: string
-            }
-            export interface TargetUuid extends BaseTarget<"UUID"> {
-                readonly uuid: Uuid,
-            }
-            export interface TargetService extends BaseTarget<"SERVICE"> {
-                readonly service: string
-            }
-            export interface TargetServiceClientUuid extends BaseTarget<"SERVICE_CLIENT_UUID"> {
-                readonly service: string,
-                readonly uuid: Uuid,
-            }
-            type TargetAll = BaseTarget<"ALL">;
-        
-            export type Any = TargetName | TargetUuid | TargetService | TargetServiceClientUuid | TargetAll;
-        }
-    }
+    /**
+     * Payloads from the server carry the sequence number of the payload they respond to,
+     * along with its id if the client chose one.
+     */
+    type Response = { readonly seq?: number; readonly id?: RequestId };
 
-    export interface Base<T extends string> {
-        readonly type: T,
-        readonly seq?: number,
-    }
-     
-    export interface Identify extends Base<"IDENTIFY"> {
-        readonly name: string,
-        readonly nickname?: string,
-        readonly version: string,
-        readonly secret?: string,
-        readonly tags?: ReadonlyArray<string>,
-    }
-    export interface Message<T> extends Base<"MESSAGE"> {
-        readonly target: Info.Targets.Any,
-        readonly origin?: Info.Origin,
-        readonly data: T
-    }
-    export type SelfSubscribe = Base<"SELF_SUBSCRIBE"> & ServiceField;
-    export type SelfUnsubscribe = Base<"SELF_UNSUBSCRIBE"> & ServiceField;
-    export interface SelfSetSeq extends Base<"SELF_SET_SEQ"> {
-        readonly seq: number,
-    }
-    export interface ServiceCreate extends Base<"SERVICE_CREATE"> {
-        readonly name: string,
-        readonly nickname?: string,
-    }
-    export type ServiceDelete = Base<"SERVICE_DELETE"> & ServiceField;
-    export type ServiceFetch = Base<"SERVICE_FETCH"> & ServiceField;
-    export type ServiceFetchAll = Base<"SERVICE_FETCH_ALL">;
-    export type ClientFetchAll = Base<"CLIENT_FETCH_ALL">;
-    export type SelfFetch = Base<"SELF_FETCH">;
-    export interface Hello extends Base<"HELLO"> {
-        readonly uuid: Uuid,
-        readonly version: string
-    }
-    export type ServiceFetchResult = Base<"SERVICE_FETCH_RESULT"> & HasServiceInfo;
-    export interface ServiceFetchAllResult extends Base<"SERVICE_FETCH_ALL_RESULT"> {
-        readonly services: ReadonlyArray<Info.Service>
-    }
-    export interface ClientFetchAllResult extends Base<"CLIENT_FETCH_ALL_RESULT"> {
-        readonly clients: ReadonlyArray<Info.Client>
-    }
-    export interface SelfFetchResult extends Base<"SELF_FETCH_RESULT">, HasClientInfo {
-        readonly subscriptions: ReadonlyArray<Info.Client>,
-    }
+    export type Message<T> = Generated.PayloadMessage<T> & Response;
 
-    export type ClientJoined = Base<"CLIENT_JOINED"> & HasClientInfo;
-    export type ClientLeft = Base<"CLIENT_LEFT"> & HasClientInfo;
-    export type Ok = Base<"OK">;
-    export type Subscribed = Base<"SELF_SUBSCRIBE_RESULT"> & HasServiceInfo & SuccessfulField;
-    export type Unsubscribed = Base<"SELF_UNSUBSCRIBE_RESULT"> & HasServiceInfo & SuccessfulField;
-    export type ServiceCreated = Base<"SERVICE_CREATE_RESULT">  & HasServiceInfo & SuccessfulField;
-    export type ServiceDeleted = Base<"SERVICE_DELETE_RESULT"> & HasServiceInfo;
-    export interface ServiceClientSubscribed extends Base<"SERVICE_CLIENT_SUBSCRIBED">, HasClientInfo {
-        service: Info.Service
-    }
-    export interface ServiceClientUnsubscribed extends Base<"SERVICE_CLIENT_UNSUBSCRIBED">, HasClientInfo {
-        service: Info.Service
-    }
-    export type Bad = Base<"BAD">;
-    export type ErrorUnsupported = Base<"ERROR_UNSUPPORTED">;
-    export interface ErrorInternal extends Base<"ERROR_INTERNAL"> {
-        readonly desc: string
-    }
-    export interface ErrorProtocol extends Base<"ERROR_PROTOCOL"> {
-        readonly desc: string
-    }
-    export type ServiceAlreadyCreated = Base<"SERVICE_ALREADY_CREATED"> & HasServiceInfo;
-    export interface InvalidName extends Base<"INVALID_NAME"> {
-        readonly name: string
-    }
-    export interface InvalidUuid extends Base<"INVALID_UUID"> {
-        readonly uuid: Uuid
-    }
-    export type InvalidService = Base<"INVALID_SERVICE"> & ServiceField;
+    type PayloadIn<T extends Generated.PayloadIn["type"]> = Variant<Generated.PayloadIn, T>;
+    export type Identify = PayloadIn<"IDENTIFY">;
+    export type SelfSubscribe = PayloadIn<"SELF_SUBSCRIBE">;
+    export type SelfUnsubscribe = PayloadIn<"SELF_UNSUBSCRIBE">;
+    export type SelfFetch = PayloadIn<"SELF_FETCH">;
+    export type SelfSetSeq = PayloadIn<"SELF_SET_SEQ">;
+    export type SelfSetEvents = PayloadIn<"SELF_SET_EVENTS">;
+    export type SelfUpdate = PayloadIn<"SELF_UPDATE">;
+    export type ServiceCreate = PayloadIn<"SERVICE_CREATE">;
+    export type ServiceDelete = PayloadIn<"SERVICE_DELETE">;
+    export type ServiceFetch = PayloadIn<"SERVICE_FETCH">;
+    export type ServiceFetchSchemas = PayloadIn<"SERVICE_FETCH_SCHEMAS">;
+    export type ServiceFetchAll = PayloadIn<"SERVICE_FETCH_ALL">;
+    export type ClientFetch = PayloadIn<"CLIENT_FETCH">;
+    export type ClientFetchAll = PayloadIn<"CLIENT_FETCH_ALL">;
 
-    export type Out = Message<any> | Ok | Subscribed | Unsubscribed
-        | ServiceCreated | ServiceDeleted | Bad | ErrorUnsupported | ErrorInternal
-        | ErrorProtocol | ServiceAlreadyCreated | InvalidName | InvalidUuid 
-        | InvalidService | ClientJoined | ClientLeft | Hello | ServiceFetchResult 
-        | ServiceFetchAllResult | ClientFetchAllResult | SelfFetchResult;
-    
-    export type In = Message<any> | Identify | SelfSubscribe | SelfUnsubscribe
-        | ServiceCreate | ServiceDelete | ServiceFetch | ClientFetchAll
-        | ServiceFetchAll | SelfFetch;
+    type PayloadOut<T extends Generated.PayloadOut["type"]> =
+        Variant<Generated.PayloadOut, T> & Response;
+    export type Ok = PayloadOut<"OK">;
+    export type Bad = PayloadOut<"BAD">;
+    export type Hello = PayloadOut<"HELLO">;
+    export type Subscribed = PayloadOut<"SELF_SUBSCRIBE_RESULT">;
+    export type Unsubscribed = PayloadOut<"SELF_UNSUBSCRIBE_RESULT">;
+    export type PatternSubscribed = PayloadOut<"SELF_SUBSCRIBE_PATTERN_RESULT">;
+    export type PatternUnsubscribed = PayloadOut<"SELF_UNSUBSCRIBE_PATTERN_RESULT">;
+    export type SelfFetchResult = PayloadOut<"SELF_FETCH_RESULT">;
+    export type ServiceCreated = PayloadOut<"SERVICE_CREATE_RESULT">;
+    export type ServiceDeleted = PayloadOut<"SERVICE_DELETE_RESULT">;
+    export type ServiceFetchResult = PayloadOut<"SERVICE_FETCH_RESULT">;
+    export type ServiceFetchSchemasResult = PayloadOut<"SERVICE_FETCH_SCHEMAS_RESULT">;
+    export type ServiceFetchAllResult = PayloadOut<"SERVICE_FETCH_ALL_RESULT">;
+    export type ClientFetchResult = PayloadOut<"CLIENT_FETCH_RESULT">;
+    export type ClientFetchAllResult = PayloadOut<"CLIENT_FETCH_ALL_RESULT">;
+    export type ClientJoined = PayloadOut<"CLIENT_JOINED">;
+    export type ClientLeft = PayloadOut<"CLIENT_LEFT">;
+    export type ClientUpdated = PayloadOut<"CLIENT_UPDATED">;
+    export type ServiceClientSubscribed = PayloadOut<"SERVICE_CLIENT_SUBSCRIBED">;
+    export type ServiceClientUnsubscribed = PayloadOut<"SERVICE_CLIENT_UNSUBSCRIBED">;
+    export type MessageStatus = PayloadOut<"MESSAGE_STATUS">;
+    export type Error = PayloadOut<"ERROR">;
+    export type ErrorInternal = PayloadOut<"ERROR_INTERNAL">;
+    export type ErrorUnsupported = PayloadOut<"ERROR_UNSUPPORTED">;
+    export type ErrorProtocol = PayloadOut<"ERROR_PROTOCOL">;
+    export type ErrorSchema = PayloadOut<"ERROR_SCHEMA">;
+    export type InvalidName = PayloadOut<"INVALID_NAME">;
+    export type InvalidUuid = PayloadOut<"INVALID_UUID">;
+    export type InvalidService = PayloadOut<"INVALID_SERVICE">;
 
+    export type Out = Message<any> | (Generated.PayloadOut & Response);
+    /** Every payload to the server may carry an id, which is echoed on its response. */
+    export type In = (Message<any> | Generated.PayloadIn) & { id?: RequestId };
     export type Any = In | Out;
 }
 
-export default Payload;
\ No newline at end of file
+export default Payload;
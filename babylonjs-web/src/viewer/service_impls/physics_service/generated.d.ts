@@ -0,0 +1,19 @@
+// This file is generated from the Rust definitions. Do not edit it by hand,
+// run `UPDATE_TYPESCRIPT=1 cargo test` to regenerate it instead.
+
+export type EntityDump = { id: Uuid, color: [number, number, number], centroid: Vec2<number>, points: Array<Vec2<number>>, };
+
+export type EntityUpdate = { id: Uuid, position: Vec2<number>, };
+
+export type PhysicsPayload = { "type": "FETCH_ENTITIES" } | { "type": "FETCH_POSITIONS" } | { "type": "SPAWN_ENTITY" } | { "type": "TOGGLE_COLOR", id: Uuid, } | { "type": "TOUCH_ENTITY", id: Uuid, } | { "type": "ENTITY_DUMP", entities: Array<EntityDump>, } | { "type": "ENTITY_NEW", entity: EntityDump, } | { "type": "ENTITY_DELETE", ids: Array<Uuid>, } | { "type": "POSITION_DUMP", updates: Array<EntityUpdate>, } | { "type": "COLOR_UPDATE", id: Uuid, color: [number, number, number], };
+
+/**
+ * Uuids are transmitted as strings. They are branded in TypeScript
+ * so that arbitrary strings are not mistaken for them.
+ */
+export type Uuid = string & { readonly __is_uuid: never };
+
+/**
+ * A 2-dimensional vector, holding 2 values of the same type.
+ */
+export type Vec2<T> = { x: T, y: T, };
@@ -1,7 +1,7 @@
 import Client, { ServiceEventHandler } from "../../../concierge_api/mod";
 import { Vector2, Color3, ExecuteCodeAction, Vector3, Scene, PolygonMeshBuilder, StandardMaterial, ActionManager, Mesh } from "babylonjs";
 import { RendererView } from "../../renderer";
-import { Vec2f, RgbColor, PhysicsPayload } from "./payloads";
+import { Vec2f, RgbColor, PhysicsPayload, Uuid } from "./payloads";
 import { PhysicsComponent as PhysicsControllerComponent } from "./components";
 import React from "react";
 import { Tabbed } from "../../../overlay/mod";
@@ -89,7 +89,7 @@ export class RustPhysicsService extends ServiceEventHandler<PhysicsPayload> {
         this.shapes.clear();
     }
 
-    private createShape(id: string, centroid: Vec2f, points: ReadonlyArray<Vec2f>, color: Readonly<RgbColor>) {
+    private createShape(id: Uuid, centroid: Vec2f, points: ReadonlyArray<Vec2f>, color: Readonly<RgbColor>) {
         const centroidv = vec2f2vector2(centroid);
         const pointsv = points.map(vec2f2vector2);
         const color3 = tuple2color3(color);
@@ -115,7 +115,7 @@ export class RustPhysicsService extends ServiceEventHandler<PhysicsPayload> {
         );
     }
 
-    private removeShape(id: string) {
+    private removeShape(id: Uuid) {
         const shape = this.shapes.get(id);
         if (shape) {
             shape.dispose();
@@ -123,7 +123,7 @@ export class RustPhysicsService extends ServiceEventHandler<PhysicsPayload> {
         }
     }
 
-    private updateShape(id: string, centroid: Vec2f) {
+    private updateShape(id: Uuid, centroid: Vec2f) {
         const shape = this.shapes.get(id);
         if (shape) {
             const vector2 = vec2f2vector2(centroid);
@@ -131,7 +131,7 @@ export class RustPhysicsService extends ServiceEventHandler<PhysicsPayload> {
         }
     }
 
-    private updateColor(id: string, color: Readonly<RgbColor>) {
+    private updateColor(id: Uuid, color: Readonly<RgbColor>) {
         const shape = this.shapes.get(id);
         if (shape) {
             shape.setColor(tuple2color3(color));
@@ -19,6 +19,7 @@ class UserComponent extends React.PureComponent<Payload.Info.Client> {
             {this.innerRender()}
             <Rs.InputValue label="UUID" inputs={[{ value: this.props.uuid }]} />
             <Rs.InputValue label="Tags" inputs={[{ value: this.props.tags }]} />
+            <Rs.InputValue label="Status" inputs={[{ value: this.props.presence.status }]} />
         </Rs.Card>;
     }
 }
@@ -47,6 +47,9 @@ export class ClientsHandler implements RawHandler {
             case "CLIENT_LEFT":
                 this.removeUser(payload.client.uuid);
                 break;
+            case "CLIENT_UPDATED":
+                this.updateClient(payload.client);
+                break;
         }
     }
 
@@ -60,6 +63,17 @@ export class ClientsHandler implements RawHandler {
         this.render()
     }
 
+    updateClient(client: Payload.Info.Client) {
+        for (let i = 0; i < this.users.length; i++) {
+            if (this.users[i].uuid == client.uuid) {
+                this.users[i] = client;
+            }
+        }
+        this.ui.removeIcon(client.uuid);
+        this.ui.addIcon(client.uuid, client.nickname || client.name);
+        this.render()
+    }
+
     removeUser(uuid: string) {
         this.ui.removeIcon(uuid);
         for (let i = 0; i < this.users.length; i++) {
@@ -0,0 +1,311 @@
+use crate::{Client, ClientBuilder, Connection, Error, Frame, Result, ServiceMessage};
+use async_trait::async_trait;
+use concierge_api_rs::{info, schema::ServiceSchemas, PayloadIn, PayloadOut, Target};
+use futures::{future, stream, Future, StreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::mpsc::unbounded_channel;
+use uuid::Uuid;
+
+/// Callbacks of a service hosted by a client, which are run by a `ServiceRunner`.
+///
+/// Callbacks are awaited one at a time, in the order of the payloads that
+/// trigger them.
+#[async_trait]
+pub trait ServiceBot: Send + Sync + 'static {
+    /// Data of the messages that subscribers send to the service.
+    type Message: DeserializeOwned + Send;
+
+    /// A client subscribed to the service.
+    async fn on_subscribe(&self, _service: &ServiceHandle, _client: &info::Client<'static>) {}
+
+    /// A client unsubscribed from the service. This is also called for every
+    /// subscriber when the connection is lost, since the concierge removes
+    /// the service along with its owner.
+    async fn on_unsubscribe(&self, _service: &ServiceHandle, _client: &info::Client<'static>) {}
+
+    /// A subscriber sent a message to the service.
+    async fn on_message(
+        &self,
+        service: &ServiceHandle,
+        origin: &info::Client<'static>,
+        data: Self::Message,
+    );
+
+    /// Called periodically while the service exists, if the runner ticks.
+    async fn on_tick(&self, _service: &ServiceHandle) {}
+}
+
+/// Handle to a service hosted by the client, which can be cloned freely.
+#[derive(Clone)]
+pub struct ServiceHandle {
+    client: Client,
+    name: Arc<str>,
+    subscribers: Arc<Mutex<HashMap<Uuid, info::Client<'static>>>>,
+}
+
+impl ServiceHandle {
+    /// The client that hosts the service.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Name of the service.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Clients currently subscribed to the service, excluding its owner.
+    pub fn subscribers(&self) -> Vec<info::Client<'static>> {
+        self.lock_subscribers().values().cloned().collect()
+    }
+
+    /// Send a message to every subscriber of the service.
+    pub fn broadcast(&self, data: &impl Serialize) -> Result<()> {
+        self.client.message(
+            Target::Service {
+                service: &self.name,
+            },
+            data,
+        )
+    }
+
+    /// Send a message to a single subscriber of the service.
+    pub fn send_to(&self, uuid: Uuid, data: &impl Serialize) -> Result<()> {
+        self.client.message(
+            Target::ServiceClientUuid {
+                service: &self.name,
+                uuid,
+            },
+            data,
+        )
+    }
+
+    fn lock_subscribers(&self) -> std::sync::MutexGuard<'_, HashMap<Uuid, info::Client<'static>>> {
+        self.subscribers.lock().expect("Subscribers lock")
+    }
+}
+
+enum Event {
+    Connected,
+    Disconnected,
+    Frame(Frame),
+    Tick,
+    Closed,
+}
+
+/// Hosts a `ServiceBot` on a client.
+///
+/// The runner creates the service and subscribes its owner to it upon every
+/// (re)connection, keeps track of the subscribers, and deserializes the
+/// messages sent to the service into `ServiceBot::Message`.
+pub struct ServiceRunner {
+    builder: ClientBuilder,
+    definition: Definition,
+    tick: Option<Duration>,
+}
+
+/// What the runner creates the service with.
+struct Definition {
+    name: String,
+    nickname: Option<String>,
+    schemas: ServiceSchemas,
+}
+
+impl ServiceRunner {
+    /// Create a runner for the service of that name, hosted by the client
+    /// that `builder` connects.
+    pub fn new(builder: ClientBuilder, name: impl Into<String>) -> Self {
+        Self {
+            builder,
+            definition: Definition {
+                name: name.into(),
+                nickname: None,
+                schemas: ServiceSchemas::default(),
+            },
+            tick: None,
+        }
+    }
+
+    /// Set the nickname of the service.
+    pub fn nickname(mut self, nickname: impl Into<String>) -> Self {
+        self.definition.nickname = Some(nickname.into());
+        self
+    }
+
+    /// Set the schemas that the concierge validates messages against.
+    pub fn schemas(mut self, schemas: ServiceSchemas) -> Self {
+        self.definition.schemas = schemas;
+        self
+    }
+
+    /// Call `ServiceBot::on_tick` at every interval.
+    pub fn tick(mut self, interval: Duration) -> Self {
+        self.tick = Some(interval);
+        self
+    }
+
+    /// Connect and host the service until the connection is closed.
+    pub async fn run<B: ServiceBot>(self, bot: B) -> Result<()> {
+        self.start(bot).await?.await
+    }
+
+    /// Connect and create the service, resolving with the future that hosts
+    /// it until the connection is closed. The future can be spawned.
+    pub async fn start<B: ServiceBot>(
+        self,
+        bot: B,
+    ) -> Result<impl Future<Output = Result<()>> + Send> {
+        let Self {
+            mut builder,
+            definition,
+            tick,
+        } = self;
+        let (lifecycle_tx, mut lifecycle) = unbounded_channel();
+        let on_connect = builder.on_connect.take();
+        let on_disconnect = builder.on_disconnect.take();
+        builder.merge_messages = true;
+        let tx = lifecycle_tx.clone();
+        builder.on_connect = Some(Arc::new(move |client: &Client| {
+            if let Some(hook) = &on_connect {
+                hook(client);
+            }
+            let _ = tx.send(Event::Connected);
+        }));
+        builder.on_disconnect = Some(Arc::new(move |client: &Client| {
+            if let Some(hook) = &on_disconnect {
+                hook(client);
+            }
+            let _ = lifecycle_tx.send(Event::Disconnected);
+        }));
+
+        let Connection { client, events, .. } = builder.connect().await?;
+        let service = ServiceHandle {
+            client,
+            name: definition.name.as_str().into(),
+            subscribers: Arc::default(),
+        };
+
+        // The hook of the first connection runs after `connect` resolves.
+        lifecycle.recv().await;
+        definition.create(&service.client).await?;
+
+        let frames = events
+            .map(Event::Frame)
+            .chain(stream::once(future::ready(Event::Closed)));
+        let ticks = match tick {
+            Some(interval) => tokio::time::interval(interval).map(|_| Event::Tick).boxed(),
+            None => stream::pending().boxed(),
+        };
+        let mut events = stream::select(stream::select(lifecycle, frames), ticks);
+
+        Ok(async move {
+            let mut created = true;
+            while let Some(event) = events.next().await {
+                match event {
+                    Event::Connected => {
+                        if let Err(err) = definition.create(&service.client).await {
+                            let _ = service.client.close();
+                            return Err(err);
+                        }
+                        created = true;
+                    }
+                    Event::Disconnected => {
+                        created = false;
+                        let subscribers = service
+                            .lock_subscribers()
+                            .drain()
+                            .map(|(_, client)| client)
+                            .collect::<Vec<_>>();
+                        for client in subscribers {
+                            bot.on_unsubscribe(&service, &client).await;
+                        }
+                    }
+                    Event::Frame(frame) => definition.dispatch(&bot, &service, frame).await,
+                    Event::Tick if created => bot.on_tick(&service).await,
+                    Event::Tick => {}
+                    Event::Closed => break,
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+impl Definition {
+    /// Create the service and subscribe its owner to it.
+    async fn create(&self, client: &Client) -> Result<()> {
+        let response = client
+            .request(&PayloadIn::ServiceCreate {
+                service: &self.name,
+                nickname: self.nickname.as_deref(),
+                schemas: self.schemas.clone(),
+            })
+            .await?;
+        match response.payload()? {
+            PayloadOut::ServiceCreateResult {
+                successful: true, ..
+            } => {}
+            _ => return Err(Error::Refused(response.text().to_owned())),
+        }
+
+        let response = client
+            .request(&PayloadIn::SelfSubscribe {
+                service: &self.name,
+                filter: None,
+            })
+            .await?;
+        match response.payload()? {
+            PayloadOut::SelfSubscribeResult {
+                successful: true, ..
+            } => Ok(()),
+            _ => Err(Error::Refused(response.text().to_owned())),
+        }
+    }
+
+    async fn dispatch<B: ServiceBot>(&self, bot: &B, service: &ServiceHandle, frame: Frame) {
+        let own_uuid = service.client.uuid();
+        match frame.payload() {
+            Ok(PayloadOut::ServiceClientSubscribed {
+                client,
+                service: info,
+            }) if info.name == self.name && client.uuid != own_uuid => {
+                let client = client.owned();
+                service
+                    .lock_subscribers()
+                    .insert(client.uuid, client.clone());
+                bot.on_subscribe(service, &client).await;
+            }
+            Ok(PayloadOut::ServiceClientUnsubscribed {
+                client,
+                service: info,
+            }) if info.name == self.name && client.uuid != own_uuid => {
+                let client = service
+                    .lock_subscribers()
+                    .remove(&client.uuid)
+                    .unwrap_or_else(|| client.owned());
+                bot.on_unsubscribe(service, &client).await;
+            }
+            Ok(_) => {}
+            Err(_) => {
+                if let Ok(ServiceMessage {
+                    origin: Some(origin),
+                    data,
+                }) = frame.service_message::<B::Message>()
+                {
+                    let to_service =
+                        matches!(&origin.service, Some(info) if info.name == self.name);
+                    // Broadcasts are not echoed to the owner, but the messages
+                    // that it sends to itself through the service are.
+                    if to_service && origin.client.uuid != own_uuid {
+                        bot.on_message(service, &origin.client, data).await;
+                    }
+                }
+            }
+        }
+    }
+}
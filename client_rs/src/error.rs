@@ -0,0 +1,22 @@
+/// Client errors.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Invalid url: {0}")]
+    Url(#[from] url::ParseError),
+    #[error("WebSocket error: {0}")]
+    WebSocket(#[from] tokio_tungstenite::tungstenite::Error),
+    #[error("Serialization error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("File system request error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Identification rejected with code {code}: {reason}")]
+    Rejected { code: u16, reason: String },
+    #[error("Expected a HELLO payload, received: {0}")]
+    UnexpectedHello(String),
+    #[error("The concierge refused the request, responding with: {0}")]
+    Refused(String),
+    #[error("The connection to the concierge is closed")]
+    Disconnected,
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,66 @@
+use crate::Result;
+use concierge_api_rs::{info::Origin, PayloadMessage, PayloadOut, RequestId};
+use serde::{de::DeserializeOwned, Deserialize};
+
+/// Fields common to every payload from the server, used for routing.
+#[derive(Deserialize)]
+pub(crate) struct Header<'a> {
+    pub r#type: &'a str,
+    pub seq: Option<usize>,
+    #[serde(borrow)]
+    pub id: Option<RequestId<'a>>,
+}
+
+/// A text payload received from the concierge.
+///
+/// The payloads of the API crate borrow from the JSON text, so frames keep
+/// the text and deserialize it on demand.
+#[derive(Clone, Debug)]
+pub struct Frame {
+    text: String,
+    seq: Option<usize>,
+}
+
+impl Frame {
+    pub(crate) fn new(text: String, seq: Option<usize>) -> Self {
+        Self { text, seq }
+    }
+
+    /// The JSON text of the payload.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Sequence number of the payload, if it responds to one of the client's.
+    pub fn seq(&self) -> Option<usize> {
+        self.seq
+    }
+
+    /// Deserialize the frame as a payload from the server.
+    pub fn payload(&self) -> Result<PayloadOut<'_>> {
+        Ok(serde_json::from_str(&self.text)?)
+    }
+
+    /// Deserialize the frame as a message, with data borrowed from the frame.
+    pub fn message<'a, T: Deserialize<'a>>(&'a self) -> Result<PayloadMessage<'a, T>> {
+        Ok(serde_json::from_str(&self.text)?)
+    }
+
+    /// Deserialize the frame as a message, with owned data.
+    pub fn service_message<T: DeserializeOwned>(&self) -> Result<ServiceMessage<T>> {
+        let message = self.message::<T>()?;
+        Ok(ServiceMessage {
+            origin: message.origin.as_ref().map(Origin::owned),
+            data: message.data,
+        })
+    }
+}
+
+/// A message relayed by the concierge, with typed data.
+#[derive(Clone, Debug)]
+pub struct ServiceMessage<T> {
+    /// Origin of the message, which is always present on relayed messages.
+    pub origin: Option<Origin<'static>>,
+    /// Data of the message.
+    pub data: T,
+}
@@ -0,0 +1,85 @@
+use crate::Result;
+use reqwest::multipart::{Form, Part};
+use url::Url;
+use uuid::Uuid;
+
+/// Header carrying the file key, which is the uuid sent in `HELLO`.
+pub const FS_KEY_HEADER: &str = "x-fs-key";
+
+/// Requests to the file system of the concierge. Clients can read
+/// every client's files, but only write to their own folder.
+#[derive(Clone, Debug)]
+pub struct Fs {
+    http: reqwest::Client,
+    base: Url,
+    name: String,
+    key: Uuid,
+}
+
+impl Fs {
+    /// Create file system requests for the client of that name and file key,
+    /// with `base` being the url of the concierge's WebSocket endpoint.
+    pub fn new(base: &Url, name: &str, key: Uuid) -> Self {
+        let mut base = base.clone();
+        let scheme = if base.scheme() == "wss" {
+            "https"
+        } else {
+            "http"
+        };
+        // Both schemes are special, so the conversion always succeeds.
+        let _ = base.set_scheme(scheme);
+        Self {
+            http: reqwest::Client::new(),
+            base,
+            name: name.to_owned(),
+            key,
+        }
+    }
+
+    fn url(&self, owner: &str, file_name: &str) -> Url {
+        let mut url = self.base.clone();
+        url.path_segments_mut()
+            .expect("Base url")
+            .clear()
+            .extend(&["fs", owner, file_name]);
+        url
+    }
+
+    /// Upload a file to the client's folder.
+    pub async fn upload(&self, file_name: &str, contents: impl Into<Vec<u8>>) -> Result<()> {
+        let part = Part::bytes(contents.into()).file_name(file_name.to_owned());
+        self.http
+            .post(self.url(&self.name, file_name))
+            .header(FS_KEY_HEADER, self.key.to_string())
+            .multipart(Form::new().part("file", part))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Download a file from the folder of the client named `owner`.
+    pub async fn download(&self, owner: &str, file_name: &str) -> Result<Vec<u8>> {
+        let bytes = self
+            .http
+            .get(self.url(owner, file_name))
+            .header(FS_KEY_HEADER, self.key.to_string())
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        Ok(bytes.to_vec())
+    }
+
+    /// Delete a file from the client's folder.
+    pub async fn delete(&self, file_name: &str) -> Result<()> {
+        self.http
+            .delete(self.url(&self.name, file_name))
+            .header(FS_KEY_HEADER, self.key.to_string())
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,491 @@
+//! Asynchronous client for the concierge.
+//!
+//! ```no_run
+//! # async fn example() -> concierge_client::Result<()> {
+//! use concierge_api_rs::{PayloadIn, PayloadOut};
+//! use concierge_client::ClientBuilder;
+//! use futures::StreamExt;
+//!
+//! let mut connection = ClientBuilder::new("ws://127.0.0.1:64209/ws", "example")
+//!     .tags(&["viewer"])
+//!     .connect()
+//!     .await?;
+//!
+//! // Requests resolve with the payload that responds to them.
+//! let response = connection
+//!     .client
+//!     .request(&PayloadIn::SelfSubscribe { service: "chat", filter: None })
+//!     .await?;
+//! if let PayloadOut::SelfSubscribeResult { successful, .. } = response.payload()? {
+//!     println!("Subscribed: {}", successful);
+//! }
+//!
+//! while let Some(frame) = connection.messages.next().await {
+//!     let message = frame.service_message::<serde_json::Value>()?;
+//!     println!("{:?}", message.data);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+mod bot;
+mod error;
+mod frame;
+mod fs;
+
+pub use async_trait::async_trait;
+pub use bot::{ServiceBot, ServiceHandle, ServiceRunner};
+pub use error::{Error, Result};
+pub use frame::{Frame, ServiceMessage};
+pub use fs::{Fs, FS_KEY_HEADER};
+
+use concierge_api_rs::{
+    Capability, EventClass, PayloadIn, PayloadMessage, PayloadOut, Request, RequestId, Target,
+};
+use frame::Header;
+use futures::{future, pin_mut, SinkExt, Stream, StreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    net::TcpStream,
+    sync::{
+        mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+        oneshot,
+    },
+    time::delay_for,
+};
+use tokio_tungstenite::{
+    tungstenite::{self, protocol::frame::coding::CloseCode, Message},
+    WebSocketStream,
+};
+use url::Url;
+use uuid::Uuid;
+
+/// Version of the protocol implemented by the client.
+pub const PROTOCOL_VERSION: &str = "0.4.0";
+
+type Hook = Arc<dyn Fn(&Client) + Send + Sync>;
+type Socket = WebSocketStream<TcpStream>;
+
+/// Identification and behaviour of a client, used to connect to the concierge.
+pub struct ClientBuilder {
+    url: String,
+    name: String,
+    nickname: Option<String>,
+    version: String,
+    secret: Option<String>,
+    tags: Vec<String>,
+    events: Option<Vec<EventClass>>,
+    capabilities: Vec<Capability>,
+    reconnect: Option<Duration>,
+    on_connect: Option<Hook>,
+    on_disconnect: Option<Hook>,
+    /// Route messages to the events as well, keeping the order of all payloads.
+    merge_messages: bool,
+}
+
+impl ClientBuilder {
+    /// Create a client of that name, connecting to the WebSocket endpoint at `url`.
+    pub fn new(url: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            name: name.into(),
+            nickname: None,
+            version: PROTOCOL_VERSION.to_owned(),
+            secret: None,
+            tags: Vec::new(),
+            events: None,
+            capabilities: vec![Capability::Errors],
+            reconnect: None,
+            on_connect: None,
+            on_disconnect: None,
+            merge_messages: false,
+        }
+    }
+
+    /// Set the nickname of the client.
+    pub fn nickname(mut self, nickname: impl Into<String>) -> Self {
+        self.nickname = Some(nickname.into());
+        self
+    }
+
+    /// Set the protocol version sent in `IDENTIFY`.
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    /// Set the secret of the concierge.
+    pub fn secret(mut self, secret: impl Into<String>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    /// Set the tags of the client.
+    pub fn tags(mut self, tags: &[&str]) -> Self {
+        self.tags = tags.iter().map(|&tag| tag.to_owned()).collect();
+        self
+    }
+
+    /// Set the global event classes that the client receives.
+    /// By default, every class is received.
+    pub fn events(mut self, events: Vec<EventClass>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Request capabilities from the concierge, replacing the default
+    /// request of `Capability::Errors`.
+    pub fn capabilities(mut self, capabilities: Vec<Capability>) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Reconnect after losing the connection, waiting `delay` between attempts.
+    pub fn reconnect(mut self, delay: Duration) -> Self {
+        self.reconnect = Some(delay);
+        self
+    }
+
+    /// Call `hook` every time the client is identified, including the first time.
+    /// Since the concierge forgets about disconnected clients, this is where
+    /// services should be created and subscriptions made.
+    pub fn on_connect(mut self, hook: impl Fn(&Client) + Send + Sync + 'static) -> Self {
+        self.on_connect = Some(Arc::new(hook));
+        self
+    }
+
+    /// Call `hook` every time the connection is lost.
+    pub fn on_disconnect(mut self, hook: impl Fn(&Client) + Send + Sync + 'static) -> Self {
+        self.on_disconnect = Some(Arc::new(hook));
+        self
+    }
+
+    /// Connect and identify to the concierge. The connection is driven by
+    /// a task spawned on the tokio runtime.
+    pub async fn connect(self) -> Result<Connection> {
+        let url = Url::parse(&self.url)?;
+        let (socket, session) = self.identify(&url).await?;
+
+        let client = Client {
+            inner: Arc::new(Inner {
+                name: self.name.clone(),
+                url,
+                session: Mutex::new(session),
+            }),
+        };
+        let (messages_tx, messages) = unbounded_channel();
+        let (events_tx, events) = unbounded_channel();
+        let messages_tx = if self.merge_messages {
+            events_tx.clone()
+        } else {
+            messages_tx
+        };
+
+        tokio::spawn(drive(self, client.clone(), socket, messages_tx, events_tx));
+
+        Ok(Connection {
+            client,
+            messages,
+            events,
+        })
+    }
+
+    async fn identify(&self, url: &Url) -> Result<(Socket, Session)> {
+        let (mut socket, _) = tokio_tungstenite::connect_async(url.clone()).await?;
+
+        let identify = PayloadIn::Identify {
+            name: &self.name,
+            nickname: self.nickname.as_deref(),
+            version: &self.version,
+            secret: self.secret.as_deref(),
+            tags: self.tags.iter().map(String::as_str).collect(),
+            events: self.events.clone(),
+            capabilities: self.capabilities.clone(),
+        };
+        socket
+            .send(Message::text(serde_json::to_string(&identify)?))
+            .await?;
+
+        match socket.next().await {
+            Some(Ok(Message::Text(text))) => match serde_json::from_str(&text) {
+                Ok(PayloadOut::Hello {
+                    uuid,
+                    version,
+                    capabilities,
+                }) => {
+                    let session = Session {
+                        uuid,
+                        version: version.to_owned(),
+                        capabilities,
+                        next_id: 0,
+                        pending: HashMap::new(),
+                        outgoing: None,
+                    };
+                    Ok((socket, session))
+                }
+                _ => Err(Error::UnexpectedHello(text)),
+            },
+            Some(Ok(Message::Close(Some(frame)))) => Err(Error::Rejected {
+                code: frame.code.into(),
+                reason: frame.reason.into_owned(),
+            }),
+            Some(Ok(message)) => Err(Error::UnexpectedHello(message.to_string())),
+            Some(Err(err)) => Err(err.into()),
+            None => Err(Error::Disconnected),
+        }
+    }
+}
+
+/// A connection to the concierge.
+pub struct Connection {
+    /// Handle to send payloads.
+    pub client: Client,
+    /// Messages relayed to the client.
+    pub messages: UnboundedReceiver<Frame>,
+    /// Every other payload that does not respond to a request of the client,
+    /// such as global events and notifications from owned services.
+    pub events: UnboundedReceiver<Frame>,
+}
+
+/// Deserialize the data of relayed messages, skipping those that do not match `T`.
+pub fn typed<T: DeserializeOwned>(
+    messages: impl Stream<Item = Frame>,
+) -> impl Stream<Item = ServiceMessage<T>> {
+    messages.filter_map(|frame| future::ready(frame.service_message().ok()))
+}
+
+/// State of the current identification, which is replaced upon reconnection.
+struct Session {
+    uuid: Uuid,
+    version: String,
+    capabilities: Vec<Capability>,
+    /// Id of the next request.
+    next_id: u64,
+    /// Requests that await the payload responding to their id.
+    pending: HashMap<u64, oneshot::Sender<Frame>>,
+    /// `None` while disconnected.
+    outgoing: Option<UnboundedSender<Message>>,
+}
+
+struct Inner {
+    name: String,
+    url: Url,
+    session: Mutex<Session>,
+}
+
+/// Handle to a connection to the concierge, which can be cloned freely.
+#[derive(Clone)]
+pub struct Client {
+    inner: Arc<Inner>,
+}
+
+impl Client {
+    /// Name of the client.
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    /// Uuid assigned to the client by the concierge. It changes upon reconnection.
+    pub fn uuid(&self) -> Uuid {
+        self.session().uuid
+    }
+
+    /// Version of the concierge.
+    pub fn server_version(&self) -> String {
+        self.session().version.clone()
+    }
+
+    /// Capabilities negotiated with the concierge.
+    pub fn capabilities(&self) -> Vec<Capability> {
+        self.session().capabilities.clone()
+    }
+
+    /// Check if the client is currently connected.
+    pub fn is_connected(&self) -> bool {
+        self.session().outgoing.is_some()
+    }
+
+    /// File system requests, using the client's current file key.
+    pub fn fs(&self) -> Fs {
+        Fs::new(&self.inner.url, &self.inner.name, self.uuid())
+    }
+
+    fn session(&self) -> std::sync::MutexGuard<'_, Session> {
+        self.inner.session.lock().expect("Session lock")
+    }
+
+    /// Send a payload without an id, which the concierge still responds to.
+    fn dispatch(&self, payload: &impl Serialize) -> Result<()> {
+        let text = serde_json::to_string(payload)?;
+        self.session()
+            .outgoing
+            .as_ref()
+            .ok_or(Error::Disconnected)?
+            .send(Message::text(text))
+            .map_err(|_| Error::Disconnected)
+    }
+
+    /// Send a payload with the next id, resolving with the payload that responds to it.
+    async fn dispatch_request(&self, payload: &impl Serialize) -> Result<Frame> {
+        let rx = {
+            let mut session = self.session();
+            let id = session.next_id;
+            let request = Request::new(Some(RequestId::Number(id)), payload);
+            session
+                .outgoing
+                .as_ref()
+                .ok_or(Error::Disconnected)?
+                .send(Message::text(serde_json::to_string(&request)?))
+                .map_err(|_| Error::Disconnected)?;
+            session.next_id = id + 1;
+
+            let (tx, rx) = oneshot::channel();
+            session.pending.insert(id, tx);
+            rx
+        };
+        rx.await.map_err(|_| Error::Disconnected)
+    }
+
+    /// Send a payload without waiting for its response.
+    pub fn send(&self, payload: &PayloadIn<'_>) -> Result<()> {
+        self.dispatch(payload)
+    }
+
+    /// Send a payload, resolving with the payload that responds to it.
+    pub async fn request(&self, payload: &PayloadIn<'_>) -> Result<Frame> {
+        self.dispatch_request(payload).await
+    }
+
+    /// Send a message without waiting for its response.
+    pub fn message(&self, target: Target<'_>, data: &impl Serialize) -> Result<()> {
+        self.dispatch(&PayloadMessage::new(target, data))
+    }
+
+    /// Send a message, resolving with the payload that responds to it.
+    pub async fn request_message(
+        &self,
+        target: Target<'_>,
+        data: &impl Serialize,
+    ) -> Result<Frame> {
+        self.dispatch_request(&PayloadMessage::new(target, data))
+            .await
+    }
+
+    /// Close the connection. The client does not reconnect afterwards.
+    pub fn close(&self) -> Result<()> {
+        let frame = tungstenite::protocol::CloseFrame {
+            code: CloseCode::Normal,
+            reason: "".into(),
+        };
+        let mut session = self.session();
+        let outgoing = session.outgoing.take().ok_or(Error::Disconnected)?;
+        outgoing
+            .send(Message::Close(Some(frame)))
+            .map_err(|_| Error::Disconnected)
+    }
+}
+
+/// Drive the connection until it is closed, reconnecting if configured to.
+async fn drive(
+    builder: ClientBuilder,
+    client: Client,
+    mut socket: Socket,
+    messages: UnboundedSender<Frame>,
+    events: UnboundedSender<Frame>,
+) {
+    loop {
+        let (outgoing_tx, outgoing_rx) = unbounded_channel();
+        client.session().outgoing = Some(outgoing_tx);
+        if let Some(hook) = &builder.on_connect {
+            hook(&client);
+        }
+
+        let closed_by_client = serve(&client, socket, outgoing_rx, &messages, &events).await;
+
+        // Dropping the pending senders fails the requests awaiting them.
+        {
+            let mut session = client.session();
+            session.outgoing = None;
+            session.pending.clear();
+        }
+        if let Some(hook) = &builder.on_disconnect {
+            hook(&client);
+        }
+
+        let delay = match builder.reconnect {
+            Some(delay) if !closed_by_client => delay,
+            _ => return,
+        };
+        socket = loop {
+            delay_for(delay).await;
+            if let Ok((socket, session)) = builder.identify(&client.inner.url).await {
+                *client.session() = session;
+                break socket;
+            }
+        };
+    }
+}
+
+/// Relay frames between the socket and the client until the socket is closed.
+/// Returns true if the client closed the connection itself.
+async fn serve(
+    client: &Client,
+    socket: Socket,
+    outgoing: UnboundedReceiver<Message>,
+    messages: &UnboundedSender<Frame>,
+    events: &UnboundedSender<Frame>,
+) -> bool {
+    let (sink, mut stream) = socket.split();
+    let send_loop = outgoing.map(Ok).forward(sink);
+    let recv_loop = async {
+        while let Some(Ok(message)) = stream.next().await {
+            if let Message::Text(text) = message {
+                route(client, text, messages, events);
+            }
+        }
+    };
+
+    pin_mut!(send_loop, recv_loop);
+    match future::select(send_loop, recv_loop).await {
+        // The outgoing channel only ends after the client closed the connection.
+        future::Either::Left((Ok(()), _)) => true,
+        _ => false,
+    }
+}
+
+/// Route a text frame to the request that awaits it, or to the streams.
+fn route(
+    client: &Client,
+    text: String,
+    messages: &UnboundedSender<Frame>,
+    events: &UnboundedSender<Frame>,
+) {
+    let (is_message, seq, id) = match serde_json::from_str::<Header>(&text) {
+        Ok(Header { r#type, seq, id }) => {
+            // The client only chooses numeric ids.
+            let id = match id {
+                Some(RequestId::Number(id)) => Some(id),
+                _ => None,
+            };
+            (r#type == "MESSAGE", seq, id)
+        }
+        Err(_) => return,
+    };
+    let frame = Frame::new(text, seq);
+
+    if seq.is_some() {
+        // Responses that nobody waits for are dropped.
+        if let Some(tx) = id.and_then(|id| client.session().pending.remove(&id)) {
+            let _ = tx.send(frame);
+        }
+    } else if is_message {
+        let _ = messages.send(frame);
+    } else {
+        let _ = events.send(frame);
+    }
+}
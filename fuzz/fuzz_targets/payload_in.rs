@@ -0,0 +1,21 @@
+//! Decoding of the payloads that clients send, other than messages.
+#![no_main]
+use concierge_api_rs::{PayloadIn, Request};
+use libfuzzer_sys::fuzz_target;
+use serde::de::IgnoredAny;
+
+fuzz_target!(|data: &[u8]| {
+    let text = match std::str::from_utf8(data) {
+        Ok(text) => text,
+        Err(_) => return,
+    };
+
+    // The concierge reads the id of every payload, whether it decodes or not.
+    let _ = serde_json::from_str::<Request<IgnoredAny>>(text);
+
+    // Decoded payloads must survive a round trip.
+    if let Ok(payload) = serde_json::from_str::<PayloadIn>(text) {
+        let encoded = serde_json::to_string(&payload).expect("Serialization");
+        serde_json::from_str::<PayloadIn>(&encoded).expect("Round trip");
+    }
+});
@@ -0,0 +1,18 @@
+//! Decoding of the messages that clients send, with data left undecoded.
+#![no_main]
+use concierge_api_rs::PayloadMessage;
+use libfuzzer_sys::fuzz_target;
+use serde_json::value::RawValue;
+
+fuzz_target!(|data: &[u8]| {
+    let text = match std::str::from_utf8(data) {
+        Ok(text) => text,
+        Err(_) => return,
+    };
+
+    // Decoded messages must survive a round trip.
+    if let Ok(message) = serde_json::from_str::<PayloadMessage<&RawValue>>(text) {
+        let encoded = serde_json::to_string(&message).expect("Serialization");
+        serde_json::from_str::<PayloadMessage<&RawValue>>(&encoded).expect("Round trip");
+    }
+});
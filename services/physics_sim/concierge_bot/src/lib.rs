@@ -7,4 +7,5 @@ pub const CLIENT_NICKNAME: &str = "Physics Engine Client";
 pub const SERVICE_NAME: &str = "physics_engine";
 pub const SERVICE_NICKNAME: &str = "Rust Physics Game";
 
+pub use physics_payload::PhysicsPayload;
 pub use service::init_bot;
\ No newline at end of file
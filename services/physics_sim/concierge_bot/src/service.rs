@@ -1,6 +1,7 @@
-use crate::physics_payload::{EntityDump, EntityUpdate, PayloadMessage, PhysicsPayload};
+use crate::physics_payload::{EntityDump, EntityUpdate, PhysicsPayload};
 use anyhow::Result;
-use concierge_api_rs::{PayloadIn, PayloadOut, info, Target};
+use concierge_api_rs::{info, schema::ServiceSchemas};
+use concierge_client::{async_trait, ClientBuilder, ServiceBot, ServiceHandle, ServiceRunner};
 use cs3_physics::{
     ecs::{
         colliders::{ColliderList, ColliderPair, Shape},
@@ -12,7 +13,6 @@ use cs3_physics::{
     specs::prelude::*,
     vector::Vec2f,
 };
-use futures::{SinkExt, Stream, StreamExt};
 use palette;
 use std::{
     sync::{
@@ -21,23 +21,10 @@ use std::{
     },
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
-use tokio::{
-    sync::{
-        mpsc::{unbounded_channel, UnboundedSender},
-        RwLock,
-    },
-    time::delay_for,
-};
-use tokio_tungstenite::tungstenite::Message;
-use url::Url;
-use uuid::Uuid;
+use tokio::sync::RwLock;
 
 pub const MS_SEND_INTERVAL: u64 = 20;
 
-const SERVICE_TARGET: Target = Target::Service {
-    service: crate::SERVICE_NAME,
-};
-
 fn color_from_string_hash(string: &str) -> (u8, u8, u8) {
     fn js_hash_string(string: &str) -> u32 {
         let mut hash = 0;
@@ -64,67 +51,103 @@ pub async fn init_bot(running: Arc<AtomicBool>, world: Arc<RwLock<World>>) -> Re
         .nth(1)
         .unwrap_or_else(|| "ws://127.0.0.1:64209/ws".to_string());
 
-    let url = Url::parse(&connect_addr).unwrap();
-    let (mut ws, _) = tokio_tungstenite::connect_async(url)
-        .await
-        .expect("Failed to connect");
-
-    ws.send(Message::text(serde_json::to_string(
-        &PayloadIn::Identify {
-            name: crate::CLIENT_NAME,
-            nickname: Some(crate::CLIENT_NICKNAME),
-            version: "0.2.0",
-            secret: None,
-            tags: vec!["simulation"],
-        },
-    )?))
-    .await?;
-
-    let client_uuid: Uuid;
-
-    if let Some(Ok(Message::Text(string))) = ws.next().await {
-        if let PayloadOut::Hello { uuid, .. } = serde_json::from_str(&string).unwrap() {
-            ws.send(Message::text(
-                serde_json::to_string(&PayloadIn::ServiceCreate {
-                    service: crate::SERVICE_NAME,
-                    nickname: Some(crate::SERVICE_NICKNAME),
-                })
-                .unwrap(),
-            ))
-            .await?;
-            ws.send(Message::text(
-                serde_json::to_string(&PayloadIn::SelfSubscribe {
-                    service: crate::SERVICE_NAME,
-                })
-                .unwrap(),
-            ))
-            .await?;
-            client_uuid = uuid;
-        } else {
-            panic!("WS did not receive a hello.")
+    let builder = ClientBuilder::new(connect_addr, crate::CLIENT_NAME)
+        .nickname(crate::CLIENT_NICKNAME)
+        .tags(&["simulation"])
+        // The bot only cares about its own service.
+        .events(vec![]);
+
+    let service = ServiceRunner::new(builder, crate::SERVICE_NAME)
+        .nickname(crate::SERVICE_NICKNAME)
+        .schemas(ServiceSchemas {
+            // Subscribers can only send requests.
+            inbound: Some(serde_json::json!({
+                "type": "object",
+                "required": ["type"],
+                "properties": {
+                    "type": {
+                        "enum": [
+                            "FETCH_ENTITIES",
+                            "FETCH_POSITIONS",
+                            "SPAWN_ENTITY",
+                            "TOGGLE_COLOR",
+                            "TOUCH_ENTITY"
+                        ]
+                    }
+                }
+            })),
+            outbound: None,
+        })
+        .tick(Duration::from_millis(MS_SEND_INTERVAL))
+        .start(PhysicsBot { running, world })
+        .await?;
+
+    tokio::spawn(async move {
+        if let Err(err) = service.await {
+            eprintln!("Concierge bot failed: {}", err);
         }
-    } else {
-        panic!("Received something else besides a text payload!")
+    });
+    Ok(())
+}
+
+struct PhysicsBot {
+    running: Arc<AtomicBool>,
+    world: Arc<RwLock<World>>,
+}
+
+#[async_trait]
+impl ServiceBot for PhysicsBot {
+    type Message = PhysicsPayload<'static>;
+
+    async fn on_subscribe(&self, _service: &ServiceHandle, client: &info::Client<'static>) {
+        println!("Client {} subscribed!", client.name);
     }
 
-    let (tx, rx) = unbounded_channel();
+    async fn on_unsubscribe(&self, service: &ServiceHandle, client: &info::Client<'static>) {
+        println!("Client {} unsubscribed!", client.name);
 
-    let (sink, stream) = ws.split();
-    let outgoing_loop = rx.map(Ok).forward(sink);
+        let mut world = self.world.write().await;
+        let entities = world.entities();
+        let owners = world.read_component::<Owner>();
+        let to_delete = (&entities, &owners)
+            .par_join()
+            .filter(|(_, owner)| owner.0 == client.uuid)
+            .map(|(ent, _)| ent)
+            .collect::<Vec<_>>();
+        drop(owners);
+        drop(entities);
+
+        let ids = world.read_component::<Id>();
+        let ids_to_delete = to_delete
+            .iter()
+            .filter_map(|ent| ids.get(*ent))
+            .map(|id| id.0)
+            .collect::<Vec<_>>();
 
-    tokio::spawn(send_loop(world.clone(), running, tx.clone()));
-    tokio::spawn(recv_loop(client_uuid, world, stream, tx));
-    tokio::spawn(outgoing_loop);
-    Ok(())
-}
+        let _ = service.broadcast(&PhysicsPayload::EntityDelete { ids: ids_to_delete });
 
-pub async fn send_loop(
-    world: Arc<RwLock<World>>,
-    running: Arc<AtomicBool>,
-    tx: UnboundedSender<Message>,
-) {
-    while running.load(Ordering::SeqCst) {
-        let world = world.read().await;
+        drop(ids);
+
+        world.delete_entities(&to_delete).expect("Delete fail");
+    }
+
+    async fn on_message(
+        &self,
+        service: &ServiceHandle,
+        origin: &info::Client<'static>,
+        data: PhysicsPayload<'static>,
+    ) {
+        println!("Client {} sent service message.", origin.name);
+        handle_message(data, origin, &self.world, service).await;
+    }
+
+    async fn on_tick(&self, service: &ServiceHandle) {
+        if !self.running.load(Ordering::SeqCst) {
+            let _ = service.client().close();
+            return;
+        }
+
+        let world = self.world.read().await;
 
         let id = world.read_component::<Id>();
         let pos = world.read_component::<Pos>();
@@ -137,92 +160,15 @@ pub async fn send_loop(
             })
             .collect::<Vec<_>>();
 
-        let _ = tx.send(Message::text(
-            serde_json::to_string(&PayloadMessage::new(
-                SERVICE_TARGET,
-                PhysicsPayload::PositionDump { updates },
-            ))
-            .unwrap(),
-        ));
-
-        delay_for(Duration::from_millis(MS_SEND_INTERVAL)).await;
-    }
-}
-
-pub async fn recv_loop<T>(
-    client_uuid: Uuid,
-    world: Arc<RwLock<World>>,
-    mut stream: impl Stream<Item = Result<Message, T>> + Unpin,
-    tx: UnboundedSender<Message>,
-) {
-    while let Some(Ok(msg)) = stream.next().await {
-        if let Ok(string) = msg.to_text() {
-            if let Ok(PayloadMessage {
-                data,
-                origin: Some(info::Origin { client, .. }),
-                ..
-            }) = serde_json::from_str(string)
-            {
-                if client.uuid == client_uuid {
-                    return;
-                }
-                // Ignore any messages sent by yourself, since you already know them
-                println!("Client {} sent service message.", client.name);
-                handle_message(data, client, &world, &tx).await;
-            }
-            match serde_json::from_str::<PayloadOut>(string) {
-                Ok(PayloadOut::ServiceClientSubscribed { client, service })
-                    if service.name == crate::SERVICE_NAME =>
-                {
-                    println!("Client {} subscribed!", client.name);
-                }
-                Ok(PayloadOut::ServiceClientUnsubscribed { client, service })
-                    if service.name == crate::SERVICE_NAME =>
-                {
-                    println!("Client {} unsubscribed!", client.name);
-
-                    let mut world = world.write().await;
-                    let entities = world.entities();
-                    let owners = world.read_component::<Owner>();
-                    let to_delete = (&entities, &owners)
-                        .par_join()
-                        .filter(|(_, owner)| owner.0 == client.uuid)
-                        .map(|(ent, _)| ent)
-                        .collect::<Vec<_>>();
-                    drop(owners);
-                    drop(entities);
-
-                    let ids = world.read_component::<Id>();
-                    let ids_to_delete = to_delete
-                        .iter()
-                        .filter_map(|ent| ids.get(*ent))
-                        .map(|id| id.0)
-                        .collect::<Vec<_>>();
-
-                    tx.send(Message::text(
-                        serde_json::to_string(&PayloadMessage::new(
-                            SERVICE_TARGET,
-                            PhysicsPayload::EntityDelete { ids: ids_to_delete },
-                        ))
-                        .unwrap(),
-                    ))
-                    .unwrap();
-
-                    drop(ids);
-
-                    world.delete_entities(&to_delete).expect("Delete fail");
-                }
-                Ok(_) | Err(_) => {}
-            }
-        }
+        let _ = service.broadcast(&PhysicsPayload::PositionDump { updates });
     }
 }
 
 async fn handle_message(
     data: PhysicsPayload<'_>,
-    client: info::Client<'_>,
+    client: &info::Client<'_>,
     world: &RwLock<World>,
-    tx: &UnboundedSender<Message>,
+    service: &ServiceHandle,
 ) {
     match data {
         PhysicsPayload::SpawnEntity => {
@@ -247,14 +193,9 @@ async fn handle_message(
                 .map(|id| id.0)
                 .collect::<Vec<_>>();
 
-            tx.send(Message::text(
-                serde_json::to_string(&PayloadMessage::new(
-                    SERVICE_TARGET,
-                    PhysicsPayload::EntityDelete { ids: ids_to_delete },
-                ))
-                .unwrap(),
-            ))
-            .unwrap();
+            service
+                .broadcast(&PhysicsPayload::EntityDelete { ids: ids_to_delete })
+                .unwrap();
 
             drop(ids);
 
@@ -306,20 +247,15 @@ async fn handle_message(
             }
 
             println!("Broadcasting new entity info.");
-            tx.send(Message::text(
-                serde_json::to_string(&PayloadMessage::new(
-                    SERVICE_TARGET,
-                    PhysicsPayload::EntityNew {
-                        entity: EntityDump {
-                            id: id.0,
-                            polygon: body,
-                            color: (color.0, color.1, color.2),
-                        },
+            service
+                .broadcast(&PhysicsPayload::EntityNew {
+                    entity: EntityDump {
+                        id: id.0,
+                        polygon: body,
+                        color: (color.0, color.1, color.2),
                     },
-                ))
-                .unwrap(),
-            ))
-            .unwrap();
+                })
+                .unwrap();
         }
         PhysicsPayload::FetchEntities => {
             let world = world.read().await;
@@ -338,18 +274,12 @@ async fn handle_message(
                 .collect::<Vec<_>>();
 
             println!("Sending fetch entities to {}.", client.name);
-            let _ = tx.send(Message::text(
-                serde_json::to_string(&PayloadMessage::new(
-                    Target::ServiceClientUuid {
-                        service: crate::SERVICE_NAME,
-                        uuid: client.uuid,
-                    },
-                    PhysicsPayload::EntityDump {
-                        entities: entities.clone(),
-                    },
-                ))
-                .unwrap(),
-            ));
+            let _ = service.send_to(
+                client.uuid,
+                &PhysicsPayload::EntityDump {
+                    entities: entities.clone(),
+                },
+            );
         }
         PhysicsPayload::ToggleColor { id } => {
             let world = world.read().await;
@@ -370,16 +300,10 @@ async fn handle_message(
                     };
 
                     println!("Broadcasting color update.");
-                    let _ = tx.send(Message::text(
-                        serde_json::to_string(&PayloadMessage::new(
-                            SERVICE_TARGET,
-                            PhysicsPayload::ColorUpdate {
-                                id: *id,
-                                color: (color.0, color.1, color.2),
-                            },
-                        ))
-                        .unwrap(),
-                    ));
+                    let _ = service.broadcast(&PhysicsPayload::ColorUpdate {
+                        id: *id,
+                        color: (color.0, color.1, color.2),
+                    });
                 }
             }
         }
@@ -416,14 +340,9 @@ async fn handle_message(
                     });
 
                     println!("Broadcasting entity deletion.");
-                    tx.send(Message::text(
-                        serde_json::to_string(&PayloadMessage::new(
-                            SERVICE_TARGET,
-                            PhysicsPayload::EntityDelete { ids: vec![id] },
-                        ))
-                        .unwrap(),
-                    ))
-                    .unwrap();
+                    service
+                        .broadcast(&PhysicsPayload::EntityDelete { ids: vec![id] })
+                        .unwrap();
                 } else {
                     drop(owners);
 
@@ -480,20 +399,15 @@ async fn handle_message(
                             "Broadcasting new entity clone of entity owned by {}.",
                             client.name
                         );
-                        tx.send(Message::text(
-                            serde_json::to_string(&PayloadMessage::new(
-                                SERVICE_TARGET,
-                                PhysicsPayload::EntityNew {
-                                    entity: EntityDump {
-                                        id: id.0,
-                                        polygon: shape.0,
-                                        color: (rgb.0, rgb.1, rgb.2),
-                                    },
+                        service
+                            .broadcast(&PhysicsPayload::EntityNew {
+                                entity: EntityDump {
+                                    id: id.0,
+                                    polygon: shape.0,
+                                    color: (rgb.0, rgb.1, rgb.2),
                                 },
-                            ))
-                            .unwrap(),
-                        ))
-                        .unwrap();
+                            })
+                            .unwrap();
                     }
                 }
             }
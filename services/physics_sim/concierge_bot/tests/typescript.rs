@@ -0,0 +1,18 @@
+use concierge_api_rs::typescript::{self, Declarations};
+use physics_concierge_bot::PhysicsPayload;
+use std::path::Path;
+
+#[test]
+fn typescript_definitions_are_up_to_date() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../../../babylonjs-web/src/viewer/service_impls/physics_service/generated.d.ts");
+    assert!(
+        Declarations::new()
+            .with::<PhysicsPayload>()
+            .verify(&path)
+            .unwrap(),
+        "{} is stale, run `{}=1 cargo test` to regenerate it",
+        path.display(),
+        typescript::UPDATE_VAR
+    );
+}
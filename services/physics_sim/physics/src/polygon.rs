@@ -2,6 +2,7 @@ use crate::vector::Vec2f;
 use serde::{Serialize, Deserialize};
 
 #[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "typescript", derive(ts_rs::TS))]
 #[derive(Debug, Clone)]
 pub struct Polygon {
     centroid: Vec2f,
@@ -2,6 +2,7 @@ use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssi
 use serde::{Serialize, Deserialize};
 
 #[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "typescript", derive(ts_rs::TS))]
 /// A 2-dimensional vector, holding 2 values of the same type.
 #[derive(Debug, Copy, Clone, PartialEq, Default)]
 pub struct Vec2<T> {
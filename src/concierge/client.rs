@@ -1,8 +1,12 @@
-use super::{service::Service, OutgoingMessage};
+use super::{service::Service, OutgoingMessage, RoutedMessage};
 use actix::prelude::*;
 use actix_web_actors::ws::Message as WsMessage;
-use concierge_api_rs::info;
+use concierge_api_rs::{
+    info, query::ClientProjection, schema::ServiceSchemas, Capability, EventClass, Filter,
+    PayloadMessage, PayloadOut, Reply,
+};
 use serde::Serialize;
+use serde_json::value::RawValue;
 use std::{
     borrow::Cow,
     collections::{HashMap, HashSet},
@@ -18,13 +22,30 @@ pub struct Client {
     /// Client nickname.
     pub nickname: Option<String>,
     pub seq: usize,
+    /// Sequence number of the messages published as the client over REST,
+    /// apart from those of its connection.
+    pub publish_seq: usize,
     /// Client tags.
     pub tags: Vec<String>,
+    /// Client presence.
+    pub presence: info::Presence<'static>,
     /// Actor address for a recipient that
     /// receives messages from the central server.
     pub addr: Recipient<OutgoingMessage>,
     /// Subscriptions.
     pub subscriptions: HashSet<String>,
+    /// Wildcard pattern subscriptions, along with their content filters.
+    pub patterns: HashMap<String, Option<Filter>>,
+    /// Subscriptions made through wildcard patterns rather than directly.
+    pub pattern_subscriptions: HashSet<String>,
+    /// Global event classes that the client receives.
+    pub events: HashSet<EventClass>,
+    /// Capabilities negotiated at identification.
+    pub capabilities: HashSet<Capability>,
+    /// Recipient of the messages routed to the client, if it is a plugin.
+    pub plugin: Option<Recipient<RoutedMessage>>,
+    /// Latency of the connection, once measured.
+    pub latency: Option<info::Latency>,
 }
 
 impl Client {
@@ -41,8 +62,30 @@ impl Client {
             uuid: self.uuid,
             name: Cow::Borrowed(&self.name),
             nickname: self.nickname.as_deref().map(Cow::Borrowed),
-            tags,
+            tags: Some(tags),
+            presence: info::Presence {
+                status: self.presence.status,
+                custom: self.presence.custom.as_deref().map(Cow::Borrowed),
+            },
+            latency: None,
+        }
+    }
+
+    /// Construct client information with only the fields of a projection.
+    pub fn info_projected(&self, fields: ClientProjection) -> info::Client<'_> {
+        let mut info = self.info();
+        if !fields.tags {
+            info.tags = None;
         }
+        if fields.latency {
+            info.latency = self.latency;
+        }
+        info
+    }
+
+    /// Check if the client receives a global event class.
+    pub fn listens(&self, class: EventClass) -> bool {
+        self.events.contains(&class)
     }
 
     /// Send a serialized payload.
@@ -50,6 +93,47 @@ impl Client {
         self.send_string(&serde_json::to_string(payload).expect("Serialization"))
     }
 
+    /// Send a response to a payload of the client, downgrading errors to
+    /// their legacy payloads unless it negotiated `Capability::Errors`.
+    pub fn respond(&self, payload: PayloadOut<'_>, reply: Reply<'_>) {
+        if !self.capabilities.contains(&Capability::Errors) {
+            if let Some(legacy) = payload.legacy() {
+                return self.send(&legacy.reply(reply));
+            }
+        }
+        self.send(&payload.reply(reply))
+    }
+
+    /// Send a message payload. Plugins receive it as is, without serializing it.
+    pub fn send_message(&self, payload: PayloadMessage<'_, &RawValue>) {
+        if !self.route_to_plugin(&payload) {
+            self.send(&payload);
+        }
+    }
+
+    /// Send a message payload that was serialized once for many clients.
+    /// Like with `send_message`, plugins receive it as is.
+    pub fn send_serialized_message(&self, payload: &PayloadMessage<'_, &RawValue>, string: &str) {
+        if !self.route_to_plugin(payload) {
+            self.send_string(string);
+        }
+    }
+
+    /// Hand a message payload over to the plugin of the client, returning
+    /// `false` if the client is not a plugin.
+    fn route_to_plugin(&self, payload: &PayloadMessage<'_, &RawValue>) -> bool {
+        match (&self.plugin, &payload.origin) {
+            (Some(plugin), Some(origin)) => {
+                let _ = plugin.do_send(RoutedMessage {
+                    origin: origin.owned(),
+                    data: payload.data.to_owned(),
+                });
+                true
+            }
+            _ => false,
+        }
+    }
+
     /// Send a string message.
     pub fn send_string(&self, string: &str) {
         self.send_ws_message(WsMessage::Text(string.to_string()));
@@ -60,7 +144,8 @@ impl Client {
         let _ = self.addr.do_send(OutgoingMessage(message));
     }
 
-    /// Attempt to subscribe to a group.
+    /// Attempt to subscribe to a group, replacing the content filter of
+    /// the subscription.
     ///
     /// ### Return Result
     /// If this function returns `None`, it means that no such group exists.
@@ -72,10 +157,12 @@ impl Client {
         &mut self,
         services: &mut HashMap<String, Service>,
         service_name: &str,
+        filter: Option<Filter>,
     ) -> Option<(info::Service<'static>, bool)> {
         if let Some(group) = services.get_mut(service_name) {
-            let result = group.add_subscriber(self.uuid);
+            let result = group.add_subscriber(self.uuid, filter);
             self.subscriptions.insert(group.name.to_owned());
+            self.pattern_subscriptions.remove(service_name);
             Some((group.info().owned(), result))
         } else {
             None
@@ -101,12 +188,37 @@ impl Client {
         if let Some(group) = services.get_mut(service_name) {
             let result = group.remove_subscriber(self.uuid);
             self.subscriptions.remove(service_name);
+            self.pattern_subscriptions.remove(service_name);
             Some((group.info().owned(), result))
         } else {
             None
         }
     }
 
+    /// Attempt to subscribe to a group through a wildcard pattern. A direct
+    /// subscription to the group is left as is, along with its content filter.
+    ///
+    /// ### Return Result
+    /// Same as [`subscribe`](Client::subscribe).
+    pub fn subscribe_through_pattern(
+        &mut self,
+        services: &mut HashMap<String, Service>,
+        service_name: &str,
+        filter: Option<Filter>,
+    ) -> Option<(info::Service<'static>, bool)> {
+        if self.subscriptions.contains(service_name)
+            && !self.pattern_subscriptions.contains(service_name)
+        {
+            return services
+                .get(service_name)
+                .map(|group| (group.info().owned(), false));
+        }
+
+        let result = self.subscribe(services, service_name, filter)?;
+        self.pattern_subscriptions.insert(service_name.to_owned());
+        Some(result)
+    }
+
     /// Create a group if it currently does not exist in the concierge.
     ///
     /// # Return Result
@@ -117,12 +229,18 @@ impl Client {
         services: &mut HashMap<String, Service>,
         name: &str,
         nickname: Option<&str>,
+        schemas: ServiceSchemas,
     ) -> (info::Service<'static>, bool) {
         if let Some(service) = services.get(name) {
             (service.info().owned(), false)
         } else {
             let service = services.entry(name.to_string()).or_insert_with(|| {
-                Service::new(name.to_owned(), nickname.map(str::to_string), self.uuid)
+                Service::new(
+                    name.to_owned(),
+                    nickname.map(str::to_string),
+                    self.uuid,
+                    schemas,
+                )
             });
             (service.info().owned(), true)
         }
@@ -150,4 +268,4 @@ impl Client {
 
         None
     }
-}
\ No newline at end of file
+}
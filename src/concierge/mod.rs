@@ -1,15 +1,35 @@
 mod client;
+mod queue;
 mod service;
+mod webhooks;
 
+use crate::ws;
 use actix::prelude::*;
 use actix_web_actors::ws::Message as WsMessage;
 use client::Client;
-use concierge_api_rs::{PayloadIn, PayloadMessage, PayloadOut, Target};
-use log::{debug, info, trace};
-use serde::Serialize;
+use concierge_api_rs::{
+    info,
+    schema::{self, ServiceSchemas},
+    topic,
+    webhook::{self, Registration, Webhook, WebhookEvent, WebhookPayload},
+    Capability, DeliveryStatus, ErrorCode, EventClass, Filter, Origin, PayloadIn, PayloadMessage,
+    PayloadOut, Reply, Request, Target,
+};
+use log::{debug, error, info, trace};
+use queue::{Queued, QueuedId, Queues};
+use serde::{de::IgnoredAny, Serialize};
+use serde_json::value::RawValue;
 use service::Service;
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 use uuid::Uuid;
+use webhooks::{Hook, Webhooks};
+
+/// How often expired messages are removed from the queues.
+const QUEUE_SWEEP_INTERVAL: Duration = Duration::from_secs(1);
 
 /// Messages sent to the socket connection.
 #[derive(Debug)]
@@ -24,6 +44,11 @@ pub struct IdentifyPackage {
     pub name: String,
     pub nickname: Option<String>,
     pub tags: Vec<String>,
+    /// * `Some(_)` represents the global event classes the client receives.
+    /// * `None` represents every event class.
+    pub events: Option<Vec<EventClass>>,
+    /// Capabilities negotiated for the connection.
+    pub capabilities: Vec<Capability>,
     pub addr: Recipient<OutgoingMessage>,
 }
 impl Message for IdentifyPackage {
@@ -46,11 +71,140 @@ impl Message for Disconnect {
 pub struct IncomingMessage {
     pub uuid: Uuid,
     pub text: String,
+    /// When the connection received the text, see `now_millis`.
+    pub received: f64,
 }
 impl Message for IncomingMessage {
     type Result = ();
 }
 
+/// A message payload published without a connection.
+/// Often used by the REST route.
+#[derive(Debug)]
+pub struct PublishMessage {
+    /// Uuid of the client that the message is sent as.
+    /// `None` if it was published with the API token.
+    pub sender: Option<Uuid>,
+    pub text: String,
+}
+impl Message for PublishMessage {
+    /// * `Some(_)` represents the response to the message.
+    /// * `None` represents that no client exists by the uuid of the sender.
+    type Result = Option<Published>;
+}
+
+/// Response to a published message.
+#[derive(Debug)]
+pub struct Published {
+    /// The serialized response payload, as a client would receive it.
+    pub text: String,
+    /// Code of the error that the message failed with, if any.
+    pub error: Option<ErrorCode>,
+}
+
+/// Register a webhook, as a service owner or with the API token.
+#[derive(Debug)]
+pub struct RegisterWebhook {
+    /// Uuid of the service owner registering the webhook.
+    /// `None` if it was registered with the API token.
+    pub owner: Option<Uuid>,
+    pub registration: Registration<'static>,
+}
+impl Message for RegisterWebhook {
+    /// * `Some(Ok(_))` represents the webhook, along with its secret.
+    /// * `Some(Err(_))` represents the reason that the registration was refused.
+    /// * `None` represents that no client exists by the uuid of the owner.
+    type Result = Option<Result<Webhook<'static>, ErrorCode>>;
+}
+
+/// Remove a webhook, as a service owner or with the API token.
+#[derive(Debug)]
+pub struct UnregisterWebhook {
+    pub owner: Option<Uuid>,
+    pub id: Uuid,
+}
+impl Message for UnregisterWebhook {
+    /// * `Some(_)` represents if the webhook was removed.
+    /// * `None` represents that no client exists by the uuid of the owner.
+    type Result = Option<bool>;
+}
+
+/// List the webhooks of a service owner, or every one with the API token.
+#[derive(Debug)]
+pub struct ListWebhooks {
+    pub owner: Option<Uuid>,
+}
+impl Message for ListWebhooks {
+    /// * `None` represents that no client exists by the uuid of the owner.
+    type Result = Option<Vec<Webhook<'static>>>;
+}
+
+/// A client uploaded a file.
+/// Sent by the file routes.
+#[derive(Debug)]
+pub struct FileUploaded {
+    pub uuid: Uuid,
+    pub file: String,
+}
+impl Message for FileUploaded {
+    type Result = ();
+}
+
+/// The latency of a client was measured.
+/// Sent by the socket connection, on every heartbeat.
+#[derive(Debug)]
+pub struct UpdateLatency {
+    pub uuid: Uuid,
+    pub latency: info::Latency,
+}
+impl Message for UpdateLatency {
+    type Result = ();
+}
+
+/// A message payload routed to a plugin, handed over without serializing it.
+#[derive(Debug)]
+pub struct RoutedMessage {
+    pub origin: Origin<'static>,
+    pub data: Box<RawValue>,
+}
+impl Message for RoutedMessage {
+    type Result = ();
+}
+
+/// Register a plugin, which identifies as a client and owns the service by its name.
+/// Sent by the plugin hosts.
+#[derive(Debug)]
+pub struct RegisterPlugin {
+    pub name: String,
+    pub schemas: ServiceSchemas,
+    /// Recipient of the payloads that clients receive.
+    pub addr: Recipient<OutgoingMessage>,
+    /// Recipient of the messages routed to the plugin.
+    pub plugin: Recipient<RoutedMessage>,
+}
+impl Message for RegisterPlugin {
+    /// * `Some(_)` represents the uuid of the client of the plugin.
+    /// * `None` represents that the name is invalid, or that a client
+    ///   or a service already exists by the name.
+    type Result = Option<Uuid>;
+}
+
+/// A message that a plugin sends through its service.
+#[derive(Debug)]
+pub struct PluginMessage {
+    /// Uuid of the client of the plugin.
+    pub uuid: Uuid,
+    /// Subscriber that the message is sent to.
+    /// `None` if it is broadcast to every subscriber.
+    pub target: Option<Uuid>,
+    pub data: Box<RawValue>,
+}
+impl Message for PluginMessage {
+    /// * `Some(Err(_))` represents the error that the message failed with.
+    /// * `None` represents that no client exists by the uuid of the plugin.
+    type Result = Option<Result<(), ErrorCode>>;
+}
+
 /// Query for a client name with the associated UUID.
 /// Often used by file routes.
 pub struct QueryUuid {
@@ -60,6 +214,39 @@ impl Message for QueryUuid {
     type Result = Option<String>;
 }
 
+/// Truncate a sorted listing to a page of at most `limit` items.
+/// Returns the last item of the page if more items remain after it.
+fn paginate<T: Copy>(items: &mut Vec<T>, limit: Option<usize>) -> Option<T> {
+    match limit {
+        Some(limit) if items.len() > limit => {
+            items.truncate(limit);
+            items.last().copied()
+        }
+        _ => None,
+    }
+}
+
+/// Origin of the messages published with the API token, which are not
+/// sent by any client.
+fn api_origin() -> Origin<'static> {
+    info::Client {
+        name: "api".into(),
+        nickname: None,
+        uuid: Uuid::nil(),
+        tags: None,
+        presence: Default::default(),
+        latency: None,
+    }
+    .to_origin()
+}
+
+/// Milliseconds since the Unix epoch, as told by the clock of the server.
+pub(crate) fn now_millis() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |time| time.as_secs_f64() * 1000.0)
+}
+
 /// Central struct that stores the concierge data.
 pub struct Concierge {
     /// Services registered with the concierge.
@@ -69,48 +256,389 @@ pub struct Concierge {
     /// This is the mapping between UUID and Clients.
     /// Statistically, the 128-bit UUIDs generated will not collide.
     pub clients: HashMap<Uuid, Client>,
+    /// This is the mapping between wildcard patterns and the clients
+    /// subscribed to them. Used to subscribe clients to new services.
+    pub patterns: HashMap<String, HashSet<Uuid>>,
+    /// Directory under which the files of every client are stored.
+    pub fs_root: PathBuf,
+    /// Messages held for offline clients, by name.
+    pub queues: Queues,
+    /// Webhooks notified of events, by id.
+    pub webhooks: Webhooks,
 }
 
 impl Actor for Concierge {
     /// Using a simple Context, since we just need the ability
     /// to communicate with other actors.
     type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.run_interval(QUEUE_SWEEP_INTERVAL, |concierge, _| {
+            concierge.expire_queued()
+        });
+    }
 }
 
 impl Concierge {
-    /// Create a new concierge.
-    pub fn new() -> Self {
+    /// Create a new concierge, storing the files of clients under `fs_root`.
+    pub fn new(fs_root: PathBuf) -> Self {
         Concierge {
             services: HashMap::default(),
             namespace: HashMap::default(),
             clients: HashMap::default(),
+            patterns: HashMap::default(),
+            fs_root,
+            queues: Queues::new(crate::QUEUE_TTL, crate::QUEUE_CAPACITY),
+            webhooks: Webhooks::new(crate::WEBHOOK_RETRIES, crate::WEBHOOK_BACKOFF),
+        }
+    }
+
+    /// Hold messages for offline clients for `ttl`, and at most `capacity`
+    /// messages for each client.
+    pub fn with_queues(mut self, ttl: Duration, capacity: usize) -> Self {
+        self.queues = Queues::new(ttl, capacity);
+        self
+    }
+
+    /// Retry failed webhook deliveries `retries` times, waiting `backoff`
+    /// before the first retry and twice as long before every following one.
+    pub fn with_webhooks(mut self, retries: u32, backoff: Duration) -> Self {
+        self.webhooks.retries = retries;
+        self.webhooks.backoff = backoff;
+        self
+    }
+
+    /// Let webhooks post to loopback, private and link-local addresses,
+    /// which are refused by default.
+    pub fn with_private_webhooks(mut self, allowed: bool) -> Self {
+        self.webhooks.allow_private = allowed;
+        self
+    }
+
+    /// Post an event to the webhooks registered for it.
+    fn emit(&self, payload: WebhookPayload<'_>) {
+        let hooks = self
+            .webhooks
+            .wanting(&payload)
+            .map(|hook| (hook.url.clone(), hook.secret.clone()))
+            .collect::<Vec<_>>();
+        if hooks.is_empty() {
+            return;
+        }
+
+        let event = payload.event();
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_millis() as u64);
+        let body = serde_json::to_string(&webhook::Delivery {
+            id: Uuid::new_v4(),
+            time,
+            payload,
+        })
+        .expect("Serialization error");
+        for (url, secret) in hooks {
+            crate::webhook::Delivery {
+                url,
+                secret,
+                event,
+                body: body.clone(),
+                retries: self.webhooks.retries,
+                backoff: self.webhooks.backoff,
+                allow_private: self.webhooks.allow_private,
+            }
+            .spawn();
+        }
+    }
+
+    /// Notify the members of a service, and the webhooks, that a client
+    /// subscribed to it or unsubscribed from it.
+    fn subscription_changed(&self, service_name: &str, client: info::Client<'_>, subscribed: bool) {
+        let service = self.services.get(service_name).unwrap();
+        let (payload, hook_payload) = if subscribed {
+            (
+                PayloadOut::service_client_subscribed(client.clone(), service.info()),
+                WebhookPayload::ServiceSubscribed {
+                    client,
+                    service: service.info(),
+                },
+            )
+        } else {
+            (
+                PayloadOut::service_client_unsubscribed(client.clone(), service.info()),
+                WebhookPayload::ServiceUnsubscribed {
+                    client,
+                    service: service.info(),
+                },
+            )
+        };
+        service.broadcast(&self.clients, &payload, true);
+        self.emit(hook_payload);
+    }
+
+    /// Send a serialized event payload to all clients that receive its class.
+    fn broadcast(&self, class: EventClass, payload: &impl Serialize) {
+        let string = serde_json::to_string(payload).expect("Serialization error");
+        for client in self.clients.values().filter(|client| client.listens(class)) {
+            client.send_string(&string);
         }
     }
 
-    /// Send a serialized payload message to all clients.
-    fn broadcast(&self, payload: &impl Serialize) {
+    /// Send a serialized payload regarding a client to the clients interested
+    /// in it. This is everyone, unless the namespace is large, in which case
+    /// only the subscribers and owners of the client's services are notified.
+    fn broadcast_client_update(&self, client: &Client, payload: &impl Serialize) {
+        if self.clients.len() <= crate::LARGE_NAMESPACE {
+            return self.broadcast(EventClass::ClientUpdated, payload);
+        }
+
         let string = serde_json::to_string(payload).expect("Serialization error");
-        self.broadcast_string(&string)
+        client
+            .subscriptions
+            .iter()
+            .filter_map(|service_name| self.services.get(service_name))
+            .flat_map(|service| {
+                service
+                    .subscribers
+                    .iter()
+                    .chain(std::iter::once(&service.owner_uuid))
+            })
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .filter_map(|uuid| self.clients.get(uuid))
+            .filter(|target| target.listens(EventClass::ClientUpdated))
+            .for_each(|target| target.send_string(&string));
+    }
+
+    /// Forget the subscriptions and webhooks of a service that was removed,
+    /// and broadcast its deletion.
+    fn service_removed(&mut self, service: &Service) {
+        for uuid in &service.subscribers {
+            if let Some(client) = self.clients.get_mut(uuid) {
+                client.subscriptions.remove(&service.name);
+                client.pattern_subscriptions.remove(&service.name);
+            }
+        }
+        self.broadcast(
+            EventClass::ServiceDeleted,
+            &PayloadOut::service_delete_result(service.info()),
+        );
+        self.emit(WebhookPayload::ServiceDeleted {
+            service: service.info(),
+        });
+        self.webhooks.remove_service(&service.name);
+    }
+
+    /// Let the sender of a queued message know what became of it,
+    /// if it is still connected.
+    fn notify_queued(&self, name: &str, message: &Queued, status: DeliveryStatus) {
+        if let Some(sender) = message.sender.and_then(|uuid| self.clients.get(&uuid)) {
+            sender.send(&PayloadOut::MessageStatus {
+                name,
+                status,
+                message_seq: message.seq,
+                message_id: message.id.as_ref().map(QueuedId::get),
+            });
+        }
+    }
+
+    /// Deliver the messages held for a client that just identified, in order.
+    fn deliver_queued(&mut self, client_uuid: Uuid) {
+        let client = self.clients.get(&client_uuid).unwrap();
+        let (delivered, expired) = self.queues.take(&client.name, Instant::now());
+        for message in &expired {
+            self.notify_queued(&client.name, message, DeliveryStatus::Expired);
+        }
+        for message in &delivered {
+            client.send_string(&message.text);
+            self.notify_queued(&client.name, message, DeliveryStatus::Delivered);
+        }
+    }
+
+    /// Drop the held messages that expired, notifying their senders.
+    fn expire_queued(&mut self) {
+        for (name, message) in self.queues.expire(Instant::now()) {
+            self.notify_queued(&name, &message, DeliveryStatus::Expired);
+        }
     }
 
-    /// Send a message to all clients.
-    fn broadcast_string(&self, string: &str) {
-        for client in self.clients.values() {
-            let _ = client
-                .addr
-                .do_send(OutgoingMessage(WsMessage::Text(string.to_string())));
+    /// Subscribe a client to a wildcard pattern, along with every
+    /// existing service that matches it.
+    fn subscribe_pattern(
+        &mut self,
+        client_uuid: Uuid,
+        reply: Reply<'_>,
+        pattern: &str,
+        filter: Option<Filter>,
+    ) {
+        let client = self.clients.get_mut(&client_uuid).unwrap();
+        if !topic::is_valid_pattern(pattern) {
+            client.respond(PayloadOut::invalid_pattern(pattern), reply);
+            return;
+        }
+
+        let successful = client
+            .patterns
+            .insert(pattern.to_owned(), filter.clone())
+            .is_none();
+        self.patterns
+            .entry(pattern.to_owned())
+            .or_default()
+            .insert(client_uuid);
+
+        let service_names = self
+            .services
+            .keys()
+            .filter(|name| topic::matches(pattern, name))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut subscribed_names = Vec::new();
+        let mut service_infos = Vec::new();
+        for service_name in service_names {
+            if let Some((service_info, subscribed)) =
+                client.subscribe_through_pattern(&mut self.services, &service_name, filter.clone())
+            {
+                if subscribed {
+                    subscribed_names.push(service_name);
+                }
+                service_infos.push(service_info);
+            }
+        }
+
+        // Clients know they are subscribed before others.
+        client.respond(
+            PayloadOut::SelfSubscribePatternResult {
+                successful,
+                pattern,
+                services: service_infos,
+            },
+            reply,
+        );
+
+        // Notify others.
+        let client_info = client.info().owned();
+        for service_name in subscribed_names {
+            self.subscription_changed(&service_name, client_info.clone(), true);
+        }
+    }
+
+    /// Unsubscribe a client from a wildcard pattern, along with every
+    /// service that matches it.
+    fn unsubscribe_pattern(&mut self, client_uuid: Uuid, reply: Reply<'_>, pattern: &str) {
+        let client = self.clients.get_mut(&client_uuid).unwrap();
+        if !topic::is_valid_pattern(pattern) {
+            client.respond(PayloadOut::invalid_pattern(pattern), reply);
+            return;
+        }
+
+        let successful = client.patterns.remove(pattern).is_some();
+        if let Some(uuids) = self.patterns.get_mut(pattern) {
+            uuids.remove(&client_uuid);
+            if uuids.is_empty() {
+                self.patterns.remove(pattern);
+            }
+        }
+
+        // Direct subscriptions, and those still matching another pattern, are kept.
+        let service_names = client
+            .pattern_subscriptions
+            .iter()
+            .filter(|name| topic::matches(pattern, name))
+            .filter(|name| {
+                !client
+                    .patterns
+                    .keys()
+                    .any(|other| topic::matches(other, name))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut unsubscribed_names = Vec::new();
+        let mut service_infos = Vec::new();
+        for service_name in service_names {
+            if let Some((service_info, _)) = client.unsubscribe(&mut self.services, &service_name) {
+                unsubscribed_names.push(service_name);
+                service_infos.push(service_info);
+            }
+        }
+
+        // Clients know they are unsubscribed before others.
+        client.respond(
+            PayloadOut::SelfUnsubscribePatternResult {
+                successful,
+                pattern,
+                services: service_infos,
+            },
+            reply,
+        );
+
+        // Notify others.
+        let client_info = client.info().owned();
+        for service_name in unsubscribed_names {
+            self.subscription_changed(&service_name, client_info.clone(), false);
+        }
+    }
+
+    /// Subscribe the clients with a matching wildcard pattern to a new service.
+    fn subscribe_pattern_subscribers(&mut self, service_name: &str) {
+        let client_uuids = self
+            .patterns
+            .iter()
+            .filter(|(pattern, _)| topic::matches(pattern, service_name))
+            .flat_map(|(_, uuids)| uuids.iter().copied())
+            .collect::<HashSet<_>>();
+
+        for client_uuid in client_uuids {
+            if let Some(client) = self.clients.get_mut(&client_uuid) {
+                // Use the filter of the first matching pattern.
+                let filter = client
+                    .patterns
+                    .iter()
+                    .find(|(pattern, _)| topic::matches(pattern, service_name))
+                    .and_then(|(_, filter)| filter.clone());
+                if let Some((service_info, true)) =
+                    client.subscribe_through_pattern(&mut self.services, service_name, filter)
+                {
+                    // Clients know they are subscribed before others.
+                    client.send(&PayloadOut::self_subscribe_result(true, service_info));
+
+                    // Notify others.
+                    let client_info = client.info().owned();
+                    self.subscription_changed(service_name, client_info, true);
+                }
+            }
         }
     }
 
     /// Handle message payloads.
     fn handle_message<'a>(
-        &self,
+        &mut self,
         client_uuid: Uuid,
-        seq: usize,
+        received: f64,
+        reply: Reply<'a>,
         payload: PayloadMessage<'a, &'a serde_json::value::RawValue>,
     ) {
-        let client = self.clients.get(&client_uuid).unwrap();
-        let client_origin = client.info().to_origin();
+        let response = self.route_message(Some(client_uuid), received, reply, payload);
+        self.clients
+            .get(&client_uuid)
+            .unwrap()
+            .respond(response, reply);
+    }
+
+    /// Route a message payload received at `received`, returning the response
+    /// to its sender. Messages without a sender were published with the API token.
+    fn route_message<'a>(
+        &mut self,
+        sender: Option<Uuid>,
+        received: f64,
+        reply: Reply<'a>,
+        mut payload: PayloadMessage<'a, &'a serde_json::value::RawValue>,
+    ) -> PayloadOut<'a> {
+        let client_origin = match sender {
+            Some(uuid) => self.clients.get(&uuid).unwrap().info().to_origin(),
+            None => api_origin(),
+        }
+        .with_received(received);
+        // Like the id, the queue flag is not relayed to the targets.
+        let queue = std::mem::take(&mut payload.queue);
         match payload.target {
             Target::Name { name } => {
                 // Obtain the UUID from the namespace and send the payload.
@@ -119,19 +647,39 @@ impl Concierge {
                     .get(name)
                     .and_then(|id| self.clients.get(&id))
                 {
-                    target_client.send(&payload.with_origin(client_origin));
-                    client.send(&PayloadOut::Ok.seq(seq))
+                    target_client.send_message(payload.with_origin(client_origin));
+                    PayloadOut::Ok
+                } else if queue {
+                    // Hold the message until a client identifies by that name.
+                    let message = Queued {
+                        text: serde_json::to_string(&payload.with_origin(client_origin))
+                            .expect("Serialization error"),
+                        sender,
+                        seq: reply.seq,
+                        id: reply.id.map(QueuedId::new),
+                        expires: Instant::now() + self.queues.ttl,
+                    };
+                    if self.queues.push(name, message) {
+                        PayloadOut::MessageStatus {
+                            name,
+                            status: DeliveryStatus::Queued,
+                            message_seq: reply.seq,
+                            message_id: reply.id.cloned(),
+                        }
+                    } else {
+                        PayloadOut::queue_full(name)
+                    }
                 } else {
-                    client.send(&PayloadOut::invalid_name(name).seq(seq))
+                    PayloadOut::invalid_name(name)
                 }
             }
             Target::Uuid { uuid } => {
                 // Send the payload.
                 if let Some(target_client) = self.clients.get(&uuid) {
-                    target_client.send(&payload.with_origin(client_origin));
-                    client.send(&PayloadOut::Ok.seq(seq))
+                    target_client.send_message(payload.with_origin(client_origin));
+                    PayloadOut::Ok
                 } else {
-                    client.send(&PayloadOut::invalid_uuid(uuid).seq(seq))
+                    PayloadOut::invalid_uuid(uuid)
                 }
             }
             Target::Service {
@@ -140,25 +688,30 @@ impl Concierge {
                 // Find the service.
                 if let Some(service) = self.services.get(service_name) {
                     let origin = client_origin.with_service(service.info());
-                    if client_uuid == service.owner_uuid {
+                    let is_owner = sender == Some(service.owner_uuid);
+                    // Messages published with the API token may reach any service owner.
+                    let subscribed =
+                        sender.map_or(true, |uuid| service.subscribers.contains(&uuid));
+                    if !is_owner && !subscribed {
+                        // Client must be subscribed in order to send messages to the owner.
+                        PayloadOut::not_subscribed(service_name)
+                    } else if let Err(err) = service.validate_data(is_owner, payload.data) {
+                        // The data must satisfy the schemas of the service.
+                        PayloadOut::error(ErrorCode::Schema, err.desc, Some(err.path.into()))
+                    } else if is_owner {
                         // Owners are allowed to broadcast to the service.
                         // They will not get an echo of their own message.
-                        service.broadcast(&self.clients, &payload.with_origin(origin), false);
-                        client.send(&PayloadOut::Ok.seq(seq))
-                    } else if !service.subscribers.contains(&client_uuid) {
-                        // Client must be subscribed in order to send messages to the owner.
-                        client.send(&PayloadOut::Bad.seq(seq))
+                        service.broadcast_message(&self.clients, &payload.with_origin(origin));
+                        PayloadOut::Ok
                     } else if let Some(owner_client) = self.clients.get(&service.owner_uuid) {
                         // Other clients sending to the service will only send to the owner.
-                        owner_client.send(&payload.with_origin(origin));
-                        client.send(&PayloadOut::Ok.seq(seq))
+                        owner_client.send_message(payload.with_origin(origin));
+                        PayloadOut::Ok
                     } else {
-                        client.send(
-                            &PayloadOut::error_internal("Group owner does not exist").seq(seq),
-                        )
+                        PayloadOut::error_internal("Group owner does not exist")
                     }
                 } else {
-                    client.send(&PayloadOut::invalid_group(service_name).seq(seq))
+                    PayloadOut::invalid_group(service_name)
                 }
             }
             Target::ServiceClientUuid {
@@ -168,55 +721,73 @@ impl Concierge {
                 // Find the service.
                 if let Some(service) = self.services.get(service_name) {
                     // Only owners of a service are allowed to use this target.
-                    if client_uuid != service.owner_uuid {
-                        client.send(&PayloadOut::Bad.seq(seq))
+                    if sender != Some(service.owner_uuid) {
+                        PayloadOut::not_owner(service_name)
+                    } else if let Err(err) = service.validate_data(true, payload.data) {
+                        // The data must satisfy the schemas of the service.
+                        PayloadOut::error(ErrorCode::Schema, err.desc, Some(err.path.into()))
                     } else if let Some(target_client) = self.clients.get(&target_client_uuid) {
                         let origin = client_origin.with_service(service.info());
-                        target_client.send(&payload.with_origin(origin));
-                        client.send(&PayloadOut::Ok.seq(seq))
+                        target_client.send_message(payload.with_origin(origin));
+                        PayloadOut::Ok
                     } else {
-                        client.send(&PayloadOut::invalid_uuid(target_client_uuid).seq(seq))
+                        PayloadOut::invalid_uuid(target_client_uuid)
                     }
                 } else {
-                    client.send(&PayloadOut::invalid_group(service_name).seq(seq))
+                    PayloadOut::invalid_group(service_name)
                 }
             }
             Target::All => {
                 // Broadcast to all clients.
-                self.broadcast(&payload.with_origin(client_origin));
-                client.send(&PayloadOut::Ok.seq(seq))
+                let payload = payload.with_origin(client_origin);
+                let string = serde_json::to_string(&payload).expect("Serialization error");
+                for client in self.clients.values() {
+                    client.send_serialized_message(&payload, &string);
+                }
+                PayloadOut::Ok
             }
         }
     }
 
     /// Handles incoming JSON payloads.
-    fn handle_payload(&mut self, client_uuid: Uuid, payload: PayloadIn<'_>) {
-        let seq = self.clients.get(&client_uuid).unwrap().seq;
+    fn handle_payload(
+        &mut self,
+        client_uuid: Uuid,
+        received: f64,
+        reply: Reply<'_>,
+        payload: PayloadIn<'_>,
+    ) {
         match payload {
+            PayloadIn::SelfSubscribe {
+                service: pattern,
+                filter,
+            } if topic::is_pattern(pattern) => {
+                self.subscribe_pattern(client_uuid, reply, pattern, filter);
+            }
+            PayloadIn::SelfUnsubscribe { service: pattern } if topic::is_pattern(pattern) => {
+                self.unsubscribe_pattern(client_uuid, reply, pattern);
+            }
             PayloadIn::SelfSubscribe {
                 service: service_name,
+                filter,
             } => {
                 let client = self.clients.get_mut(&client_uuid).unwrap();
 
                 if let Some((service_info, successful)) =
-                    client.subscribe(&mut self.services, service_name)
+                    client.subscribe(&mut self.services, service_name, filter)
                 {
                     // Clients know they are subscribed before others.
-                    client.send(
-                        &PayloadOut::self_subscribe_result(successful, service_info).seq(seq),
+                    client.respond(
+                        PayloadOut::self_subscribe_result(successful, service_info),
+                        reply,
                     );
                     if successful {
                         // Notify others.
                         let client_info = client.info().owned();
-                        let service = self.services.get(service_name).unwrap();
-                        service.broadcast(
-                            &self.clients,
-                            &PayloadOut::service_client_subscribed(client_info, service.info()),
-                            true,
-                        );
+                        self.subscription_changed(service_name, client_info, true);
                     }
                 } else {
-                    client.send(&PayloadOut::invalid_group(service_name).seq(seq));
+                    client.respond(PayloadOut::invalid_group(service_name), reply);
                 }
             }
             PayloadIn::SelfUnsubscribe {
@@ -228,66 +799,122 @@ impl Concierge {
                     client.unsubscribe(&mut self.services, service_name)
                 {
                     // Clients know they are unsubscribed before others.
-                    client.send(
-                        &PayloadOut::self_unsubscribe_result(successful, service_info).seq(seq),
+                    client.respond(
+                        PayloadOut::self_unsubscribe_result(successful, service_info),
+                        reply,
                     );
                     if successful {
                         // Notify others.
                         let client_info = client.info().owned();
-                        let service = self.services.get(service_name).unwrap();
-                        service.broadcast(
-                            &self.clients,
-                            &PayloadOut::service_client_unsubscribed(client_info, service.info()),
-                            true,
-                        )
+                        self.subscription_changed(service_name, client_info, false);
                     }
                 } else {
-                    client.send(&PayloadOut::invalid_group(service_name).seq(seq));
+                    client.respond(PayloadOut::invalid_group(service_name), reply);
                 }
             }
             PayloadIn::SelfSetSeq { seq } => {
                 // Set the client's sequence number.
                 let client = self.clients.get_mut(&client_uuid).unwrap();
                 client.seq = seq;
-                client.send(&PayloadOut::Ok.seq(client.seq));
+                client.respond(PayloadOut::Ok, Reply { seq, ..reply });
+            }
+            PayloadIn::SelfSetEvents { events } => {
+                // Replace the client's event classes.
+                let client = self.clients.get_mut(&client_uuid).unwrap();
+                client.events = events.into_iter().collect();
+                client.respond(PayloadOut::Ok, reply);
+            }
+            PayloadIn::SelfUpdate {
+                nickname,
+                tags,
+                presence,
+            } => {
+                // Update the fields that were provided.
+                let client = self.clients.get_mut(&client_uuid).unwrap();
+                if let Some(nickname) = nickname {
+                    client.nickname = Some(nickname.to_owned());
+                }
+                if let Some(tags) = tags {
+                    client.tags = tags.into_iter().map(str::to_owned).collect();
+                }
+                if let Some(presence) = presence {
+                    client.presence = presence.owned();
+                }
+
+                // Notify others, then let the client know the result.
+                let client = self.clients.get(&client_uuid).unwrap();
+                let updated = PayloadOut::ClientUpdated {
+                    client: client.info(),
+                };
+                self.broadcast_client_update(client, &updated);
+                client.respond(updated, reply);
             }
             PayloadIn::ServiceCreate {
                 service: service_name,
                 nickname,
+                schemas,
             } => {
                 let client = self.clients.get(&client_uuid).unwrap();
+                if topic::is_pattern(service_name) {
+                    client.respond(PayloadOut::invalid_pattern(service_name), reply);
+                    return;
+                }
+
+                // Schemas must be usable for validation.
+                let checked = [
+                    ("inbound", &schemas.inbound),
+                    ("outbound", &schemas.outbound),
+                ]
+                .iter()
+                .filter_map(|(name, schema)| schema.as_ref().map(|schema| (name, schema)))
+                .try_for_each(|(name, schema)| {
+                    schema::check(schema)
+                        .map_err(|err| (format!("/schemas/{}{}", name, err.path), err))
+                });
+                if let Err((path, err)) = checked {
+                    client.respond(PayloadOut::error_schema(&path, &err.desc), reply);
+                    return;
+                }
+
                 let (service_info, successful) =
-                    client.try_create_service(&mut self.services, service_name, nickname);
+                    client.try_create_service(&mut self.services, service_name, nickname, schemas);
 
                 let created_result = PayloadOut::service_create_result(successful, service_info);
 
                 // Only broadcast service creation if successful. Client sees it also but
                 // it does not have a sequence number attached.
                 if successful {
-                    self.broadcast(&created_result);
+                    self.broadcast(EventClass::ServiceCreated, &created_result);
+                    self.emit(WebhookPayload::ServiceCreated {
+                        service: self.services.get(service_name).unwrap().info(),
+                    });
                 }
 
                 // Client gets to know the result.
-                client.send(&created_result.seq(seq));
+                client.respond(created_result, reply);
+
+                // Clients subscribed to matching patterns are subscribed to the new service.
+                if successful {
+                    self.subscribe_pattern_subscribers(service_name);
+                }
             }
             PayloadIn::ServiceDelete {
                 service: service_name,
             } => {
                 let client = self.clients.get(&client_uuid).unwrap();
-                if let Some(result) = client.try_remove_service(&mut self.services, service_name) {
-                    match result {
-                        Ok(service) => {
-                            // Broadcast successful deletion.
-                            let delete_result = PayloadOut::service_delete_result(service.info());
-                            self.broadcast(&delete_result);
-                            client.send(&delete_result.seq(seq));
-                        }
-                        Err(_) => {
-                            client.send(&PayloadOut::Bad);
-                        }
+                match client.try_remove_service(&mut self.services, service_name) {
+                    Some(Ok(service)) => {
+                        // Broadcast successful deletion.
+                        self.service_removed(&service);
+                        let client = self.clients.get(&client_uuid).unwrap();
+                        client.respond(PayloadOut::service_delete_result(service.info()), reply);
+                    }
+                    Some(Err(_)) => {
+                        client.respond(PayloadOut::not_owner(service_name), reply);
+                    }
+                    None => {
+                        client.respond(PayloadOut::invalid_group(service_name), reply);
                     }
-                } else {
-                    client.send(&PayloadOut::invalid_group(service_name).seq(seq));
                 }
             }
             PayloadIn::ServiceFetch {
@@ -296,31 +923,89 @@ impl Concierge {
                 let client = self.clients.get(&client_uuid).unwrap();
                 // Get and respond with service info.
                 if let Some(service) = self.services.get(service_name) {
-                    client.send(
-                        &PayloadOut::ServiceFetchResult {
+                    client.respond(
+                        PayloadOut::ServiceFetchResult {
                             service: service.info(),
-                        }
-                        .seq(seq),
+                        },
+                        reply,
                     );
                 } else {
-                    client.send(&PayloadOut::invalid_group(service_name).seq(seq));
+                    client.respond(PayloadOut::invalid_group(service_name), reply);
                 }
             }
-            PayloadIn::ClientFetchAll => {
-                // Respond with all client info.
+            PayloadIn::ClientFetchAll {
+                tag,
+                name_prefix,
+                service,
+                cursor,
+                limit,
+                fields,
+            } => {
+                // Respond with the info of matching clients, ordered by name.
+                let client = self.clients.get(&client_uuid).unwrap();
+                let mut clients = self
+                    .clients
+                    .values()
+                    .filter(|c| tag.map_or(true, |tag| c.tags.iter().any(|t| t == tag)))
+                    .filter(|c| name_prefix.map_or(true, |prefix| c.name.starts_with(prefix)))
+                    .filter(|c| service.map_or(true, |service| c.subscriptions.contains(service)))
+                    .filter(|c| cursor.map_or(true, |cursor| c.name.as_str() > cursor))
+                    .collect::<Vec<_>>();
+                clients.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+                let next = paginate(&mut clients, limit).map(|last| last.name.as_str());
+
+                let clients = clients
+                    .into_iter()
+                    .map(|c| c.info_projected(fields))
+                    .collect();
+                client.respond(PayloadOut::ClientFetchAllResult { clients, next }, reply);
+            }
+            PayloadIn::ServiceFetchSchemas {
+                service: service_name,
+            } => {
                 let client = self.clients.get(&client_uuid).unwrap();
-                let clients = self.clients.values().map(Client::info).collect::<Vec<_>>();
-                client.send(&PayloadOut::ClientFetchAllResult { clients }.seq(seq));
+                // Get and respond with the service schemas.
+                if let Some(service) = self.services.get(service_name) {
+                    client.respond(
+                        PayloadOut::ServiceFetchSchemasResult {
+                            service: service_name,
+                            schemas: service.schemas.clone(),
+                        },
+                        reply,
+                    );
+                } else {
+                    client.respond(PayloadOut::invalid_group(service_name), reply);
+                }
             }
-            PayloadIn::ServiceFetchAll => {
-                // Respond with all service info.
+            PayloadIn::ServiceFetchAll {
+                name_prefix,
+                owner_uuid,
+                cursor,
+                limit,
+                fields,
+            } => {
+                // Respond with the info of matching services, ordered by name.
                 let client = self.clients.get(&client_uuid).unwrap();
-                let service_infos = self.services.values().map(Service::info).collect();
-                client.send(
-                    &PayloadOut::ServiceFetchAllResult {
+                let mut services = self
+                    .services
+                    .values()
+                    .filter(|s| name_prefix.map_or(true, |prefix| s.name.starts_with(prefix)))
+                    .filter(|s| owner_uuid.map_or(true, |owner_uuid| s.owner_uuid == owner_uuid))
+                    .filter(|s| cursor.map_or(true, |cursor| s.name.as_str() > cursor))
+                    .collect::<Vec<_>>();
+                services.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+                let next = paginate(&mut services, limit).map(|last| last.name.as_str());
+
+                let service_infos = services
+                    .into_iter()
+                    .map(|s| s.info_projected(fields))
+                    .collect();
+                client.respond(
+                    PayloadOut::ServiceFetchAllResult {
                         services: service_infos,
-                    }
-                    .seq(seq),
+                        next,
+                    },
+                    reply,
                 );
             }
             PayloadIn::SelfFetch => {
@@ -332,12 +1017,12 @@ impl Concierge {
                     .filter_map(|id| self.services.get(id))
                     .map(Service::info)
                     .collect::<Vec<_>>();
-                client.send(
-                    &PayloadOut::SelfFetchResult {
+                client.respond(
+                    PayloadOut::SelfFetchResult {
                         client: client.info(),
                         subscriptions: service_infos,
-                    }
-                    .seq(seq),
+                    },
+                    reply,
                 )
             }
             PayloadIn::ClientFetch { uuid: target_uuid } => {
@@ -350,18 +1035,31 @@ impl Concierge {
                         .filter_map(|id| self.services.get(id))
                         .map(Service::info)
                         .collect::<Vec<_>>();
-                    client.send(
-                        &PayloadOut::ClientFetchResult {
+                    client.respond(
+                        PayloadOut::ClientFetchResult {
                             client: target_client.info(),
                             subscriptions: service_infos,
-                        }
-                        .seq(seq),
+                        },
+                        reply,
                     )
+                } else {
+                    client.respond(PayloadOut::invalid_uuid(target_uuid), reply)
                 }
             }
+            PayloadIn::TimeSync { client_time } => {
+                let client = self.clients.get(&client_uuid).unwrap();
+                client.respond(
+                    PayloadOut::TimeSyncResult {
+                        client_time,
+                        server_receive: received,
+                        server_transmit: now_millis(),
+                    },
+                    reply,
+                )
+            }
             _ => {
                 let client = self.clients.get(&client_uuid).unwrap();
-                client.send(&PayloadOut::ErrorUnsupported.seq(seq))
+                client.respond(PayloadOut::error_unsupported(), reply)
             }
         }
     }
@@ -387,13 +1085,31 @@ impl Handler<IdentifyPackage> for Concierge {
             name: msg.name,
             nickname: msg.nickname,
             seq: 0,
+            publish_seq: 0,
             tags: msg.tags,
+            presence: Default::default(),
             addr: msg.addr,
             subscriptions: HashSet::default(),
+            patterns: HashMap::default(),
+            pattern_subscriptions: HashSet::default(),
+            events: msg
+                .events
+                .unwrap_or_else(|| EventClass::ALL.to_vec())
+                .into_iter()
+                .collect(),
+            capabilities: msg.capabilities.iter().copied().collect(),
+            plugin: None,
+            latency: None,
         };
 
         // Broadcast client join to everyone.
-        self.broadcast(&PayloadOut::ClientJoined {
+        self.broadcast(
+            EventClass::ClientJoined,
+            &PayloadOut::ClientJoined {
+                client: client.info(),
+            },
+        );
+        self.emit(WebhookPayload::ClientJoined {
             client: client.info(),
         });
 
@@ -401,9 +1117,11 @@ impl Handler<IdentifyPackage> for Concierge {
         client.send(&PayloadOut::Hello {
             uuid,
             version: crate::VERSION,
+            capabilities: msg.capabilities,
         });
 
         self.clients.insert(uuid, client);
+        self.deliver_queued(uuid);
 
         debug!("Client (uuid: {}) registered.", uuid);
         MessageResult(Some(uuid))
@@ -420,6 +1138,16 @@ impl Handler<Disconnect> for Concierge {
 
             self.namespace.remove(&client.name);
 
+            // Remove the client from the pattern subscriptions.
+            for pattern in client.patterns.keys() {
+                if let Some(uuids) = self.patterns.get_mut(pattern) {
+                    uuids.remove(&client.uuid);
+                    if uuids.is_empty() {
+                        self.patterns.remove(pattern);
+                    }
+                }
+            }
+
             // Remove all services owned by this client.
             let removing_services = self
                 .services
@@ -432,13 +1160,15 @@ impl Handler<Disconnect> for Concierge {
                 // Safety: The service must exist since we have a write lock,
                 // and we just queried it's existence.
                 let service = self.services.remove(&service_name).unwrap();
-                self.broadcast(&PayloadOut::ServiceDeleteResult {
-                    service: service.info(),
-                });
+                self.service_removed(&service);
             }
 
             // Remove the client from all services
+            let mut unsubscribed = Vec::new();
             for service in self.services.values_mut() {
+                if service.subscribers.contains(&client.uuid) {
+                    unsubscribed.push(service.info().owned());
+                }
                 service.broadcast(
                     &self.clients,
                     &PayloadOut::ServiceClientUnsubscribed {
@@ -449,11 +1179,23 @@ impl Handler<Disconnect> for Concierge {
                 );
                 service.remove_subscriber(client.uuid);
             }
+            for service in unsubscribed {
+                self.emit(WebhookPayload::ServiceUnsubscribed {
+                    client: client.info(),
+                    service,
+                });
+            }
 
-            let _ = std::fs::remove_dir_all(crate::fs::base_path(&client.name));
+            let _ = std::fs::remove_dir_all(crate::fs::base_path(&self.fs_root, &client.name));
 
             // Broadcast client leave to all connecting clients.
-            self.broadcast(&PayloadOut::ClientLeft {
+            self.broadcast(
+                EventClass::ClientLeft,
+                &PayloadOut::ClientLeft {
+                    client: client.info(),
+                },
+            );
+            self.emit(WebhookPayload::ClientLeft {
                 client: client.info(),
             });
         }
@@ -464,25 +1206,42 @@ impl Handler<IncomingMessage> for Concierge {
     type Result = ();
 
     fn handle(&mut self, msg: IncomingMessage, _: &mut Context<Self>) {
-        let IncomingMessage { uuid, text } = msg;
+        let IncomingMessage {
+            uuid,
+            text,
+            received,
+        } = msg;
         trace!("Client (uuid: {}) sent message: {}", uuid, text);
 
-        let seq = self.clients.get(&uuid).unwrap().seq;
+        // The client may have disconnected while its message was in flight.
+        let seq = match self.clients.get(&uuid) {
+            Some(client) => client.seq,
+            None => return,
+        };
+
+        // Every payload may carry an id, which is echoed on its response.
+        let id = serde_json::from_str::<Request<IgnoredAny>>(&text)
+            .ok()
+            .and_then(|request| request.id);
+        let reply = Reply {
+            seq,
+            id: id.as_ref(),
+        };
 
         // Prioritize trying to parse messages (since they are the primary form of function).
         if let Ok(payload) = serde_json::from_str(&text) {
-            self.handle_message(uuid, seq, payload);
+            self.handle_message(uuid, received, reply, payload);
         } else {
             // Parse other payloads.
             match serde_json::from_str(&text) {
                 Ok(payload) => {
-                    self.handle_payload(uuid, payload);
+                    self.handle_payload(uuid, received, reply, payload);
                 }
                 Err(err) => {
                     self.clients
                         .get(&uuid)
                         .unwrap()
-                        .send(&PayloadOut::error_protocol(&err.to_string()).seq(seq));
+                        .respond(PayloadOut::error_protocol(&err.to_string()), reply);
                 }
             }
         }
@@ -491,6 +1250,216 @@ impl Handler<IncomingMessage> for Concierge {
     }
 }
 
+impl Handler<PublishMessage> for Concierge {
+    type Result = Option<Published>;
+
+    fn handle(&mut self, msg: PublishMessage, _: &mut Context<Self>) -> Self::Result {
+        let PublishMessage { sender, text } = msg;
+        trace!("Message published (sender: {:?}): {}", sender, text);
+
+        // Published messages have a sequence of their own, so that the counter
+        // that the sender's connection mirrors is left alone.
+        let seq = match sender {
+            Some(uuid) => self.clients.get(&uuid)?.publish_seq,
+            None => 0,
+        };
+        let id = serde_json::from_str::<Request<IgnoredAny>>(&text)
+            .ok()
+            .and_then(|request| request.id);
+        let reply = Reply {
+            seq,
+            id: id.as_ref(),
+        };
+
+        let response = match serde_json::from_str(&text) {
+            Ok(payload) => self.route_message(sender, now_millis(), reply, payload),
+            Err(err) => PayloadOut::error(ErrorCode::Protocol, err.to_string(), None),
+        };
+        let error = match &response {
+            PayloadOut::Error { code, .. } => Some(*code),
+            _ => None,
+        };
+        let text = serde_json::to_string(&response.reply(reply)).expect("Serialization error");
+
+        if let Some(uuid) = sender {
+            self.clients.get_mut(&uuid).unwrap().publish_seq += 1;
+        }
+        Some(Published { text, error })
+    }
+}
+
+impl Handler<RegisterWebhook> for Concierge {
+    type Result = Option<Result<Webhook<'static>, ErrorCode>>;
+
+    fn handle(&mut self, msg: RegisterWebhook, _: &mut Context<Self>) -> Self::Result {
+        let RegisterWebhook {
+            owner,
+            registration,
+        } = msg;
+        if let Some(uuid) = owner {
+            self.clients.get(&uuid)?;
+            // Service owners may only be notified of the events of their services.
+            let service = match registration.service.as_deref() {
+                Some(name) => self.services.get(name),
+                None => return Some(Err(ErrorCode::NotOwner)),
+            };
+            match service {
+                Some(service) if service.owner_uuid == uuid => {}
+                Some(_) => return Some(Err(ErrorCode::NotOwner)),
+                None => return Some(Err(ErrorCode::InvalidService)),
+            }
+            if !registration
+                .events
+                .iter()
+                .all(|event| WebhookEvent::SERVICE.contains(event))
+            {
+                return Some(Err(ErrorCode::NotOwner));
+            }
+        }
+
+        let hook = self.webhooks.insert(Hook {
+            id: Uuid::new_v4(),
+            url: registration.url.into_owned(),
+            secret: format!(
+                "{}{}",
+                Uuid::new_v4().to_simple(),
+                Uuid::new_v4().to_simple()
+            ),
+            events: registration.events.into_iter().collect(),
+            service: registration.service.map(|service| service.into_owned()),
+            owner,
+        });
+        info!("Webhook (id: {}) registered for {}.", hook.id, hook.url);
+        Some(Ok(hook.info(true).owned()))
+    }
+}
+
+impl Handler<UnregisterWebhook> for Concierge {
+    type Result = Option<bool>;
+
+    fn handle(&mut self, msg: UnregisterWebhook, _: &mut Context<Self>) -> Self::Result {
+        if let Some(uuid) = msg.owner {
+            self.clients.get(&uuid)?;
+        }
+        Some(self.webhooks.remove(msg.id, msg.owner))
+    }
+}
+
+impl Handler<ListWebhooks> for Concierge {
+    type Result = Option<Vec<Webhook<'static>>>;
+
+    fn handle(&mut self, msg: ListWebhooks, _: &mut Context<Self>) -> Self::Result {
+        if let Some(uuid) = msg.owner {
+            self.clients.get(&uuid)?;
+        }
+        Some(
+            self.webhooks
+                .visible(msg.owner)
+                .map(|hook| hook.info(false).owned())
+                .collect(),
+        )
+    }
+}
+
+impl Handler<FileUploaded> for Concierge {
+    type Result = ();
+
+    fn handle(&mut self, msg: FileUploaded, _: &mut Context<Self>) {
+        if let Some(client) = self.clients.get(&msg.uuid) {
+            self.emit(WebhookPayload::FileUploaded {
+                client: client.info(),
+                file: msg.file.into(),
+            });
+        }
+    }
+}
+
+impl Handler<UpdateLatency> for Concierge {
+    type Result = ();
+
+    fn handle(&mut self, msg: UpdateLatency, _: &mut Context<Self>) {
+        if let Some(client) = self.clients.get_mut(&msg.uuid) {
+            client.latency = Some(msg.latency);
+        }
+    }
+}
+
+impl Handler<RegisterPlugin> for Concierge {
+    type Result = Option<Uuid>;
+
+    fn handle(&mut self, msg: RegisterPlugin, ctx: &mut Context<Self>) -> Self::Result {
+        let RegisterPlugin {
+            name,
+            schemas,
+            addr,
+            plugin,
+        } = msg;
+        // The name must be valid for both the client and the service.
+        if topic::is_pattern(&name) || !ws::verify_name(&name) {
+            error!("Plugin {} can not register, its name is invalid.", name);
+            return None;
+        }
+        if self.services.contains_key(&name) {
+            return None;
+        }
+
+        // Plugins identify like any client, without receiving global events.
+        let identify = IdentifyPackage {
+            name: name.clone(),
+            nickname: None,
+            tags: vec!["plugin".to_owned()],
+            events: Some(Vec::new()),
+            capabilities: vec![Capability::Errors],
+            addr,
+        };
+        let uuid = Handler::<IdentifyPackage>::handle(self, identify, ctx).0?;
+        let client = self.clients.get_mut(&uuid).unwrap();
+        client.plugin = Some(plugin);
+
+        client.try_create_service(&mut self.services, &name, None, schemas);
+        // Plugins are subscribed to their own service, so that they are told
+        // when clients subscribe and unsubscribe. No one else is told of it.
+        let (service_info, _) = client.subscribe(&mut self.services, &name, None).unwrap();
+        self.broadcast(
+            EventClass::ServiceCreated,
+            &PayloadOut::service_create_result(true, service_info),
+        );
+        self.emit(WebhookPayload::ServiceCreated {
+            service: self.services.get(&name).unwrap().info(),
+        });
+        self.subscribe_pattern_subscribers(&name);
+        info!("Plugin {} (uuid: {}) registered.", name, uuid);
+        Some(uuid)
+    }
+}
+
+impl Handler<PluginMessage> for Concierge {
+    type Result = Option<Result<(), ErrorCode>>;
+
+    fn handle(&mut self, msg: PluginMessage, _: &mut Context<Self>) -> Self::Result {
+        let service = self.clients.get(&msg.uuid)?.name.clone();
+        let target = match msg.target {
+            Some(uuid) => Target::ServiceClientUuid {
+                service: &service,
+                uuid,
+            },
+            None => Target::Service { service: &service },
+        };
+
+        // Routed like the messages of any service owner.
+        let reply = Reply { seq: 0, id: None };
+        match self.route_message(
+            Some(msg.uuid),
+            now_millis(),
+            reply,
+            PayloadMessage::new(target, &*msg.data),
+        ) {
+            PayloadOut::Error { code, .. } => Some(Err(code)),
+            _ => Some(Ok(())),
+        }
+    }
+}
+
 impl Handler<QueryUuid> for Concierge {
     type Result = Option<String>;
 
@@ -0,0 +1,110 @@
+use concierge_api_rs::RequestId;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+/// Id of a queued message, owned since the message outlives its payload.
+#[derive(Clone, Debug)]
+pub enum QueuedId {
+    Number(u64),
+    String(String),
+}
+
+impl QueuedId {
+    pub fn new(id: &RequestId<'_>) -> Self {
+        match id {
+            RequestId::Number(number) => QueuedId::Number(*number),
+            RequestId::String(string) => QueuedId::String(string.to_string()),
+        }
+    }
+
+    pub fn get(&self) -> RequestId<'_> {
+        match self {
+            QueuedId::Number(number) => RequestId::Number(*number),
+            QueuedId::String(string) => RequestId::String(Cow::Borrowed(string)),
+        }
+    }
+}
+
+/// A message held for a client that is offline.
+#[derive(Debug)]
+pub struct Queued {
+    /// Serialized message payload, as it will be delivered.
+    pub text: String,
+    /// Uuid of the client that sent the message, if it was not
+    /// published with the API token.
+    pub sender: Option<Uuid>,
+    /// Sequence number of the message payload, for its statuses.
+    pub seq: usize,
+    /// Id of the message payload, if the sender chose one.
+    pub id: Option<QueuedId>,
+    /// Instant after which the message is no longer delivered.
+    pub expires: Instant,
+}
+
+/// Messages held for offline clients, by the name of the client.
+pub struct Queues {
+    queues: HashMap<String, VecDeque<Queued>>,
+    /// How long messages are held.
+    pub ttl: Duration,
+    /// Maximum number of messages held for one name.
+    pub capacity: usize,
+}
+
+impl Queues {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            queues: HashMap::default(),
+            ttl,
+            capacity,
+        }
+    }
+
+    /// Number of messages held for a name, including expired ones.
+    pub fn held(&self, name: &str) -> usize {
+        self.queues.get(name).map_or(0, VecDeque::len)
+    }
+
+    /// Hold a message for a name, unless its queue is full.
+    /// Returns if the message was queued.
+    pub fn push(&mut self, name: &str, message: Queued) -> bool {
+        let queue = self.queues.entry(name.to_owned()).or_default();
+        if queue.len() >= self.capacity {
+            if queue.is_empty() {
+                self.queues.remove(name);
+            }
+            return false;
+        }
+        queue.push_back(message);
+        true
+    }
+
+    /// Take the messages held for a name, in order. Messages that expired
+    /// are returned separately.
+    pub fn take(&mut self, name: &str, now: Instant) -> (Vec<Queued>, Vec<Queued>) {
+        self.queues
+            .remove(name)
+            .unwrap_or_default()
+            .into_iter()
+            .partition(|message| message.expires > now)
+    }
+
+    /// Remove the messages that expired, along with the names they were held for.
+    pub fn expire(&mut self, now: Instant) -> Vec<(String, Queued)> {
+        let mut expired = Vec::new();
+        for (name, queue) in self.queues.iter_mut() {
+            // Messages expire in order, since they are held for the same duration.
+            while queue
+                .front()
+                .map_or(false, |message| message.expires <= now)
+            {
+                expired.push((name.clone(), queue.pop_front().unwrap()));
+            }
+        }
+        self.queues.retain(|_, queue| !queue.is_empty());
+        expired
+    }
+}
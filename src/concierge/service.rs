@@ -1,6 +1,12 @@
 use super::client::Client;
-use concierge_api_rs::info;
+use concierge_api_rs::{
+    info,
+    query::{ServiceProjection, SubscribersProjection},
+    schema::{self, SchemaError, ServiceSchemas},
+    Filter, PayloadMessage,
+};
 use serde::Serialize;
+use serde_json::{value::RawValue, Value};
 use std::{borrow::Cow, collections::{HashMap, HashSet}};
 use uuid::Uuid;
 
@@ -14,16 +20,27 @@ pub struct Service {
     pub owner_uuid: Uuid,
     // Subscriber UUIDs.
     pub subscribers: HashSet<Uuid>,
+    // Content filters of the subscribers that attached one.
+    pub filters: HashMap<Uuid, Filter>,
+    // JSON Schemas of the message data.
+    pub schemas: ServiceSchemas,
 }
 
 impl Service {
     /// Create a new group associated with an owner uuid.
-    pub fn new(name: String, nickname: Option<String>, owner_uuid: Uuid) -> Self {
+    pub fn new(
+        name: String,
+        nickname: Option<String>,
+        owner_uuid: Uuid,
+        schemas: ServiceSchemas,
+    ) -> Self {
         Self {
             name,
             nickname,
             owner_uuid,
             subscribers: HashSet::new(),
+            filters: HashMap::new(),
+            schemas,
         }
     }
 
@@ -33,17 +50,62 @@ impl Service {
             name: Cow::Borrowed(&self.name),
             nickname: self.nickname.as_deref().map(Cow::Borrowed),
             owner_uuid: self.owner_uuid,
-            subscribers: self.subscribers.iter().copied().collect::<Vec<_>>(),
+            subscribers: Some(self.subscribers.iter().copied().collect::<Vec<_>>()),
+            subscriber_count: None,
         }
     }
 
-    /// Add the client to the group.
-    pub fn add_subscriber(&mut self, uuid: Uuid) -> bool {
+    /// Construct service information with only the fields of a projection.
+    pub fn info_projected(&self, fields: ServiceProjection) -> info::Service<'_> {
+        let (subscribers, subscriber_count) = match fields.subscribers {
+            SubscribersProjection::List => (
+                Some(self.subscribers.iter().copied().collect::<Vec<_>>()),
+                None,
+            ),
+            SubscribersProjection::Count => (None, Some(self.subscribers.len())),
+            SubscribersProjection::Omit => (None, None),
+        };
+
+        info::Service {
+            name: Cow::Borrowed(&self.name),
+            nickname: self.nickname.as_deref().map(Cow::Borrowed),
+            owner_uuid: self.owner_uuid,
+            subscribers,
+            subscriber_count,
+        }
+    }
+
+    /// Validate the data of a message sent to the service against its schemas.
+    /// Messages from the owner are outbound, and other messages are inbound.
+    pub fn validate_data(&self, from_owner: bool, data: &RawValue) -> Result<(), SchemaError> {
+        let schema = if from_owner {
+            &self.schemas.outbound
+        } else {
+            &self.schemas.inbound
+        };
+
+        if let Some(schema) = schema {
+            let data: Value = serde_json::from_str(data.get()).map_err(|err| SchemaError {
+                path: String::new(),
+                desc: err.to_string(),
+            })?;
+            schema::validate(schema, &data)?;
+        }
+        Ok(())
+    }
+
+    /// Add the client to the group, replacing its content filter.
+    pub fn add_subscriber(&mut self, uuid: Uuid, filter: Option<Filter>) -> bool {
+        match filter {
+            Some(filter) => self.filters.insert(uuid, filter),
+            None => self.filters.remove(&uuid),
+        };
         self.subscribers.insert(uuid)
     }
 
     /// Remove the client from the group.
     pub fn remove_subscriber(&mut self, uuid: Uuid) -> bool {
+        self.filters.remove(&uuid);
         self.subscribers.remove(&uuid)
     }
 
@@ -65,4 +127,28 @@ impl Service {
                 client.send_string(&string);
             });
     }
+
+    /// Broadcast a message payload of the owner to the subscribers whose
+    /// content filter is satisfied by the message data.
+    pub fn broadcast_message(&self, clients: &HashMap<Uuid, Client>, payload: &PayloadMessage<'_, &RawValue>) {
+        // Only decode the data if someone needs it.
+        let data = if self.filters.is_empty() {
+            None
+        } else {
+            serde_json::from_str::<Value>(payload.data.get()).ok()
+        };
+
+        let string = serde_json::to_string(payload).expect("Serialization");
+        self.subscribers
+            .iter()
+            .filter(|client_uuid| **client_uuid != self.owner_uuid)
+            .filter(|client_uuid| match (&data, self.filters.get(client_uuid)) {
+                (Some(data), Some(filter)) => filter.matches(data),
+                _ => true,
+            })
+            .filter_map(|client_uuid| clients.get(client_uuid))
+            .for_each(|client| {
+                client.send_serialized_message(payload, &string);
+            });
+    }
 }
@@ -0,0 +1,111 @@
+use concierge_api_rs::webhook::{Webhook, WebhookEvent, WebhookPayload};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+use uuid::Uuid;
+
+/// A webhook registered with the concierge.
+#[derive(Debug)]
+pub struct Hook {
+    pub id: Uuid,
+    /// URL that deliveries are posted to.
+    pub url: String,
+    /// Secret with which deliveries are signed.
+    pub secret: String,
+    pub events: HashSet<WebhookEvent>,
+    /// Only events regarding this service are delivered, if set.
+    pub service: Option<String>,
+    /// Uuid of the service owner that registered the webhook,
+    /// `None` if it was registered with the API token.
+    pub owner: Option<Uuid>,
+}
+
+impl Hook {
+    /// Check if the webhook is registered for a payload.
+    pub fn wants(&self, payload: &WebhookPayload) -> bool {
+        self.events.contains(&payload.event())
+            && self
+                .service
+                .as_deref()
+                .map_or(true, |service| payload.service() == Some(service))
+    }
+
+    /// Information of the webhook, with its secret if asked for.
+    pub fn info(&self, secret: bool) -> Webhook<'_> {
+        let mut events = self.events.iter().copied().collect::<Vec<_>>();
+        events.sort_by_key(|event| *event as u8);
+        Webhook {
+            id: self.id,
+            url: Cow::Borrowed(&self.url),
+            events,
+            service: self.service.as_deref().map(Cow::Borrowed),
+            secret: if secret {
+                Some(Cow::Borrowed(&self.secret))
+            } else {
+                None
+            },
+        }
+    }
+}
+
+/// Webhooks registered with the concierge, by id.
+pub struct Webhooks {
+    hooks: HashMap<Uuid, Hook>,
+    /// How many times a failed delivery is retried.
+    pub retries: u32,
+    /// Delay before the first retry, doubled for every following one.
+    pub backoff: Duration,
+    /// Deliver to loopback, private and link-local addresses.
+    pub allow_private: bool,
+}
+
+impl Webhooks {
+    pub fn new(retries: u32, backoff: Duration) -> Self {
+        Self {
+            hooks: HashMap::default(),
+            retries,
+            backoff,
+            allow_private: false,
+        }
+    }
+
+    pub fn insert(&mut self, hook: Hook) -> &Hook {
+        self.hooks.entry(hook.id).or_insert(hook)
+    }
+
+    /// Remove a webhook, if the owner registered it. Webhooks registered
+    /// with the API token can only be removed with it.
+    pub fn remove(&mut self, id: Uuid, owner: Option<Uuid>) -> bool {
+        match self.hooks.get(&id) {
+            Some(hook) if owner.is_none() || hook.owner == owner => {
+                self.hooks.remove(&id);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The webhooks that an owner can see, every one for the API token.
+    pub fn visible(&self, owner: Option<Uuid>) -> impl Iterator<Item = &Hook> {
+        self.hooks
+            .values()
+            .filter(move |hook| owner.is_none() || hook.owner == owner)
+    }
+
+    /// The webhooks registered for a payload.
+    pub fn wanting<'a>(
+        &'a self,
+        payload: &'a WebhookPayload<'a>,
+    ) -> impl Iterator<Item = &'a Hook> + 'a {
+        self.hooks.values().filter(move |hook| hook.wants(payload))
+    }
+
+    /// Forget the webhooks that service owners registered for a service,
+    /// which was deleted.
+    pub fn remove_service(&mut self, service: &str) {
+        self.hooks
+            .retain(|_, hook| hook.owner.is_none() || hook.service.as_deref() != Some(service));
+    }
+}
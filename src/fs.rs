@@ -1,4 +1,7 @@
-use crate::concierge::{Concierge, QueryUuid};
+use crate::{
+    concierge::{Concierge, FileUploaded, QueryUuid},
+    Config,
+};
 use actix::prelude::*;
 use actix_files::NamedFile;
 use actix_multipart::Multipart;
@@ -11,7 +14,13 @@ use actix_web::{
 };
 use futures::{StreamExt, TryStreamExt};
 use std::io::Write;
-use std::{collections::HashSet, fs::OpenOptions, path::PathBuf, str::FromStr};
+use std::{
+    collections::HashSet,
+    fs::OpenOptions,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use uuid::Uuid;
 
 pub const FS_KEY_HEADER: &str = "x-fs-key";
 
@@ -29,7 +38,6 @@ fn extract_header<T: FromStr>(req: &HttpRequest) -> Result<T, FsError> {
 /// File system errors.
 #[derive(thiserror::Error, Debug)]
 pub enum FsError {
-    #[allow(dead_code)]
     #[error("Unknown file system error")]
     Unknown,
     #[error("Encoding error")]
@@ -64,13 +72,16 @@ impl ResponseError for FsError {
     }
 }
 
-/// The base file path.
-pub fn base_path(name: &str) -> PathBuf {
-    let mut buf = PathBuf::new();
-    buf.push(".");
-    buf.push("fs");
-    buf.push(name);
-    buf
+/// Query the name of the client that a file key belongs to.
+async fn query_name(srv: &Addr<Concierge>, uuid: Uuid) -> Result<Option<String>, FsError> {
+    srv.send(QueryUuid { uuid })
+        .await
+        .map_err(|_| FsError::Unknown)
+}
+
+/// The base file path of a client, under the file system root.
+pub fn base_path(root: &Path, name: &str) -> PathBuf {
+    root.join(name)
 }
 
 /// Handler for the /fs/{name}/{file_name} GET route.
@@ -78,12 +89,13 @@ pub async fn get(
     path: web::Path<(String, String)>,
     req: HttpRequest,
     srv: web::Data<Addr<Concierge>>,
+    config: web::Data<Config>,
 ) -> Result<impl Responder, Error> {
     // Get the file key.
     let uuid = extract_header(&req)?;
 
     // Query the server that this UUID leads to a named client.
-    let query = srv.send(QueryUuid { uuid }).await.unwrap().is_some();
+    let query = query_name(&srv, uuid).await?.is_some();
 
     if !query {
         // Reject if there is no name with that uuid.
@@ -94,7 +106,7 @@ pub async fn get(
     let path_tail = sanitize_filename::sanitize(&path.1);
 
     // Construct the file path.
-    let file_path = base_path(&path_name).join(path_tail);
+    let file_path = base_path(&config.fs_root, &path_name).join(path_tail);
     let file = NamedFile::open(file_path)?;
 
     Ok(file
@@ -110,13 +122,14 @@ pub async fn multipart_upload_single(
     path: web::Path<(String, String)>,
     req: HttpRequest,
     srv: web::Data<Addr<Concierge>>,
+    config: web::Data<Config>,
     mut payload: Multipart,
 ) -> Result<impl Responder, Error> {
     // Get the file key.
     let uuid = extract_header(&req)?;
 
     // Query the server that this UUID leads to a named client.
-    let query: Option<String> = srv.send(QueryUuid { uuid }).await.unwrap();
+    let query = query_name(&srv, uuid).await?;
 
     let client_name = query.ok_or(FsError::BadAuthorization)?;
 
@@ -128,7 +141,7 @@ pub async fn multipart_upload_single(
         return Err(FsError::Forbidden.into());
     }
 
-    let base_path = base_path(&path_name);
+    let base_path = base_path(&config.fs_root, &path_name);
     let file_path = base_path.join(&path_tail);
 
     // Submit blocking operations to threadpool
@@ -162,12 +175,16 @@ pub async fn multipart_upload_single(
         }
 
         while let Some(chunk) = field.next().await {
-            let data = chunk.unwrap();
+            let data = chunk?;
             // filesystem operations are blocking, we have to use threadpool
             f = web::block(move || f.write_all(&data).map(|_| f)).await?;
         }
     }
 
+    srv.do_send(FileUploaded {
+        uuid,
+        file: path_tail,
+    });
     Ok(HttpResponse::Created())
 }
 
@@ -176,12 +193,13 @@ pub async fn multipart_upload_multi(
     path: web::Path<String>,
     req: HttpRequest,
     srv: web::Data<Addr<Concierge>>,
+    config: web::Data<Config>,
     mut payload: Multipart,
 ) -> Result<impl Responder, Error> {
     // Get the file key.
     let uuid = extract_header(&req)?;
 
-    let query: Option<String> = srv.send(QueryUuid { uuid }).await.unwrap();
+    let query = query_name(&srv, uuid).await?;
 
     let client_name = query.ok_or(FsError::BadAuthorization)?;
     let path_name = path.to_string();
@@ -202,7 +220,8 @@ pub async fn multipart_upload_multi(
             .get_filename()
             .ok_or(FsError::ContentDispositionFileNameMissing)?;
 
-        let file_path = base_path(&path_name).join(sanitize_filename::sanitize(&file_name));
+        let file_path =
+            base_path(&config.fs_root, &path_name).join(sanitize_filename::sanitize(&file_name));
 
         let mut f = if file_list.contains(file_name) {
             web::block(|| OpenOptions::new().append(true).open(file_path)).await
@@ -219,12 +238,18 @@ pub async fn multipart_upload_multi(
         }?;
 
         while let Some(chunk) = field.next().await {
-            let data = chunk.unwrap();
+            let data = chunk?;
             // filesystem operations are blocking, we have to use threadpool
             f = web::block(move || f.write_all(&data).map(|_| f)).await?;
         }
     }
 
+    for file in file_list {
+        srv.do_send(FileUploaded {
+            uuid,
+            file: sanitize_filename::sanitize(&file),
+        });
+    }
     Ok(HttpResponse::Created())
 }
 
@@ -233,12 +258,13 @@ pub async fn delete(
     path: web::Path<(String, String)>,
     req: HttpRequest,
     srv: web::Data<Addr<Concierge>>,
+    config: web::Data<Config>,
 ) -> Result<impl Responder, Error> {
     // Get the file key.
     let uuid = extract_header(&req)?;
 
     // Query the server that this UUID leads to a named client.
-    let query: Option<String> = srv.send(QueryUuid { uuid }).await.unwrap();
+    let query = query_name(&srv, uuid).await?;
 
     let client_name = query.ok_or(FsError::BadAuthorization)?;
     let path_name = &path.0;
@@ -250,7 +276,7 @@ pub async fn delete(
 
     let tail = sanitize_filename::sanitize(&path.1);
 
-    let file_path = base_path(&path_name).join(tail);
+    let file_path = base_path(&config.fs_root, &path_name).join(tail);
 
     web::block(|| std::fs::remove_file(file_path)).await?;
 
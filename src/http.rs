@@ -0,0 +1,504 @@
+//! HTTP transport, for networks that block websocket upgrades.
+//!
+//! A client identifies by posting its `IDENTIFY` payload to `/http/session`,
+//! which responds with `HELLO` and a random token in the [`SESSION_HEADER`]
+//! header. The token names the session, rather than the uuid of the client,
+//! which every other client can learn: the client posts its payloads to
+//! `/http/session/{token}`, and receives the payloads of the concierge either
+//! as Server-Sent Events from `/http/session/{token}/events`, or by
+//! long-polling `/http/session/{token}/poll`. Sessions that are neither
+//! streamed nor polled for longer than the client timeout are disconnected.
+//!
+//! Sessions are recorded like websocket connections, with the posted text
+//! as the frames of the client, so that recordings replay the same way.
+
+use crate::{
+    concierge::{Concierge, Disconnect, IdentifyPackage, OutgoingMessage},
+    record::{self, Record, Recorder},
+    ws, Config,
+};
+use actix::prelude::*;
+use actix_web::{
+    http::{header, StatusCode},
+    web::{self, Bytes},
+    Error, HttpResponse, ResponseError,
+};
+use actix_web_actors::ws::Message as WsMessage;
+use concierge_api_rs::{record::Event, Capability, CloseReason, PayloadIn};
+use futures::{
+    channel::{mpsc, oneshot},
+    StreamExt,
+};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+use uuid::Uuid;
+
+/// Header of the identification response that holds the session token.
+pub const SESSION_HEADER: &str = "x-session-token";
+
+/// Sent on event streams every heartbeat, to detect clients that are gone.
+const KEEP_ALIVE: &[u8] = b": keep-alive\n\n";
+
+/// HTTP transport errors.
+#[derive(thiserror::Error, Debug)]
+pub enum HttpError {
+    #[error("No session by that token")]
+    UnknownSession,
+    #[error("Session closed")]
+    Closed,
+    /// Identification failed, for the same reason that a websocket
+    /// connection would be closed with.
+    #[error("{reason} ({code})")]
+    Rejected {
+        code: u16,
+        reason: Cow<'static, str>,
+    },
+}
+
+impl From<CloseReason<'static>> for HttpError {
+    fn from(reason: CloseReason<'static>) -> Self {
+        let (code, reason) = reason.as_tuple();
+        HttpError::Rejected { code, reason }
+    }
+}
+
+impl ResponseError for HttpError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            HttpError::UnknownSession => StatusCode::NOT_FOUND,
+            HttpError::Closed => StatusCode::GONE,
+            HttpError::Rejected { code, .. } if *code == CloseReason::BAD_SECRET.code => {
+                StatusCode::UNAUTHORIZED
+            }
+            HttpError::Rejected { code, .. } if *code == CloseReason::DUPLICATE_AUTH.code => {
+                StatusCode::CONFLICT
+            }
+            HttpError::Rejected { .. } => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+/// Sessions of the HTTP transport, by their token.
+/// Clones share the same sessions, across every worker.
+#[derive(Clone, Default)]
+pub struct Sessions(Arc<Mutex<HashMap<Uuid, Addr<HttpSession>>>>);
+
+impl Sessions {
+    fn get(&self, token: Uuid) -> Result<Addr<HttpSession>, HttpError> {
+        self.0
+            .lock()
+            .unwrap()
+            .get(&token)
+            .cloned()
+            .ok_or(HttpError::UnknownSession)
+    }
+
+    fn insert(&self, token: Uuid, session: Addr<HttpSession>) {
+        self.0.lock().unwrap().insert(token, session);
+    }
+
+    fn remove(&self, token: Uuid) {
+        self.0.lock().unwrap().remove(&token);
+    }
+}
+
+/// A client connected over HTTP. Payloads of the concierge are held until
+/// the client streams or polls them.
+pub struct HttpSession {
+    /// Uuid of the client, nil until identified.
+    uuid: Uuid,
+    /// Secret token that the routes of the session are addressed by.
+    token: Uuid,
+    c_addr: Addr<Concierge>,
+    capabilities: Vec<Capability>,
+    config: web::Data<Config>,
+    sessions: Sessions,
+    /// Payloads that the client has not received yet.
+    pending: VecDeque<String>,
+    /// Event stream of the client, if attached.
+    stream: Option<mpsc::UnboundedSender<Bytes>>,
+    /// Long-poll of the client waiting for payloads, if any,
+    /// along with the handle of its timeout.
+    poll: Option<(oneshot::Sender<Vec<String>>, SpawnHandle)>,
+    /// Last time that the client was heard from.
+    last_seen: Instant,
+    /// Number of the session in recordings.
+    conn: u64,
+    /// Recorder of the payloads exchanged, if recording.
+    recorder: Option<Addr<Recorder>>,
+    /// Code of the reason that the identification was rejected with, if it was.
+    close_code: Option<u16>,
+}
+
+impl Actor for HttpSession {
+    type Context = Context<Self>;
+
+    fn started(&mut self, _: &mut Self::Context) {
+        self.record(|| Event::Open);
+    }
+
+    fn stopping(&mut self, _: &mut Self::Context) -> Running {
+        if !self.uuid.is_nil() {
+            self.sessions.remove(self.token);
+            self.c_addr.do_send(Disconnect { uuid: self.uuid });
+        }
+        let code = self.close_code;
+        self.record(|| Event::Close { code });
+        Running::Stop
+    }
+}
+
+/// Format a payload as a server-sent event. Raw data may span lines,
+/// so every line is sent as a data field of the same event.
+fn event(text: &str) -> Bytes {
+    let mut event = String::with_capacity(text.len() + 8);
+    for line in text.lines() {
+        event.push_str("data: ");
+        event.push_str(line);
+        event.push('\n');
+    }
+    event.push('\n');
+    event.into()
+}
+
+impl HttpSession {
+    /// Record an event of the session, if recording.
+    fn record(&self, event: impl FnOnce() -> Event<'static>) {
+        if let Some(recorder) = &self.recorder {
+            recorder.do_send(Record {
+                conn: self.conn,
+                at: Instant::now(),
+                event: event(),
+            });
+        }
+    }
+
+    /// Deliver a payload to the client, or hold it until the client asks.
+    fn push(&mut self, text: String, ctx: &mut Context<Self>) {
+        if let Some((poll, timeout)) = self.poll.take() {
+            ctx.cancel_future(timeout);
+            // The poll was abandoned if it cannot be answered.
+            if let Err(texts) = poll.send(vec![text]) {
+                self.pending.extend(texts);
+            }
+            return;
+        }
+        if let Some(stream) = &self.stream {
+            if stream.unbounded_send(event(&text)).is_ok() {
+                return;
+            }
+            self.stream = None;
+        }
+        self.pending.push_back(text);
+    }
+
+    /// Check that the client is still there, disconnecting it otherwise.
+    fn heartbeat(&mut self, ctx: &mut Context<Self>) {
+        if let Some(stream) = &self.stream {
+            if stream
+                .unbounded_send(Bytes::from_static(KEEP_ALIVE))
+                .is_ok()
+            {
+                self.last_seen = Instant::now();
+            } else {
+                self.stream = None;
+            }
+        }
+        if let Some((poll, _)) = &self.poll {
+            if !poll.is_canceled() {
+                self.last_seen = Instant::now();
+            }
+        }
+        if self.last_seen.elapsed() > self.config.client_timeout {
+            ctx.stop();
+        }
+    }
+}
+
+/// Payloads of the concierge.
+impl Handler<OutgoingMessage> for HttpSession {
+    type Result = ();
+
+    fn handle(&mut self, msg: OutgoingMessage, ctx: &mut Self::Context) {
+        if let WsMessage::Text(text) = msg.0 {
+            self.record(|| Event::Out {
+                text: text.clone().into(),
+            });
+            self.push(text, ctx);
+        }
+    }
+}
+
+/// Text that the client identifies with.
+struct Identifying(String);
+impl Message for Identifying {
+    type Result = ();
+}
+
+impl Handler<Identifying> for HttpSession {
+    type Result = ();
+
+    fn handle(&mut self, msg: Identifying, _: &mut Self::Context) {
+        self.record(|| Event::In { text: msg.0.into() });
+    }
+}
+
+/// The identification of the client was rejected for a reason.
+struct Rejected {
+    code: u16,
+}
+impl Message for Rejected {
+    type Result = ();
+}
+
+impl Handler<Rejected> for HttpSession {
+    type Result = ();
+
+    fn handle(&mut self, msg: Rejected, ctx: &mut Self::Context) {
+        self.close_code = Some(msg.code);
+        ctx.stop();
+    }
+}
+
+/// The concierge accepted the identification of the client.
+struct Identified {
+    uuid: Uuid,
+    capabilities: Vec<Capability>,
+}
+impl Message for Identified {
+    /// The `HELLO` payload, which the concierge sends first.
+    type Result = Option<String>;
+}
+
+impl Handler<Identified> for HttpSession {
+    type Result = Option<String>;
+
+    fn handle(&mut self, msg: Identified, ctx: &mut Self::Context) -> Self::Result {
+        self.uuid = msg.uuid;
+        self.capabilities = msg.capabilities;
+        self.last_seen = Instant::now();
+        self.sessions.insert(self.token, ctx.address());
+        ctx.run_interval(self.config.heartbeat_interval, Self::heartbeat);
+        self.pending.pop_front()
+    }
+}
+
+/// Attach an event stream, replacing the previous one.
+struct Attach;
+impl Message for Attach {
+    type Result = mpsc::UnboundedReceiver<Bytes>;
+}
+
+impl Handler<Attach> for HttpSession {
+    type Result = MessageResult<Attach>;
+
+    fn handle(&mut self, _: Attach, _: &mut Self::Context) -> Self::Result {
+        self.last_seen = Instant::now();
+        let (tx, rx) = mpsc::unbounded();
+        for text in self.pending.drain(..) {
+            let _ = tx.unbounded_send(event(&text));
+        }
+        self.stream = Some(tx);
+        MessageResult(rx)
+    }
+}
+
+/// Wait for payloads, for one heartbeat interval at most.
+struct Poll;
+impl Message for Poll {
+    type Result = oneshot::Receiver<Vec<String>>;
+}
+
+impl Handler<Poll> for HttpSession {
+    type Result = MessageResult<Poll>;
+
+    fn handle(&mut self, _: Poll, ctx: &mut Self::Context) -> Self::Result {
+        self.last_seen = Instant::now();
+        let (tx, rx) = oneshot::channel();
+        if !self.pending.is_empty() {
+            let _ = tx.send(self.pending.drain(..).collect());
+            return MessageResult(rx);
+        }
+
+        // A previous poll is answered empty, since only one is expected at a time.
+        if let Some((poll, timeout)) = self.poll.take() {
+            ctx.cancel_future(timeout);
+            let _ = poll.send(Vec::new());
+        }
+        let timeout = ctx.run_later(self.config.heartbeat_interval, |session, _| {
+            if let Some((poll, _)) = session.poll.take() {
+                session.last_seen = Instant::now();
+                let _ = poll.send(Vec::new());
+            }
+        });
+        self.poll = Some((tx, timeout));
+        MessageResult(rx)
+    }
+}
+
+/// Text posted by the client.
+struct Relay(String);
+impl Message for Relay {
+    type Result = ();
+}
+
+impl Handler<Relay> for HttpSession {
+    type Result = ();
+
+    fn handle(&mut self, msg: Relay, _: &mut Self::Context) {
+        self.last_seen = Instant::now();
+        self.record(|| Event::In {
+            text: msg.0.clone().into(),
+        });
+        ws::relay(&self.c_addr, self.uuid, &self.capabilities, msg.0);
+    }
+}
+
+/// The client ended the session.
+struct Close;
+impl Message for Close {
+    type Result = ();
+}
+
+impl Handler<Close> for HttpSession {
+    type Result = ();
+
+    fn handle(&mut self, _: Close, ctx: &mut Self::Context) {
+        ctx.stop();
+    }
+}
+
+/// Handler for the /http/session POST route, which identifies a client.
+pub async fn identify(
+    body: String,
+    srv: web::Data<Addr<Concierge>>,
+    recorder: web::Data<Option<Addr<Recorder>>>,
+    sessions: web::Data<Sessions>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, Error> {
+    // The session is started first, so that rejections are recorded too.
+    let token = Uuid::new_v4();
+    let session = HttpSession {
+        uuid: Uuid::nil(),
+        token,
+        c_addr: srv.get_ref().clone(),
+        capabilities: Vec::new(),
+        config: config.clone(),
+        sessions: sessions.get_ref().clone(),
+        pending: VecDeque::new(),
+        stream: None,
+        poll: None,
+        last_seen: Instant::now(),
+        conn: record::next_connection(),
+        recorder: recorder.get_ref().clone(),
+        close_code: None,
+    }
+    .start();
+    session.do_send(Identifying(body.clone()));
+    let reject = |reason: CloseReason<'static>| {
+        session.do_send(Rejected { code: reason.code });
+        Error::from(HttpError::from(reason))
+    };
+
+    let payload = serde_json::from_str::<PayloadIn>(&body);
+    let (name, nickname, tags, events, capabilities) = match payload {
+        Ok(PayloadIn::Identify {
+            name,
+            nickname,
+            version,
+            secret,
+            tags,
+            events,
+            capabilities,
+        }) => {
+            ws::verify_identity(&config, name, secret, version).map_err(reject)?;
+            (name, nickname, tags, events, capabilities)
+        }
+        Ok(_) => return Err(reject(CloseReason::NO_AUTH)),
+        Err(_) => return Err(reject(CloseReason::UNKNOWN)),
+    };
+
+    let capabilities = ws::negotiate(capabilities);
+    let uuid = srv
+        .send(IdentifyPackage {
+            name: name.to_owned(),
+            nickname: nickname.map(ToOwned::to_owned),
+            tags: tags.into_iter().map(str::to_owned).collect(),
+            events,
+            capabilities: capabilities.clone(),
+            addr: session.clone().recipient(),
+        })
+        .await
+        .map_err(|_| HttpError::Closed)?
+        .ok_or_else(|| reject(CloseReason::DUPLICATE_AUTH))?;
+    let hello = session
+        .send(Identified { uuid, capabilities })
+        .await
+        .map_err(|_| HttpError::Closed)?
+        .ok_or(HttpError::Closed)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/json")
+        .set_header(SESSION_HEADER, token.to_string())
+        .body(hello))
+}
+
+/// Handler for the /http/session/{token} POST route, which relays the
+/// payloads of the client. The concierge responds through the session.
+pub async fn send(
+    token: web::Path<Uuid>,
+    body: String,
+    sessions: web::Data<Sessions>,
+) -> Result<HttpResponse, Error> {
+    sessions.get(*token)?.do_send(Relay(body));
+    Ok(HttpResponse::Accepted().finish())
+}
+
+/// Handler for the /http/session/{token} DELETE route, which disconnects the client.
+pub async fn close(
+    token: web::Path<Uuid>,
+    sessions: web::Data<Sessions>,
+) -> Result<HttpResponse, Error> {
+    sessions.get(*token)?.do_send(Close);
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Handler for the /http/session/{token}/events GET route, which streams
+/// the payloads of the concierge as Server-Sent Events.
+pub async fn events(
+    token: web::Path<Uuid>,
+    sessions: web::Data<Sessions>,
+) -> Result<HttpResponse, Error> {
+    let stream = sessions
+        .get(*token)?
+        .send(Attach)
+        .await
+        .map_err(|_| HttpError::Closed)?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .set_header(header::CACHE_CONTROL, "no-cache")
+        .streaming(stream.map(Ok::<_, Error>)))
+}
+
+/// Handler for the /http/session/{token}/poll GET route, which responds with
+/// an array of the payloads of the concierge, once there are any or after
+/// one heartbeat interval.
+pub async fn poll(
+    token: web::Path<Uuid>,
+    sessions: web::Data<Sessions>,
+) -> Result<HttpResponse, Error> {
+    let payloads = sessions
+        .get(*token)?
+        .send(Poll)
+        .await
+        .map_err(|_| HttpError::Closed)?
+        .await
+        .unwrap_or_default();
+    Ok(HttpResponse::Ok()
+        .content_type("application/json")
+        .body(format!("[{}]", payloads.join(","))))
+}
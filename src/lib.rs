@@ -0,0 +1,167 @@
+//! The ERT concierge, a central server through which clients discover each
+//! other, host services and share files.
+//!
+//! `main.rs` serves the routes of this crate, which are also served by the
+//! integration tests on an ephemeral port.
+
+pub mod concierge;
+pub mod fs;
+pub mod http;
+pub mod plugin;
+pub mod raw;
+pub mod record;
+pub mod rest;
+pub mod webhook;
+pub mod ws;
+
+use actix::prelude::*;
+use actix_cors::Cors;
+use actix_web::web;
+use concierge::Concierge;
+use concierge_api_rs::Capability;
+use http::Sessions;
+use record::Recorder;
+use semver::VersionReq;
+use std::{path::PathBuf, time::Duration};
+
+// Listen on every available network interface
+pub const SOCKET_ADDR: ([u8; 4], u16) = ([0, 0, 0, 0], 64209);
+pub const VERSION: &str = "0.4.0";
+// Clients of the 0.2 protocol are still accepted, they simply do not request capabilities
+pub const MIN_VERSION: &str = ">=0.2.0, <0.5.0";
+// Protocol features that the server supports, see `Capability`
+pub const CAPABILITIES: &[Capability] = &[Capability::Batching, Capability::Errors];
+pub const SECRET: Option<&str> = None;
+// Past this many clients, client updates are only sent to clients sharing a service
+pub const LARGE_NAMESPACE: usize = 256;
+// How long messages queued for offline clients are held
+pub const QUEUE_TTL: Duration = Duration::from_secs(60 * 60);
+// Maximum number of messages queued for one offline client
+pub const QUEUE_CAPACITY: usize = 64;
+// How many times failed webhook deliveries are retried, and the delay before the first retry
+pub const WEBHOOK_RETRIES: u32 = 5;
+pub const WEBHOOK_BACKOFF: Duration = Duration::from_secs(1);
+
+pub fn min_version_req() -> VersionReq {
+    VersionReq::parse(crate::MIN_VERSION).expect("Valid versioning scheme")
+}
+
+/// Settings of a running concierge, shared by its routes.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Secret that clients must identify with, if any.
+    pub secret: Option<String>,
+    /// Token that scripts can publish messages with, without being a client.
+    pub api_token: Option<String>,
+    /// Directory under which the files of every client are stored.
+    pub fs_root: PathBuf,
+    /// How often heartbeat pings are sent.
+    pub heartbeat_interval: Duration,
+    /// How long before lack of client response causes a timeout.
+    pub client_timeout: Duration,
+    /// File to which every frame exchanged is recorded, if any.
+    pub record: Option<PathBuf>,
+    /// How long messages queued for offline clients are held.
+    pub queue_ttl: Duration,
+    /// Maximum number of messages queued for one offline client.
+    pub queue_capacity: usize,
+    /// How many times failed webhook deliveries are retried.
+    pub webhook_retries: u32,
+    /// Delay before the first retry of a webhook delivery,
+    /// doubled for every following one.
+    pub webhook_backoff: Duration,
+    /// Let webhooks post to loopback, private and link-local addresses.
+    pub webhook_private: bool,
+    /// Names of the plugins hosted in the process, see `plugin::PLUGINS`.
+    pub plugins: Vec<String>,
+    /// Directory of the scripted services, if any, see `plugin::Scripts`.
+    pub scripts: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            secret: SECRET.map(str::to_owned),
+            api_token: None,
+            fs_root: PathBuf::from(".").join("fs"),
+            heartbeat_interval: ws::HEARTBEAT_INTERVAL,
+            client_timeout: ws::CLIENT_TIMEOUT,
+            record: None,
+            queue_ttl: QUEUE_TTL,
+            queue_capacity: QUEUE_CAPACITY,
+            webhook_retries: WEBHOOK_RETRIES,
+            webhook_backoff: WEBHOOK_BACKOFF,
+            webhook_private: false,
+            plugins: Vec::new(),
+            scripts: None,
+        }
+    }
+}
+
+/// Register the websocket, HTTP, REST and file system routes of a concierge,
+/// recording the frames exchanged if a recorder is given. Sessions of the
+/// HTTP transport must be shared by every worker.
+pub fn routes(
+    server: Addr<Concierge>,
+    recorder: Option<Addr<Recorder>>,
+    sessions: Sessions,
+    config: Config,
+) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |cfg| {
+        cfg.data(server)
+            .data(recorder)
+            .data(sessions)
+            .data(config)
+            .service(web::resource("/ws").route(web::get().to(ws::index)))
+            .service(
+                web::scope("/http/session")
+                    .wrap(
+                        Cors::new()
+                            .allowed_methods(vec!["GET", "POST", "DELETE"])
+                            .expose_headers(vec![http::SESSION_HEADER])
+                            .finish(),
+                    )
+                    .route("", web::post().to(http::identify))
+                    .service(
+                        web::resource("/{token}")
+                            .route(web::post().to(http::send))
+                            .route(web::delete().to(http::close)),
+                    )
+                    .route("/{token}/events", web::get().to(http::events))
+                    .route("/{token}/poll", web::get().to(http::poll)),
+            )
+            .service(
+                web::scope("/api")
+                    .wrap(
+                        Cors::new()
+                            .allowed_methods(vec!["GET", "POST", "DELETE"])
+                            .finish(),
+                    )
+                    .route("/message", web::post().to(rest::publish))
+                    .service(
+                        web::resource("/webhooks")
+                            .route(web::get().to(rest::list_webhooks))
+                            .route(web::post().to(rest::register_webhook)),
+                    )
+                    .route("/webhooks/{id}", web::delete().to(rest::unregister_webhook)),
+            )
+            .service(
+                web::scope("/fs")
+                    .wrap(
+                        Cors::new()
+                            .allowed_methods(vec!["GET", "POST", "DELETE"])
+                            .finish(),
+                    )
+                    .service(
+                        web::scope("/{client_name}")
+                            .service(
+                                web::resource("/{file_name}")
+                                    .route(web::get().to(fs::get))
+                                    .route(web::delete().to(fs::delete))
+                                    .route(web::post().to(fs::multipart_upload_single)),
+                            )
+                            .route("/", web::post().to(fs::multipart_upload_multi)),
+                    ),
+            );
+    }
+}
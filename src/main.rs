@@ -1,24 +1,26 @@
-mod concierge;
-mod fs;
-mod ws;
-
 use actix::prelude::*;
-use actix_cors::Cors;
 use actix_files::Files;
 use actix_web::{middleware, web, App, HttpServer, Responder};
-use concierge::Concierge;
-use semver::VersionReq;
-use std::net::SocketAddr;
-
-// Listen on every available network interface
-pub const SOCKET_ADDR: ([u8; 4], u16) = ([0, 0, 0, 0], 64209);
-pub const VERSION: &str = "0.2.0";
-pub const MIN_VERSION: &str = "^0.2.0";
-pub const SECRET: Option<&str> = None;
+use ert_concierge::{
+    concierge::Concierge, http::Sessions, plugin, raw::Acceptor, record::Recorder, Config,
+    MIN_VERSION, SOCKET_ADDR, VERSION,
+};
+use std::{net::SocketAddr, path::PathBuf};
 
-pub fn min_version_req() -> VersionReq {
-    VersionReq::parse(crate::MIN_VERSION).expect("Valid versioning scheme")
-}
+/// Environment variable holding the file to record every frame to, if set.
+const RECORD_VAR: &str = "CONCIERGE_RECORD";
+/// Environment variable holding the token that scripts can publish messages with, if set.
+const API_TOKEN_VAR: &str = "CONCIERGE_API_TOKEN";
+/// Environment variable holding the comma-separated names of the plugins to host, if set.
+const PLUGINS_VAR: &str = "CONCIERGE_PLUGINS";
+/// Environment variable holding the directory of the scripted services, if set.
+const SCRIPTS_VAR: &str = "CONCIERGE_SCRIPTS";
+/// Environment variable letting webhooks post to private addresses, if set.
+const WEBHOOK_PRIVATE_VAR: &str = "CONCIERGE_WEBHOOK_PRIVATE";
+/// Environment variable holding the TCP address of the raw transport, if set.
+const RAW_TCP_VAR: &str = "CONCIERGE_RAW_TCP";
+/// Environment variable holding the Unix socket path of the raw transport, if set.
+const RAW_UNIX_VAR: &str = "CONCIERGE_RAW_UNIX";
 
 async fn index() -> impl Responder {
     format!(
@@ -33,10 +35,71 @@ async fn main() -> std::io::Result<()> {
         .filter_level(log::LevelFilter::Debug)
         .init();
 
-    let server = Concierge::new().start();
+    let config = Config {
+        record: std::env::var_os(RECORD_VAR).map(PathBuf::from),
+        api_token: std::env::var(API_TOKEN_VAR).ok(),
+        plugins: std::env::var(PLUGINS_VAR)
+            .map(|names| names.split(',').map(str::trim).map(str::to_owned).collect())
+            .unwrap_or_default(),
+        scripts: std::env::var_os(SCRIPTS_VAR).map(PathBuf::from),
+        webhook_private: std::env::var_os(WEBHOOK_PRIVATE_VAR).is_some(),
+        ..Config::default()
+    };
+    let server = Concierge::new(config.fs_root.clone())
+        .with_queues(config.queue_ttl, config.queue_capacity)
+        .with_webhooks(config.webhook_retries, config.webhook_backoff)
+        .with_private_webhooks(config.webhook_private)
+        .start();
+    for name in &config.plugins {
+        if !plugin::start(name, server.clone()) {
+            let message = format!(
+                "Unknown plugin {}, expected one of {:?}",
+                name,
+                plugin::PLUGINS
+            );
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                message,
+            ));
+        }
+    }
+    if let Some(dir) = &config.scripts {
+        log::info!("Hosting the scripts of {}", dir.display());
+        plugin::Scripts::new(dir.clone(), server.clone()).start();
+    }
+    let recorder = match &config.record {
+        Some(path) => {
+            log::info!("Recording every frame to {}", path.display());
+            Some(Recorder::create(path)?.start())
+        }
+        None => None,
+    };
+    let acceptor = Acceptor {
+        server: server.clone(),
+        recorder: recorder.clone(),
+        config: web::Data::new(config.clone()),
+    };
+    if let Ok(addr) = std::env::var(RAW_TCP_VAR) {
+        let addr = addr
+            .parse()
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
+        acceptor.clone().bind_tcp(addr).await?;
+    }
+    #[cfg(unix)]
+    {
+        if let Some(path) = std::env::var_os(RAW_UNIX_VAR) {
+            acceptor.bind_unix(&PathBuf::from(path))?;
+        }
+    }
+    let sessions = Sessions::default();
     HttpServer::new(move || {
         App::new()
-            .data(server.clone())
+            .configure(ert_concierge::routes(
+                server.clone(),
+                recorder.clone(),
+                sessions.clone(),
+                config.clone(),
+            ))
             .service(
                 Files::new("/babylonjs", "./babylonjs-web/dist")
                     .show_files_listing()
@@ -44,25 +107,6 @@ async fn main() -> std::io::Result<()> {
             )
             .service(web::resource("/").route(web::get().to(index)))
             .wrap(middleware::Logger::default())
-            .service(web::resource("/ws").route(web::get().to(ws::index)))
-            .service(
-                web::scope("/fs")
-                    .wrap(
-                        Cors::new()
-                            .allowed_methods(vec!["GET", "POST", "DELETE"])
-                            .finish(),
-                    )
-                    .service(
-                        web::scope("/{client_name}")
-                            .service(
-                                web::resource("/{file_name}")
-                                    .route(web::get().to(fs::get))
-                                    .route(web::delete().to(fs::delete))
-                                    .route(web::post().to(fs::multipart_upload_single)),
-                            )
-                            .route("/", web::post().to(fs::multipart_upload_multi)),
-                    ),
-            )
     })
     .bind(SocketAddr::from(SOCKET_ADDR))?
     .run()
@@ -0,0 +1,49 @@
+use super::{Plugin, ServiceHandle};
+use concierge_api_rs::{info::Origin, schema::ServiceSchemas};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, value::RawValue};
+use std::borrow::Cow;
+
+/// A chat room: text sent by a subscriber is relayed to every subscriber,
+/// along with the name of the sender.
+pub struct Chat;
+
+#[derive(Deserialize)]
+struct Said<'a> {
+    #[serde(borrow)]
+    text: Cow<'a, str>,
+}
+
+#[derive(Serialize)]
+struct Chatted<'a> {
+    name: &'a str,
+    text: &'a str,
+}
+
+impl Plugin for Chat {
+    const NAME: &'static str = "chat";
+
+    fn schemas(&self) -> ServiceSchemas {
+        ServiceSchemas {
+            inbound: Some(json!({
+                "type": "object",
+                "properties": { "text": { "type": "string" } },
+                "required": ["text"],
+            })),
+            outbound: None,
+        }
+    }
+
+    fn message(&mut self, origin: &Origin<'static>, data: &RawValue, service: &ServiceHandle) {
+        // Only messages sent to the room are relayed.
+        if origin.service.is_none() {
+            return;
+        }
+        if let Ok(said) = serde_json::from_str::<Said>(data.get()) {
+            service.broadcast(&Chatted {
+                name: &origin.client.name,
+                text: &said.text,
+            });
+        }
+    }
+}
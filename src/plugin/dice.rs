@@ -0,0 +1,69 @@
+use super::{Plugin, ServiceHandle};
+use concierge_api_rs::{info::Origin, schema::ServiceSchemas};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, value::RawValue};
+use uuid::Uuid;
+
+/// A dice roller: rolls requested by a subscriber are announced to every
+/// subscriber, along with the name of the roller.
+pub struct Dice;
+
+#[derive(Deserialize)]
+struct Roll {
+    #[serde(default = "Roll::default_sides")]
+    sides: u32,
+    #[serde(default = "Roll::default_count")]
+    count: u32,
+}
+
+impl Roll {
+    fn default_sides() -> u32 {
+        6
+    }
+
+    fn default_count() -> u32 {
+        1
+    }
+}
+
+#[derive(Serialize)]
+struct Rolled<'a> {
+    name: &'a str,
+    sides: u32,
+    rolls: Vec<u32>,
+}
+
+impl Plugin for Dice {
+    const NAME: &'static str = "dice";
+
+    fn schemas(&self) -> ServiceSchemas {
+        ServiceSchemas {
+            inbound: Some(json!({
+                "type": "object",
+                "properties": {
+                    "sides": { "type": "integer", "minimum": 2, "maximum": 1000 },
+                    "count": { "type": "integer", "minimum": 1, "maximum": 100 },
+                },
+            })),
+            outbound: None,
+        }
+    }
+
+    fn message(&mut self, origin: &Origin<'static>, data: &RawValue, service: &ServiceHandle) {
+        // Only rolls sent to the table are announced.
+        if origin.service.is_none() {
+            return;
+        }
+        if let Ok(roll) = serde_json::from_str::<Roll>(data.get()) {
+            // Version 4 uuids are random, which is all that a die needs.
+            let rolls = (0..roll.count)
+                .map(|_| (Uuid::new_v4().as_u128() % u128::from(roll.sides)) as u32 + 1)
+                .collect();
+            service.broadcast(&Rolled {
+                name: &origin.client.name,
+                sides: roll.sides,
+                rolls,
+            });
+        }
+    }
+}
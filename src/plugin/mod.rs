@@ -0,0 +1,154 @@
+//! Services hosted inside the concierge process.
+//!
+//! A plugin identifies as a client named after it, and owns the service by
+//! the same name. Messages routed to it are handed over as they were received,
+//! and the messages it sends go through the same paths as those of any
+//! service owner, without a connection in between.
+
+mod chat;
+mod dice;
+mod script;
+
+use crate::concierge::{
+    Concierge, Disconnect, OutgoingMessage, PluginMessage, RegisterPlugin, RoutedMessage,
+};
+use actix::prelude::*;
+use concierge_api_rs::{info::Origin, schema::ServiceSchemas};
+use log::error;
+use serde::Serialize;
+use serde_json::value::RawValue;
+use uuid::Uuid;
+
+pub use script::Scripts;
+
+/// Names of the plugins that can be enabled.
+pub const PLUGINS: &[&str] = &[chat::Chat::NAME, dice::Dice::NAME];
+
+/// A service hosted inside the concierge process.
+pub trait Plugin: Unpin + 'static {
+    /// Name of the plugin, which is also the name of its client and service.
+    const NAME: &'static str;
+
+    /// Schemas of the messages of the service.
+    fn schemas(&self) -> ServiceSchemas {
+        ServiceSchemas::default()
+    }
+
+    /// Handle a message sent to the service by a subscriber, or to the
+    /// client of the plugin.
+    fn message(&mut self, origin: &Origin<'static>, data: &RawValue, service: &ServiceHandle);
+}
+
+/// Sends messages through the service of a plugin.
+pub struct ServiceHandle {
+    concierge: Addr<Concierge>,
+    /// Uuid of the client of the plugin.
+    uuid: Uuid,
+}
+
+impl ServiceHandle {
+    /// Broadcast data to every subscriber of the service.
+    pub fn broadcast(&self, data: &impl Serialize) {
+        self.send_to(None, data)
+    }
+
+    /// Send data to one subscriber of the service.
+    pub fn send(&self, uuid: Uuid, data: &impl Serialize) {
+        self.send_to(Some(uuid), data)
+    }
+
+    fn send_to(&self, target: Option<Uuid>, data: &impl Serialize) {
+        let data = serde_json::to_string(data)
+            .and_then(RawValue::from_string)
+            .expect("Serialization error");
+        self.concierge.do_send(PluginMessage {
+            uuid: self.uuid,
+            target,
+            data,
+        });
+    }
+}
+
+/// Actor hosting a plugin, which registers it with the concierge.
+pub struct PluginHost<P> {
+    plugin: P,
+    service: ServiceHandle,
+}
+
+impl<P: Plugin> PluginHost<P> {
+    pub fn new(plugin: P, concierge: Addr<Concierge>) -> Self {
+        Self {
+            plugin,
+            service: ServiceHandle {
+                concierge,
+                uuid: Uuid::nil(),
+            },
+        }
+    }
+}
+
+impl<P: Plugin> Actor for PluginHost<P> {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.service
+            .concierge
+            .send(RegisterPlugin {
+                name: P::NAME.to_owned(),
+                schemas: self.plugin.schemas(),
+                addr: ctx.address().recipient(),
+                plugin: ctx.address().recipient(),
+            })
+            .into_actor(self)
+            .then(|res, host, ctx| {
+                match res {
+                    Ok(Some(uuid)) => host.service.uuid = uuid,
+                    _ => {
+                        error!(
+                            "Plugin {} could not register, its name is invalid or taken.",
+                            P::NAME
+                        );
+                        ctx.stop();
+                    }
+                }
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    fn stopping(&mut self, _: &mut Self::Context) -> Running {
+        self.service.concierge.do_send(Disconnect {
+            uuid: self.service.uuid,
+        });
+        Running::Stop
+    }
+}
+
+/// Payloads that clients receive, which plugins have no use for.
+impl<P: Plugin> Handler<OutgoingMessage> for PluginHost<P> {
+    type Result = ();
+
+    fn handle(&mut self, _: OutgoingMessage, _: &mut Self::Context) {}
+}
+
+impl<P: Plugin> Handler<RoutedMessage> for PluginHost<P> {
+    type Result = ();
+
+    fn handle(&mut self, msg: RoutedMessage, _: &mut Self::Context) {
+        self.plugin.message(&msg.origin, &msg.data, &self.service);
+    }
+}
+
+/// Start the plugin by that name, if there is one.
+pub fn start(name: &str, concierge: Addr<Concierge>) -> bool {
+    match name {
+        chat::Chat::NAME => {
+            PluginHost::new(chat::Chat, concierge).start();
+        }
+        dice::Dice::NAME => {
+            PluginHost::new(dice::Dice, concierge).start();
+        }
+        _ => return false,
+    }
+    true
+}
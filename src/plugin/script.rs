@@ -0,0 +1,433 @@
+//! Services scripted in Rhai, loaded from a directory.
+//!
+//! Every `<name>.rhai` file of the directory is hosted like a plugin named
+//! after it, running in an engine of its own with limited resources. Names
+//! must be valid client names, so other files are not hosted. Scripts
+//! are reloaded when their file changes, keeping their state, and their
+//! service is deleted along with the file.
+
+use super::ServiceHandle;
+use crate::concierge::{Concierge, Disconnect, OutgoingMessage, RegisterPlugin, RoutedMessage};
+use actix::prelude::*;
+use actix_web_actors::ws::Message as WsMessage;
+use concierge_api_rs::{schema::ServiceSchemas, PayloadOut};
+use log::{error, info, warn};
+use rhai::{
+    serde::{from_dynamic, to_dynamic},
+    Dynamic, Engine, EvalAltResult, FuncArgs, ImmutableString, Scope, AST,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{Duration, SystemTime},
+};
+use uuid::Uuid;
+
+/// How often the directory is checked for changed scripts.
+const SCAN_INTERVAL: Duration = Duration::from_secs(1);
+/// Operations that a hook or a timer may run before it is aborted.
+const MAX_OPERATIONS: u64 = 100_000;
+/// Depth of nested function calls.
+const MAX_CALL_LEVELS: usize = 32;
+/// Depth of nested expressions, at the top level and in functions.
+const MAX_EXPR_DEPTH: usize = 64;
+/// Length of strings, and number of items of arrays and maps.
+const MAX_STRING_SIZE: usize = 64 * 1024;
+const MAX_COLLECTION_SIZE: usize = 4096;
+/// Keys that the state of a script may hold.
+const MAX_STATE_KEYS: usize = 1024;
+/// Timers that a script may have pending.
+const MAX_TIMERS: usize = 16;
+
+/// What a script asked for while it was running, carried out once it returns.
+enum Action {
+    Broadcast(Value),
+    Send(Uuid, Value),
+    After(Duration, String),
+}
+
+/// Shared between a script host and the functions it registers.
+#[derive(Default)]
+struct Runtime {
+    actions: Vec<Action>,
+    state: HashMap<String, Dynamic>,
+    /// Timers pending, including those still in `actions`.
+    timers: usize,
+}
+
+/// Message to reload a script from its new source.
+struct Reload(String);
+impl Message for Reload {
+    type Result = ();
+}
+
+/// Message to stop a script, deleting its service.
+struct Unload;
+impl Message for Unload {
+    type Result = ();
+}
+
+/// Actor hosting a script, which registers it with the concierge.
+struct ScriptHost {
+    name: String,
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    runtime: Rc<RefCell<Runtime>>,
+    timers: HashMap<usize, SpawnHandle>,
+    next_timer: usize,
+    service: ServiceHandle,
+}
+
+impl ScriptHost {
+    /// Compile a script, which fails on syntax errors.
+    fn new(name: String, source: &str, concierge: Addr<Concierge>) -> Result<Self, String> {
+        let runtime = Rc::new(RefCell::new(Runtime::default()));
+        let engine = sandbox(&name, &runtime);
+        let ast = engine.compile(source).map_err(|err| err.to_string())?;
+        Ok(Self {
+            name,
+            engine,
+            ast,
+            scope: Scope::new(),
+            runtime,
+            timers: HashMap::new(),
+            next_timer: 0,
+            service: ServiceHandle {
+                concierge,
+                uuid: Uuid::nil(),
+            },
+        })
+    }
+
+    /// Call a function of the script, if it defines it, then carry out
+    /// what it asked for.
+    fn call(&mut self, function: &str, args: impl FuncArgs, ctx: &mut Context<Self>) {
+        let result = self
+            .engine
+            .call_fn::<_, Dynamic>(&mut self.scope, &self.ast, function, args);
+        if let Err(err) = result {
+            match *err {
+                EvalAltResult::ErrorFunctionNotFound(ref signature, _)
+                    if signature.starts_with(&format!("{} (", function)) => {}
+                _ => warn!("Script {} failed in {}: {}", self.name, function, err),
+            }
+        }
+
+        let actions = std::mem::take(&mut self.runtime.borrow_mut().actions);
+        for action in actions {
+            match action {
+                Action::Broadcast(data) => self.service.broadcast(&data),
+                Action::Send(uuid, data) => self.service.send(uuid, &data),
+                Action::After(delay, function) => {
+                    let id = self.next_timer;
+                    self.next_timer += 1;
+                    let handle = ctx.run_later(delay, move |host, ctx| {
+                        host.timers.remove(&id);
+                        host.runtime.borrow_mut().timers -= 1;
+                        host.call(&function, (), ctx);
+                    });
+                    self.timers.insert(id, handle);
+                }
+            }
+        }
+    }
+}
+
+/// An engine with the functions available to scripts, and the limits they
+/// run under. Scripts cannot import modules, nor evaluate code.
+fn sandbox(name: &str, runtime: &Rc<RefCell<Runtime>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(MAX_CALL_LEVELS);
+    engine.set_max_expr_depths(MAX_EXPR_DEPTH, MAX_EXPR_DEPTH);
+    engine.set_max_string_size(MAX_STRING_SIZE);
+    engine.set_max_array_size(MAX_COLLECTION_SIZE);
+    engine.set_max_map_size(MAX_COLLECTION_SIZE);
+    engine.disable_symbol("import");
+    engine.disable_symbol("eval");
+
+    let script = name.to_owned();
+    engine.on_print(move |text| info!("Script {}: {}", script, text));
+
+    let shared = runtime.clone();
+    engine.register_result_fn("broadcast", move |data: Dynamic| {
+        let data = from_dynamic::<Value>(&data)?;
+        shared.borrow_mut().actions.push(Action::Broadcast(data));
+        Ok(Dynamic::from(()))
+    });
+    let shared = runtime.clone();
+    engine.register_result_fn("send", move |uuid: ImmutableString, data: Dynamic| {
+        let uuid = uuid.parse().map_err(|_| format!("Invalid uuid {}", uuid))?;
+        let data = from_dynamic::<Value>(&data)?;
+        shared.borrow_mut().actions.push(Action::Send(uuid, data));
+        Ok(Dynamic::from(()))
+    });
+    let shared = runtime.clone();
+    engine.register_fn("has_state", move |key: ImmutableString| {
+        shared.borrow().state.contains_key(key.as_str())
+    });
+    let shared = runtime.clone();
+    engine.register_fn("get_state", move |key: ImmutableString| {
+        shared
+            .borrow()
+            .state
+            .get(key.as_str())
+            .cloned()
+            .unwrap_or_else(|| Dynamic::from(()))
+    });
+    let shared = runtime.clone();
+    engine.register_result_fn("set_state", move |key: ImmutableString, value: Dynamic| {
+        let mut runtime = shared.borrow_mut();
+        if runtime.state.len() >= MAX_STATE_KEYS && !runtime.state.contains_key(key.as_str()) {
+            return Err(format!("State is limited to {} keys", MAX_STATE_KEYS).into());
+        }
+        runtime.state.insert(key.to_string(), value);
+        Ok(Dynamic::from(()))
+    });
+    let shared = runtime.clone();
+    engine.register_result_fn("after", move |millis: i64, function: ImmutableString| {
+        let mut runtime = shared.borrow_mut();
+        if runtime.timers >= MAX_TIMERS {
+            return Err(format!("Timers are limited to {} pending", MAX_TIMERS).into());
+        }
+        runtime.timers += 1;
+        let delay = Duration::from_millis(millis.max(0) as u64);
+        runtime
+            .actions
+            .push(Action::After(delay, function.to_string()));
+        Ok(Dynamic::from(()))
+    });
+    engine
+}
+
+/// Convert a value for a script.
+fn dynamic(value: &impl Serialize) -> Dynamic {
+    to_dynamic(value).unwrap_or_else(|_| Dynamic::from(()))
+}
+
+impl Actor for ScriptHost {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.service
+            .concierge
+            .send(RegisterPlugin {
+                name: self.name.clone(),
+                schemas: ServiceSchemas::default(),
+                addr: ctx.address().recipient(),
+                plugin: ctx.address().recipient(),
+            })
+            .into_actor(self)
+            .then(|res, host, ctx| {
+                match res {
+                    Ok(Some(uuid)) => {
+                        host.service.uuid = uuid;
+                        host.call("on_load", (), ctx);
+                    }
+                    _ => {
+                        error!(
+                            "Script {} could not register, its name is invalid or taken.",
+                            host.name
+                        );
+                        ctx.stop();
+                    }
+                }
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    fn stopping(&mut self, _: &mut Self::Context) -> Running {
+        self.service.concierge.do_send(Disconnect {
+            uuid: self.service.uuid,
+        });
+        Running::Stop
+    }
+}
+
+/// Subscriptions to the service are the only payloads that scripts are told about.
+impl Handler<OutgoingMessage> for ScriptHost {
+    type Result = ();
+
+    fn handle(&mut self, msg: OutgoingMessage, ctx: &mut Self::Context) {
+        if let WsMessage::Text(text) = msg.0 {
+            match serde_json::from_str(&text) {
+                Ok(PayloadOut::ServiceClientSubscribed { client, .. }) => {
+                    self.call("on_subscribe", (dynamic(&client),), ctx)
+                }
+                Ok(PayloadOut::ServiceClientUnsubscribed { client, .. }) => {
+                    self.call("on_unsubscribe", (dynamic(&client),), ctx)
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Handler<RoutedMessage> for ScriptHost {
+    type Result = ();
+
+    fn handle(&mut self, msg: RoutedMessage, ctx: &mut Self::Context) {
+        let data = serde_json::from_str::<Value>(msg.data.get()).unwrap_or_default();
+        self.call("on_message", (dynamic(&msg.origin), dynamic(&data)), ctx);
+    }
+}
+
+impl Handler<Reload> for ScriptHost {
+    type Result = ();
+
+    fn handle(&mut self, Reload(source): Reload, ctx: &mut Self::Context) {
+        match self.engine.compile(&source) {
+            Ok(ast) => {
+                info!("Script {} reloaded.", self.name);
+                self.ast = ast;
+                // Timers of the previous version are cancelled, its state is kept.
+                for (_, handle) in self.timers.drain() {
+                    ctx.cancel_future(handle);
+                }
+                self.runtime.borrow_mut().timers = 0;
+                self.call("on_load", (), ctx);
+            }
+            Err(err) => warn!(
+                "Script {} kept running, it failed to reload: {}",
+                self.name, err
+            ),
+        }
+    }
+}
+
+impl Handler<Unload> for ScriptHost {
+    type Result = ();
+
+    fn handle(&mut self, _: Unload, ctx: &mut Self::Context) {
+        info!("Script {} unloaded.", self.name);
+        ctx.stop();
+    }
+}
+
+/// A script loaded from the directory.
+struct Loaded {
+    modified: SystemTime,
+    /// `None` if it failed to compile.
+    host: Option<Addr<ScriptHost>>,
+}
+
+/// Actor watching a directory of scripts, which loads, reloads and unloads
+/// them as their files change.
+pub struct Scripts {
+    dir: PathBuf,
+    concierge: Addr<Concierge>,
+    loaded: HashMap<PathBuf, Loaded>,
+}
+
+impl Scripts {
+    pub fn new(dir: PathBuf, concierge: Addr<Concierge>) -> Self {
+        Self {
+            dir,
+            concierge,
+            loaded: HashMap::new(),
+        }
+    }
+
+    /// Compare the scripts of the directory to those loaded.
+    fn scan(&mut self) {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) => {
+                warn!(
+                    "Scripts could not be read from {}: {}",
+                    self.dir.display(),
+                    err
+                );
+                return;
+            }
+        };
+        let mut present = HashSet::new();
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            if path.extension() != Some(OsStr::new("rhai")) {
+                continue;
+            }
+            let modified = match entry.metadata().and_then(|metadata| metadata.modified()) {
+                Ok(modified) => modified,
+                Err(_) => continue,
+            };
+            present.insert(path.clone());
+            if let Some(loaded) = self.loaded.get(&path) {
+                if loaded.modified == modified {
+                    continue;
+                }
+            }
+            let source = match std::fs::read_to_string(&path) {
+                Ok(source) => source,
+                Err(err) => {
+                    warn!("Script {} could not be read: {}", path.display(), err);
+                    continue;
+                }
+            };
+            let running = self
+                .loaded
+                .remove(&path)
+                .and_then(|loaded| loaded.host)
+                .filter(Addr::connected);
+            let host = match running {
+                Some(host) => {
+                    host.do_send(Reload(source));
+                    Some(host)
+                }
+                None => self.load(&path, &source),
+            };
+            self.loaded.insert(path, Loaded { modified, host });
+        }
+
+        self.loaded.retain(|path, loaded| {
+            if present.contains(path) {
+                return true;
+            }
+            if let Some(host) = &loaded.host {
+                host.do_send(Unload);
+            }
+            false
+        });
+    }
+
+    fn load(&self, path: &Path, source: &str) -> Option<Addr<ScriptHost>> {
+        let name = path.file_stem().and_then(OsStr::to_str)?.to_owned();
+        match ScriptHost::new(name, source, self.concierge.clone()) {
+            Ok(host) => {
+                info!("Script {} loaded.", path.display());
+                Some(host.start())
+            }
+            Err(err) => {
+                warn!("Script {} failed to compile: {}", path.display(), err);
+                None
+            }
+        }
+    }
+}
+
+impl Actor for Scripts {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.scan();
+        ctx.run_interval(SCAN_INTERVAL, |scripts, _| scripts.scan());
+    }
+
+    fn stopping(&mut self, _: &mut Self::Context) -> Running {
+        for host in self
+            .loaded
+            .values()
+            .filter_map(|loaded| loaded.host.as_ref())
+        {
+            host.do_send(Unload);
+        }
+        Running::Stop
+    }
+}
@@ -0,0 +1,294 @@
+//! Raw transport, for services on the same host that have no use for
+//! websocket handshakes and framing.
+//!
+//! Payloads are exchanged as newline-delimited JSON over TCP or a Unix domain
+//! socket, and the connection behaves like a websocket one: the first line
+//! must be `IDENTIFY`, and the concierge writes an empty line every heartbeat,
+//! which the client answers with an empty line. Any line from the client
+//! counts as a heartbeat. Before closing the connection for a reason, the
+//! concierge writes the reason as a last line, such as
+//! `{"code":4006,"reason":"Secret mismatch"}`.
+
+use crate::{
+    concierge::{Concierge, Disconnect, IdentifyPackage, OutgoingMessage},
+    record::{self, Record, Recorder},
+    ws, Config,
+};
+use actix::prelude::*;
+use actix_web::web;
+use actix_web_actors::ws::Message as WsMessage;
+use concierge_api_rs::{record::Event, Capability, CloseReason, PayloadIn};
+use futures::{channel::mpsc, StreamExt};
+use log::{error, info, warn};
+use std::{io, net::SocketAddr, time::Instant};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt, WriteHalf},
+    net::TcpListener,
+};
+use tokio_util::codec::{FramedRead, LinesCodec, LinesCodecError};
+use uuid::Uuid;
+
+/// Longest line accepted from a client, like the largest websocket frame.
+pub const MAX_LINE_LENGTH: usize = 65_536;
+const LINE_BREAKS: &[char] = &['\n', '\r'];
+
+/// Accepts connections of the raw transport.
+#[derive(Clone)]
+pub struct Acceptor {
+    pub server: Addr<Concierge>,
+    pub recorder: Option<Addr<Recorder>>,
+    pub config: web::Data<Config>,
+}
+
+impl Acceptor {
+    /// Accept connections on a TCP address in the background,
+    /// returning the address that was bound.
+    pub async fn bind_tcp(self, addr: SocketAddr) -> io::Result<SocketAddr> {
+        let mut listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        info!("Accepting raw connections on {}", addr);
+        actix_rt::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => self.start(stream),
+                    Err(err) => error!("Failed to accept a raw connection: {}", err),
+                }
+            }
+        });
+        Ok(addr)
+    }
+
+    /// Accept connections on a Unix domain socket in the background.
+    /// A socket left at the path by a previous run is replaced.
+    #[cfg(unix)]
+    pub fn bind_unix(self, path: &std::path::Path) -> io::Result<()> {
+        use std::os::unix::fs::FileTypeExt;
+
+        if let Ok(metadata) = std::fs::metadata(path) {
+            if metadata.file_type().is_socket() {
+                std::fs::remove_file(path)?;
+            }
+        }
+        let mut listener = tokio::net::UnixListener::bind(path)?;
+        info!("Accepting raw connections on {}", path.display());
+        actix_rt::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => self.start(stream),
+                    Err(err) => error!("Failed to accept a raw connection: {}", err),
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Serve a connection.
+    fn start<S: AsyncRead + AsyncWrite + 'static>(&self, stream: S) {
+        let (reader, writer) = tokio::io::split(stream);
+        let (lines, outgoing) = mpsc::unbounded();
+        actix_rt::spawn(write_lines(writer, outgoing));
+        RawConnection::create(|ctx| {
+            ctx.add_stream(FramedRead::new(
+                reader,
+                LinesCodec::new_with_max_length(MAX_LINE_LENGTH),
+            ));
+            RawConnection {
+                uuid: Uuid::nil(),
+                last_hb: Instant::now(),
+                c_addr: self.server.clone(),
+                capabilities: Vec::new(),
+                config: self.config.clone(),
+                conn: record::next_connection(),
+                recorder: self.recorder.clone(),
+                close_code: None,
+                lines,
+            }
+        });
+    }
+}
+
+/// Write lines to the socket until the connection stops, then shut it down.
+async fn write_lines<W: AsyncWrite>(
+    mut writer: WriteHalf<W>,
+    mut lines: mpsc::UnboundedReceiver<String>,
+) {
+    while let Some(line) = lines.next().await {
+        if writer.write_all(line.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+    let _ = writer.shutdown().await;
+}
+
+pub struct RawConnection {
+    pub uuid: Uuid,
+    pub last_hb: Instant,
+    pub c_addr: Addr<Concierge>,
+    /// Capabilities negotiated with the client.
+    pub capabilities: Vec<Capability>,
+    /// Settings of the concierge.
+    pub config: web::Data<Config>,
+    /// Number of the connection in recordings.
+    pub conn: u64,
+    /// Recorder of the lines exchanged, if recording.
+    pub recorder: Option<Addr<Recorder>>,
+    /// Code of the close reason sent to the client, if any.
+    pub close_code: Option<u16>,
+    /// Lines to write to the client, each ending with a newline.
+    lines: mpsc::UnboundedSender<String>,
+}
+
+impl Actor for RawConnection {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.record(|| Event::Open);
+        let interval = self.config.heartbeat_interval;
+        ctx.run_later(interval, |raw, raw_ctx| {
+            // If the UUID is still nil after one heartbeat interval,
+            // then the client failed to identify.
+            if raw.uuid.is_nil() {
+                raw.close(raw_ctx, CloseReason::AUTH_FAILED);
+            }
+        });
+        ctx.run_interval(interval, |raw, raw_ctx| {
+            if Instant::now().duration_since(raw.last_hb) > raw.config.client_timeout {
+                warn!("Raw client {} failed heartbeat. Dropping.", raw.uuid);
+                raw.c_addr.do_send(Disconnect { uuid: raw.uuid });
+                raw.close(raw_ctx, CloseReason::HB_FAILED);
+            } else {
+                // An empty line stands for a ping.
+                raw.write(String::new());
+            }
+        });
+    }
+
+    fn stopping(&mut self, _: &mut Self::Context) -> Running {
+        self.c_addr.do_send(Disconnect { uuid: self.uuid });
+        let code = self.close_code;
+        self.record(|| Event::Close { code });
+        Running::Stop
+    }
+}
+
+impl Handler<OutgoingMessage> for RawConnection {
+    type Result = ();
+
+    fn handle(&mut self, msg: OutgoingMessage, _: &mut Self::Context) {
+        if let WsMessage::Text(text) = msg.0 {
+            self.record(|| Event::Out {
+                text: text.clone().into(),
+            });
+            // Relayed data keeps the whitespace that it was sent with, but
+            // line breaks can only be whitespace in valid JSON.
+            if text.contains(LINE_BREAKS) {
+                self.write(text.replace(LINE_BREAKS, " "));
+            } else {
+                self.write(text);
+            }
+        }
+    }
+}
+
+impl RawConnection {
+    /// Record an event of the connection, if recording.
+    fn record(&self, event: impl FnOnce() -> Event<'static>) {
+        if let Some(recorder) = &self.recorder {
+            recorder.do_send(Record {
+                conn: self.conn,
+                at: Instant::now(),
+                event: event(),
+            });
+        }
+    }
+
+    /// Write a line to the client.
+    fn write(&self, mut line: String) {
+        line.push('\n');
+        let _ = self.lines.unbounded_send(line);
+    }
+
+    /// Write the close reason, then close the connection and stop the actor.
+    fn close(&mut self, ctx: &mut Context<Self>, reason: CloseReason) {
+        self.close_code = Some(reason.code);
+        let reason = serde_json::json!({ "code": reason.code, "reason": reason.reason });
+        self.write(reason.to_string());
+        ctx.stop();
+    }
+
+    /// Handle the first line of the client, which must identify it.
+    fn identify(&mut self, line: &str, ctx: &mut Context<Self>) {
+        match serde_json::from_str::<PayloadIn>(line) {
+            Ok(PayloadIn::Identify {
+                name,
+                nickname,
+                version,
+                secret,
+                tags,
+                events,
+                capabilities,
+            }) => {
+                if let Err(reason) = ws::verify_identity(&self.config, name, secret, version) {
+                    self.close(ctx, reason);
+                    return;
+                }
+                self.capabilities = ws::negotiate(capabilities);
+
+                self.c_addr
+                    .send(IdentifyPackage {
+                        name: name.to_owned(),
+                        nickname: nickname.map(ToOwned::to_owned),
+                        tags: tags.into_iter().map(str::to_owned).collect(),
+                        events,
+                        capabilities: self.capabilities.clone(),
+                        addr: ctx.address().recipient(),
+                    })
+                    .into_actor(self)
+                    .then(|res, act, ctx| {
+                        match res {
+                            Ok(Some(res)) => act.uuid = res,
+                            Ok(None) => act.close(ctx, CloseReason::DUPLICATE_AUTH),
+                            _ => ctx.stop(),
+                        }
+                        fut::ready(())
+                    })
+                    .wait(ctx);
+            }
+            Ok(_) => self.close(ctx, CloseReason::NO_AUTH),
+            Err(_) => self.close(ctx, CloseReason::UNKNOWN),
+        }
+    }
+}
+
+/// Line handler
+impl StreamHandler<Result<String, LinesCodecError>> for RawConnection {
+    fn handle(&mut self, msg: Result<String, LinesCodecError>, ctx: &mut Self::Context) {
+        let line = match msg {
+            Ok(line) => line,
+            Err(LinesCodecError::MaxLineLengthExceeded) => {
+                warn!("Raw client {} sent a line that is too long.", self.uuid);
+                self.close(ctx, CloseReason::FATAL_DECODE);
+                return;
+            }
+            Err(LinesCodecError::Io(err)) => {
+                error!("Dropping a raw connection due to an error. Error: {}", err);
+                ctx.stop();
+                return;
+            }
+        };
+        self.last_hb = Instant::now();
+        // Empty lines only serve as heartbeats.
+        if line.trim().is_empty() {
+            return;
+        }
+        self.record(|| Event::In {
+            text: line.clone().into(),
+        });
+
+        if self.uuid.is_nil() {
+            self.identify(&line, ctx);
+        } else {
+            ws::relay(&self.c_addr, self.uuid, &self.capabilities, line);
+        }
+    }
+}
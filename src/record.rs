@@ -0,0 +1,79 @@
+//! Recording of every frame exchanged on the connections, for replaying
+//! sessions later on. See `concierge_api_rs::record` for the format.
+
+use actix::prelude::*;
+use concierge_api_rs::record::{Entry, Event};
+use log::error;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+/// How often the recording is flushed to the file.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+static NEXT_CONNECTION: AtomicU64 = AtomicU64::new(0);
+
+/// Number the next connection, in order of connection.
+pub fn next_connection() -> u64 {
+    NEXT_CONNECTION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Writes the entries of a recording to a file.
+pub struct Recorder {
+    writer: BufWriter<File>,
+    start: Instant,
+}
+
+impl Recorder {
+    /// Start a recording, replacing the file if it exists.
+    pub fn create(path: &Path) -> std::io::Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+            start: Instant::now(),
+        })
+    }
+}
+
+impl Actor for Recorder {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        // The writer is also flushed when dropped.
+        ctx.run_interval(FLUSH_INTERVAL, |recorder, _| {
+            if let Err(err) = recorder.writer.flush() {
+                error!("Failed to flush the recording. Error: {}", err);
+            }
+        });
+    }
+}
+
+/// Something that happened on a connection, at some instant.
+#[derive(Debug)]
+pub struct Record {
+    pub conn: u64,
+    pub at: Instant,
+    pub event: Event<'static>,
+}
+impl Message for Record {
+    type Result = ();
+}
+
+impl Handler<Record> for Recorder {
+    type Result = ();
+
+    fn handle(&mut self, msg: Record, _: &mut Context<Self>) {
+        let entry = Entry {
+            time: msg.at.saturating_duration_since(self.start).as_micros() as u64,
+            conn: msg.conn,
+            event: msg.event,
+        };
+        let line = serde_json::to_string(&entry).expect("Serialization error");
+        if let Err(err) = writeln!(self.writer, "{}", line) {
+            error!("Failed to record a frame. Error: {}", err);
+        }
+    }
+}
@@ -0,0 +1,179 @@
+//! REST routes, for scripts that publish messages or manage webhooks
+//! without holding a connection.
+
+use crate::{
+    concierge::{Concierge, ListWebhooks, PublishMessage, RegisterWebhook, UnregisterWebhook},
+    fs::FS_KEY_HEADER,
+    webhook, Config,
+};
+use actix::prelude::*;
+use actix_web::{
+    http::{header, StatusCode},
+    web, Error, HttpRequest, HttpResponse, ResponseError,
+};
+use concierge_api_rs::{webhook::Registration, ErrorCode};
+use uuid::Uuid;
+
+/// Scheme of the authorization header bearing the API token.
+const BEARER: &str = "Bearer ";
+
+/// REST errors.
+#[derive(thiserror::Error, Debug)]
+pub enum RestError {
+    #[error("Unknown REST error")]
+    Unknown,
+    #[error("Bad authorization")]
+    BadAuthorization,
+    #[error("Missing x-fs-key or authorization header")]
+    MissingAuthorization,
+    #[error("Only the owner of the service can do that")]
+    Forbidden,
+    #[error("No service by that name")]
+    UnknownService,
+    #[error("No webhook by that id")]
+    UnknownWebhook,
+    #[error("Invalid webhook registration")]
+    InvalidWebhook,
+    #[error("Webhooks can only post to public addresses")]
+    PrivateWebhook,
+}
+
+impl ResponseError for RestError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            RestError::Unknown => StatusCode::INTERNAL_SERVER_ERROR,
+            RestError::BadAuthorization => StatusCode::UNAUTHORIZED,
+            RestError::MissingAuthorization => StatusCode::PRECONDITION_REQUIRED,
+            RestError::Forbidden => StatusCode::FORBIDDEN,
+            RestError::UnknownService | RestError::UnknownWebhook => StatusCode::NOT_FOUND,
+            RestError::InvalidWebhook | RestError::PrivateWebhook => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+/// Find who a request is sent as. Requests bearing the API token have no
+/// sender, other requests are sent as the client that the file key belongs to.
+fn authorize(req: &HttpRequest, config: &Config) -> Result<Option<Uuid>, RestError> {
+    if let Some(value) = req.headers().get(header::AUTHORIZATION) {
+        let token = value
+            .to_str()
+            .ok()
+            .filter(|value| value.starts_with(BEARER))
+            .map(|value| &value[BEARER.len()..]);
+        return match (token, config.api_token.as_deref()) {
+            (Some(token), Some(expected)) if token == expected => Ok(None),
+            _ => Err(RestError::BadAuthorization),
+        };
+    }
+    req.headers()
+        .get(FS_KEY_HEADER)
+        .ok_or(RestError::MissingAuthorization)?
+        .to_str()
+        .ok()
+        .and_then(|key| key.parse().ok())
+        .map(Some)
+        .ok_or(RestError::BadAuthorization)
+}
+
+/// HTTP status of a message that failed with an error code.
+fn error_status(code: ErrorCode) -> StatusCode {
+    match code {
+        ErrorCode::Protocol | ErrorCode::Unsupported => StatusCode::BAD_REQUEST,
+        ErrorCode::Schema => StatusCode::UNPROCESSABLE_ENTITY,
+        ErrorCode::InvalidName | ErrorCode::InvalidUuid | ErrorCode::InvalidService => {
+            StatusCode::NOT_FOUND
+        }
+        ErrorCode::NotSubscribed | ErrorCode::NotOwner => StatusCode::FORBIDDEN,
+        ErrorCode::QueueFull => StatusCode::SERVICE_UNAVAILABLE,
+        ErrorCode::Internal | ErrorCode::Unknown => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Handler for the /api/message POST route, which routes a `MESSAGE`
+/// payload like one sent over a connection. Responds with the payload that
+/// the sender would have received, with a status matching its outcome.
+pub async fn publish(
+    req: HttpRequest,
+    body: String,
+    srv: web::Data<Addr<Concierge>>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, Error> {
+    let sender = authorize(&req, &config)?;
+    let published = srv
+        .send(PublishMessage { sender, text: body })
+        .await
+        .map_err(|_| RestError::Unknown)?
+        .ok_or(RestError::BadAuthorization)?;
+    let status = published.error.map_or(StatusCode::OK, error_status);
+    Ok(HttpResponse::build(status)
+        .content_type("application/json")
+        .body(published.text))
+}
+
+/// Handler for the /api/webhooks POST route, which registers a webhook.
+/// Service owners can only register webhooks for the events of their services.
+pub async fn register_webhook(
+    req: HttpRequest,
+    body: String,
+    srv: web::Data<Addr<Concierge>>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, Error> {
+    let owner = authorize(&req, &config)?;
+    let registration =
+        serde_json::from_str::<Registration>(&body).map_err(|_| RestError::InvalidWebhook)?;
+    if !registration.url.starts_with("http://") && !registration.url.starts_with("https://") {
+        return Err(RestError::InvalidWebhook.into());
+    }
+    if !config.webhook_private && !webhook::is_public_url(&registration.url).await {
+        return Err(RestError::PrivateWebhook.into());
+    }
+
+    let webhook = srv
+        .send(RegisterWebhook {
+            owner,
+            registration: registration.owned(),
+        })
+        .await
+        .map_err(|_| RestError::Unknown)?
+        .ok_or(RestError::BadAuthorization)?
+        .map_err(|code| match code {
+            ErrorCode::InvalidService => RestError::UnknownService,
+            _ => RestError::Forbidden,
+        })?;
+    Ok(HttpResponse::Created().json(webhook))
+}
+
+/// Handler for the /api/webhooks GET route, which lists the webhooks
+/// that the sender registered, or every webhook for the API token.
+pub async fn list_webhooks(
+    req: HttpRequest,
+    srv: web::Data<Addr<Concierge>>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, Error> {
+    let owner = authorize(&req, &config)?;
+    let webhooks = srv
+        .send(ListWebhooks { owner })
+        .await
+        .map_err(|_| RestError::Unknown)?
+        .ok_or(RestError::BadAuthorization)?;
+    Ok(HttpResponse::Ok().json(webhooks))
+}
+
+/// Handler for the /api/webhooks/{id} DELETE route.
+pub async fn unregister_webhook(
+    id: web::Path<Uuid>,
+    req: HttpRequest,
+    srv: web::Data<Addr<Concierge>>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, Error> {
+    let owner = authorize(&req, &config)?;
+    let removed = srv
+        .send(UnregisterWebhook { owner, id: *id })
+        .await
+        .map_err(|_| RestError::Unknown)?
+        .ok_or(RestError::BadAuthorization)?;
+    if !removed {
+        return Err(RestError::UnknownWebhook.into());
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,220 @@
+//! Delivery of webhooks, see `concierge_api_rs::webhook`.
+
+use actix_connect::{Connect, ConnectError, Connection, TcpConnector};
+use actix_service::Service;
+use actix_web::{
+    client::{Client, Connector},
+    http::Uri,
+};
+use concierge_api_rs::webhook::{WebhookEvent, EVENT_HEADER, SIGNATURE_HEADER};
+use futures::future::LocalBoxFuture;
+use hmac::{Hmac, Mac, NewMac};
+use log::{debug, warn};
+use sha2::Sha256;
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::net::TcpStream;
+
+/// How long a delivery waits for the endpoint to respond.
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Signature of a body, as sent in the `SIGNATURE_HEADER`.
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_varkey(secret.as_bytes()).expect("HMAC accepts any key");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Check if an address can be reached from the public internet. Loopback,
+/// private, link-local and other special-purpose addresses can not, and
+/// webhooks must not be able to reach the host or its network through them.
+/// Addresses that wrap an IPv4 address are public if the wrapped one is.
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+            let wrapped = |high: u16, low: u16| {
+                let [a, b] = high.to_be_bytes();
+                let [c, d] = low.to_be_bytes();
+                is_public_v4(Ipv4Addr::new(a, b, c, d))
+            };
+            // IPv4-mapped addresses, ::ffff:0:0/96.
+            if segments[..5] == [0; 5] && segments[5] == 0xffff {
+                return wrapped(segments[6], segments[7]);
+            }
+            // NAT64 addresses, 64:ff9b::/96.
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                return wrapped(segments[6], segments[7]);
+            }
+            // 6to4 addresses, 2002::/16.
+            if segments[0] == 0x2002 {
+                return wrapped(segments[1], segments[2]);
+            }
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local addresses, fc00::/7.
+                || segments[0] & 0xfe00 == 0xfc00
+                // Link-local addresses, fe80::/10.
+                || segments[0] & 0xffc0 == 0xfe80)
+        }
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        // "This network", 0.0.0.0/8.
+        || a == 0
+        // Shared address space, 100.64.0.0/10.
+        || (a == 100 && b & 0xc0 == 64)
+        // Protocol assignments, 192.0.0.0/24.
+        || (a == 192 && b == 0 && c == 0)
+        // Benchmarking, 198.18.0.0/15.
+        || (a == 198 && b & 0xfe == 18)
+        // Reserved, 240.0.0.0/4, along with the broadcast address.
+        || a >= 240)
+}
+
+/// Resolve a host to its addresses, if every one of them is public.
+/// Hosts that do not resolve have none.
+async fn resolve_public(host: &str, port: u16) -> Option<Vec<SocketAddr>> {
+    let addrs: Vec<_> = tokio::net::lookup_host((host, port)).await.ok()?.collect();
+    if addrs.is_empty() || !addrs.iter().all(|addr| is_public(addr.ip())) {
+        return None;
+    }
+    Some(addrs)
+}
+
+/// Check if every address that the host of a URL resolves to is public.
+/// URLs that do not resolve are not.
+pub async fn is_public_url(url: &str) -> bool {
+    let uri = match url.parse::<Uri>() {
+        Ok(uri) => uri,
+        Err(_) => return false,
+    };
+    let host = match uri.host() {
+        // IPv6 addresses are bracketed in URLs.
+        Some(host) => host.trim_start_matches('[').trim_end_matches(']'),
+        None => return false,
+    };
+    let port = uri.port_u16().unwrap_or_else(|| match uri.scheme_str() {
+        Some("https") => 443,
+        _ => 80,
+    });
+
+    resolve_public(host, port).await.is_some()
+}
+
+/// Connects deliveries to the addresses that their host resolves to, only
+/// if every one is public. The check and the connection share the same
+/// resolution, so that the host can not resolve to a public address when
+/// checked and to a private one when connected to.
+#[derive(Clone)]
+struct PublicConnector;
+
+impl Service for PublicConnector {
+    type Request = Connect<Uri>;
+    type Response = Connection<Uri, TcpStream>;
+    type Error = ConnectError;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Connect<Uri>) -> Self::Future {
+        Box::pin(async move {
+            let host = req.host().trim_start_matches('[').trim_end_matches(']');
+            let addrs = resolve_public(host, req.port()).await.ok_or_else(|| {
+                ConnectError::Io(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    "Not a public address",
+                ))
+            })?;
+            TcpConnector::new().call(req.set_addrs(addrs)).await
+        })
+    }
+}
+
+/// A body to post to a webhook, until it succeeds or runs out of retries.
+#[derive(Debug)]
+pub struct Delivery {
+    pub url: String,
+    pub secret: String,
+    pub event: WebhookEvent,
+    pub body: String,
+    /// How many times a failed delivery is retried.
+    pub retries: u32,
+    /// Delay before the first retry, doubled for every following one.
+    pub backoff: Duration,
+    /// Deliver to loopback, private and link-local addresses.
+    pub allow_private: bool,
+}
+
+impl Delivery {
+    /// Post the body in the background.
+    pub fn spawn(self) {
+        actix_rt::spawn(self.run());
+    }
+
+    async fn run(self) {
+        let mut client = Client::build().timeout(DELIVERY_TIMEOUT);
+        if !self.allow_private {
+            client = client.connector(Connector::new().connector(PublicConnector).finish());
+        }
+        let client = client.finish();
+        let signature = sign(&self.secret, &self.body);
+        let event = serde_json::to_value(self.event).expect("Serialization error");
+        let event = event.as_str().unwrap_or_default();
+
+        let mut backoff = self.backoff;
+        for attempt in 0..=self.retries {
+            if attempt > 0 {
+                actix_rt::time::delay_for(backoff).await;
+                backoff *= 2;
+            }
+            // The host is resolved again, since its addresses may have changed.
+            // Deliveries are refused here rather than retried, but only the
+            // connector guarantees that private addresses are never reached.
+            if !self.allow_private && !is_public_url(&self.url).await {
+                warn!(
+                    "Refused to deliver {} to {}, which is not public.",
+                    event, self.url
+                );
+                return;
+            }
+            let response = client
+                .post(&self.url)
+                .header(SIGNATURE_HEADER, signature.as_str())
+                .header(EVENT_HEADER, event)
+                .content_type("application/json")
+                .send_body(self.body.clone())
+                .await;
+            match response {
+                Ok(response) if response.status().is_success() => {
+                    debug!("Delivered {} to {}.", event, self.url);
+                    return;
+                }
+                Ok(response) => warn!(
+                    "Webhook {} responded to {} with {}.",
+                    self.url,
+                    event,
+                    response.status()
+                ),
+                Err(err) => warn!("Webhook {} failed on {}: {}.", self.url, event, err),
+            }
+        }
+        warn!("Gave up delivering {} to {}.", event, self.url);
+    }
+}
@@ -1,18 +1,25 @@
-use crate::concierge::{self, Concierge};
+use crate::{
+    concierge::{self, Concierge},
+    record::{self, Record, Recorder},
+    Config,
+};
 use actix::prelude::*;
 use actix_web::{web, Error, HttpRequest, HttpResponse};
 use actix_web_actors::ws::{CloseCode, CloseReason, Message, ProtocolError, WebsocketContext};
-use concierge::{Disconnect, IdentifyPackage, IncomingMessage};
-use concierge_api_rs::{CloseReason as ConciergeCloseReason, PayloadIn};
+use concierge::{Disconnect, IdentifyPackage, IncomingMessage, UpdateLatency};
+use concierge_api_rs::{
+    info::Latency, record::Event, Capability, CloseReason as ConciergeCloseReason, PayloadIn,
+};
 use log::{error, warn};
 use semver::Version;
+use serde_json::value::RawValue;
 use std::time::{Duration, Instant};
 use uuid::Uuid;
 
-/// How often heartbeat pings are sent.
-const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
-/// How long before lack of client response causes a timeout.
-const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often heartbeat pings are sent by default.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+/// How long before lack of client response causes a timeout by default.
+pub const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub const SUBPROTOCOL: &str = "ert-concierge";
 
@@ -21,12 +28,21 @@ pub async fn index(
     req: HttpRequest,
     stream: web::Payload,
     srv: web::Data<Addr<Concierge>>,
+    recorder: web::Data<Option<Addr<Recorder>>>,
+    config: web::Data<Config>,
 ) -> Result<HttpResponse, Error> {
     actix_web_actors::ws::start_with_protocols(
         WsConnection {
             uuid: Uuid::nil(),
             last_hb: Instant::now(),
+            ping: (0, Instant::now()),
+            latency: None,
             c_addr: srv.get_ref().clone(),
+            capabilities: Vec::new(),
+            config,
+            conn: record::next_connection(),
+            recorder: recorder.get_ref().clone(),
+            close_code: None,
         },
         &[SUBPROTOCOL],
         &req,
@@ -37,41 +53,57 @@ pub async fn index(
 pub struct WsConnection {
     pub uuid: Uuid,
     pub last_hb: Instant,
+    /// Number of the last ping sent, and when it was sent.
+    pub ping: (u64, Instant),
+    /// Latency measured from the pongs of the client, once one arrived.
+    pub latency: Option<Latency>,
     pub c_addr: Addr<Concierge>,
+    /// Capabilities negotiated with the client.
+    pub capabilities: Vec<Capability>,
+    /// Settings of the concierge.
+    pub config: web::Data<Config>,
+    /// Number of the connection in recordings.
+    pub conn: u64,
+    /// Recorder of the frames exchanged, if recording.
+    pub recorder: Option<Addr<Recorder>>,
+    /// Code of the close frame sent to the client, if any.
+    pub close_code: Option<u16>,
 }
 
 impl Actor for WsConnection {
     type Context = WebsocketContext<Self>;
 
     fn started(&mut self, ctx: &mut Self::Context) {
-        ctx.run_later(HEARTBEAT_INTERVAL, |ws, ws_ctx| {
+        self.record(|| Event::Open);
+        let interval = self.config.heartbeat_interval;
+        ctx.run_later(interval, |ws, ws_ctx| {
             // If the UUID is still nil after one heartbeat interval,
             // then the client failed to identify.
             if ws.uuid.is_nil() {
-                ws_ctx.close(convert(ConciergeCloseReason::AUTH_FAILED));
-                ws_ctx.stop();
-                return;
+                ws.close(ws_ctx, convert(ConciergeCloseReason::AUTH_FAILED));
             }
         });
-        ctx.run_interval(HEARTBEAT_INTERVAL, |ws, ws_ctx| {
+        ctx.run_interval(interval, |ws, ws_ctx| {
             // If the duration between the current moment and last heartbeat
             // is greater than the timeout threshold, then initiate disconnect.
-            if Instant::now().duration_since(ws.last_hb) > CLIENT_TIMEOUT {
+            if Instant::now().duration_since(ws.last_hb) > ws.config.client_timeout {
                 warn!("WS client {} failed heartbeat. Dropping.", ws.uuid);
                 // Disconnect the connection from the concierge.
                 ws.c_addr.do_send(Disconnect { uuid: ws.uuid });
                 // Close the actor.
-                ws_ctx.close(convert(ConciergeCloseReason::HB_FAILED));
-                ws_ctx.stop();
+                ws.close(ws_ctx, convert(ConciergeCloseReason::HB_FAILED));
             } else {
-                // Send a ping.
-                ws_ctx.ping(&[]);
+                // Send a ping, numbered so that its pong can be timed.
+                ws.ping = (ws.ping.0 + 1, Instant::now());
+                ws_ctx.ping(&ws.ping.0.to_be_bytes());
             }
         });
     }
 
     fn stopping(&mut self, _: &mut Self::Context) -> Running {
         self.c_addr.do_send(Disconnect { uuid: self.uuid });
+        let code = self.close_code;
+        self.record(|| Event::Close { code });
         Running::Stop
     }
 }
@@ -81,15 +113,61 @@ impl Handler<concierge::OutgoingMessage> for WsConnection {
     type Result = ();
 
     fn handle(&mut self, msg: concierge::OutgoingMessage, ctx: &mut Self::Context) {
+        if let Message::Text(text) = &msg.0 {
+            self.record(|| Event::Out {
+                text: text.clone().into(),
+            });
+        }
         ctx.write_raw(msg.0);
     }
 }
 
 /// Verify that a name has only alphanumeric or underscores characters.
-fn verify_name(name: &str) -> bool {
+pub(crate) fn verify_name(name: &str) -> bool {
     name.chars().all(|c| c.is_alphanumeric() || c == '_')
 }
 
+/// Check that the version of a client is compatible with the server.
+fn verify_version(version: &str) -> bool {
+    Version::parse(version).map_or(false, |version| crate::min_version_req().matches(&version))
+}
+
+/// Check the identification of a client, returning the reason
+/// to reject it with if it fails.
+pub(crate) fn verify_identity(
+    config: &Config,
+    name: &str,
+    secret: Option<&str>,
+    version: &str,
+) -> Result<(), ConciergeCloseReason<'static>> {
+    // Check that name is alphanumeric.
+    if !verify_name(name) {
+        return Err(ConciergeCloseReason::BAD_AUTH);
+    }
+    // Check for secret if it is set.
+    let expected = config.secret.as_deref();
+    if expected.is_some() && secret != expected {
+        return Err(ConciergeCloseReason::BAD_SECRET);
+    }
+    // Check that versioning is allowed.
+    if !verify_version(version) {
+        return Err(ConciergeCloseReason::BAD_VERSION);
+    }
+    Ok(())
+}
+
+/// Negotiate the capabilities requested by a client. These are the
+/// requested capabilities that the server supports, without duplicates.
+pub(crate) fn negotiate(requested: Vec<Capability>) -> Vec<Capability> {
+    let mut capabilities = Vec::new();
+    for capability in requested {
+        if crate::CAPABILITIES.contains(&capability) && !capabilities.contains(&capability) {
+            capabilities.push(capability);
+        }
+    }
+    capabilities
+}
+
 fn convert(close_reason: ConciergeCloseReason) -> Option<CloseReason> {
     Some(CloseReason::from((
         CloseCode::Other(close_reason.code),
@@ -97,18 +175,93 @@ fn convert(close_reason: ConciergeCloseReason) -> Option<CloseReason> {
     )))
 }
 
+impl WsConnection {
+    /// Record an event of the connection, if recording.
+    fn record(&self, event: impl FnOnce() -> Event<'static>) {
+        if let Some(recorder) = &self.recorder {
+            recorder.do_send(Record {
+                conn: self.conn,
+                at: Instant::now(),
+                event: event(),
+            });
+        }
+    }
+
+    /// Fold a round-trip time into the latency of the connection, smoothed
+    /// like TCP does (RFC 6298), and report it to the concierge.
+    fn measure(&mut self, rtt: Duration) {
+        let rtt = rtt.as_secs_f64() * 1000.0;
+        let latency = match self.latency {
+            Some(latency) => Latency {
+                rtt: 0.875 * latency.rtt + 0.125 * rtt,
+                jitter: 0.75 * latency.jitter + 0.25 * (latency.rtt - rtt).abs(),
+            },
+            None => Latency {
+                rtt,
+                jitter: rtt / 2.0,
+            },
+        };
+        self.latency = Some(latency);
+        self.c_addr.do_send(UpdateLatency {
+            uuid: self.uuid,
+            latency,
+        });
+    }
+
+    /// Close the connection and stop the actor.
+    fn close(&mut self, ctx: &mut WebsocketContext<Self>, reason: Option<CloseReason>) {
+        self.close_code = reason.as_ref().map(|reason| reason.code.into());
+        ctx.close(reason);
+        ctx.stop();
+    }
+}
+
+/// Relay text from a client to the concierge. If batching was negotiated,
+/// text holding an array is relayed as one message per payload.
+pub(crate) fn relay(
+    concierge: &Addr<Concierge>,
+    uuid: Uuid,
+    capabilities: &[Capability],
+    text: String,
+) {
+    // Payloads are stamped here, rather than once the concierge gets to them.
+    let received = concierge::now_millis();
+    if capabilities.contains(&Capability::Batching) && text.trim_start().starts_with('[') {
+        if let Ok(batch) = serde_json::from_str::<Vec<&RawValue>>(&text) {
+            for payload in batch {
+                concierge.do_send(IncomingMessage {
+                    uuid,
+                    text: payload.get().to_owned(),
+                    received,
+                });
+            }
+            return;
+        }
+    }
+
+    concierge.do_send(IncomingMessage {
+        uuid,
+        text,
+        received,
+    });
+}
+
 /// WebSocket message handler
 impl StreamHandler<Result<Message, ProtocolError>> for WsConnection {
     fn handle(&mut self, msg: Result<Message, ProtocolError>, ctx: &mut Self::Context) {
         let msg = match msg {
             Err(err) => {
                 error!("Dropping a session due to protocol error. Error: {}", err);
-                ctx.close(Some(CloseReason::from(CloseCode::Protocol)));
-                ctx.stop();
+                self.close(ctx, Some(CloseReason::from(CloseCode::Protocol)));
                 return;
             }
             Ok(msg) => msg,
         };
+        if let Message::Text(text) = &msg {
+            self.record(|| Event::In {
+                text: text.clone().into(),
+            });
+        }
 
         if self.uuid.is_nil() {
             if let Message::Text(text) = msg {
@@ -119,61 +272,43 @@ impl StreamHandler<Result<Message, ProtocolError>> for WsConnection {
                         version,
                         secret,
                         tags,
+                        events,
+                        capabilities,
                     }) => {
-                        // Check that name is alphanumeric.
-                        if !verify_name(name) {
-                            ctx.close(convert(ConciergeCloseReason::BAD_AUTH));
-                            ctx.stop();
-                            return;
-                        }
-                        // Check for secret if it is set.
-                        if crate::SECRET.is_some() && secret != crate::SECRET {
-                            ctx.close(convert(ConciergeCloseReason::BAD_SECRET));
-                            ctx.stop();
-                            return;
-                        }
-                        // Check that versioning is allowed.
-                        if !crate::min_version_req().matches(&Version::parse(version).unwrap()) {
-                            ctx.close(convert(ConciergeCloseReason::BAD_VERSION));
-                            ctx.stop();
+                        if let Err(reason) = verify_identity(&self.config, name, secret, version) {
+                            self.close(ctx, convert(reason));
                             return;
                         }
                         // Convert tags to owned.
                         let tags = tags.into_iter().map(str::to_owned).collect();
+                        self.capabilities = negotiate(capabilities);
 
                         self.c_addr
                             .send(IdentifyPackage {
                                 name: name.to_owned(),
                                 nickname: nickname.map(ToOwned::to_owned),
                                 tags,
+                                events,
+                                capabilities: self.capabilities.clone(),
                                 addr: ctx.address().recipient(),
                             })
                             .into_actor(self)
                             .then(|res, act, ctx| {
                                 match res {
                                     Ok(Some(res)) => act.uuid = res,
-                                    Ok(None) => {
-                                        ctx.close(convert(ConciergeCloseReason::DUPLICATE_AUTH));
-                                        ctx.stop()
-                                    }
+                                    Ok(None) => act
+                                        .close(ctx, convert(ConciergeCloseReason::DUPLICATE_AUTH)),
                                     _ => ctx.stop(),
                                 }
                                 fut::ready(())
                             })
                             .wait(ctx);
                     }
-                    Ok(_) => {
-                        ctx.close(convert(ConciergeCloseReason::NO_AUTH));
-                        ctx.stop();
-                    }
-                    Err(_) => {
-                        ctx.close(convert(ConciergeCloseReason::UNKNOWN));
-                        ctx.stop();
-                    }
+                    Ok(_) => self.close(ctx, convert(ConciergeCloseReason::NO_AUTH)),
+                    Err(_) => self.close(ctx, convert(ConciergeCloseReason::UNKNOWN)),
                 }
             } else {
-                ctx.close(convert(ConciergeCloseReason::NO_AUTH));
-                ctx.stop();
+                self.close(ctx, convert(ConciergeCloseReason::NO_AUTH));
             }
         } else {
             match msg {
@@ -182,20 +317,19 @@ impl StreamHandler<Result<Message, ProtocolError>> for WsConnection {
                     self.last_hb = Instant::now();
                     ctx.pong(&msg);
                 }
-                // Received a pong.
-                Message::Pong(_) => self.last_hb = Instant::now(),
+                // Received a pong, which is timed if it answers the last ping.
+                Message::Pong(msg) => {
+                    self.last_hb = Instant::now();
+                    if msg[..] == self.ping.0.to_be_bytes()[..] {
+                        self.measure(self.last_hb - self.ping.1);
+                    }
+                }
                 // Relay the text message from the client to the server.
-                Message::Text(text) => self.c_addr.do_send(IncomingMessage {
-                    uuid: self.uuid,
-                    text,
-                }),
+                Message::Text(text) => relay(&self.c_addr, self.uuid, &self.capabilities, text),
                 // We don't handle binary (yet!).
                 Message::Binary(_) => warn!("Received binary message."),
                 // The client socket closed on us. Stop the actor.
-                Message::Close(reason) => {
-                    ctx.close(reason);
-                    ctx.stop();
-                }
+                Message::Close(reason) => self.close(ctx, reason),
                 // We don't really know what to do with a continuation frame yet.
                 Message::Continuation(_) => {
                     ctx.stop();
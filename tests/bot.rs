@@ -0,0 +1,155 @@
+//! Scenarios covering services hosted with the `ServiceRunner` of the client crate.
+
+mod harness;
+
+use concierge_api_rs::info;
+use concierge_client::{async_trait, ClientBuilder, ServiceBot, ServiceHandle, ServiceRunner};
+use harness::{within, Proxy, Script, TestServer};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    time::delay_for,
+};
+use uuid::Uuid;
+
+/// A callback of the bot.
+#[derive(Debug, PartialEq)]
+enum Call {
+    Subscribe(Uuid),
+    Unsubscribe(Uuid),
+    Message(Uuid, Value),
+}
+
+/// A bot that reports its callbacks.
+struct Probe(UnboundedSender<Call>);
+
+#[async_trait]
+impl ServiceBot for Probe {
+    type Message = Value;
+
+    async fn on_subscribe(&self, _: &ServiceHandle, client: &info::Client<'static>) {
+        let _ = self.0.send(Call::Subscribe(client.uuid));
+    }
+
+    async fn on_unsubscribe(&self, _: &ServiceHandle, client: &info::Client<'static>) {
+        let _ = self.0.send(Call::Unsubscribe(client.uuid));
+    }
+
+    async fn on_message(&self, _: &ServiceHandle, origin: &info::Client<'static>, data: Value) {
+        let _ = self.0.send(Call::Message(origin.uuid, data));
+    }
+}
+
+/// Host the probe as the service "echo", once it is created.
+async fn host(builder: ClientBuilder) -> UnboundedReceiver<Call> {
+    let (tx, calls) = unbounded_channel();
+    let hosting = within(ServiceRunner::new(builder, "echo").start(Probe(tx)))
+        .await
+        .expect("Service");
+    tokio::spawn(hosting);
+    calls
+}
+
+async fn next(calls: &mut UnboundedReceiver<Call>) -> Call {
+    within(calls.recv()).await.expect("Call")
+}
+
+/// Subscribe to the service, as its only subscriber besides the bot.
+async fn subscribe(client: &mut Script, seq: usize) {
+    client
+        .send(json!({ "type": "SELF_SUBSCRIBE", "service": "echo" }))
+        .await;
+    client
+        .expect(json!({ "type": "SELF_SUBSCRIBE_RESULT", "seq": seq, "successful": true }))
+        .await;
+    client
+        .expect(json!({ "type": "SERVICE_CLIENT_SUBSCRIBED" }))
+        .await;
+}
+
+/// Wait until the bot owns the service and is subscribed to it under a uuid
+/// other than `previous`, returning its uuid.
+async fn hosted(client: &mut Script, previous: Option<Uuid>) -> Uuid {
+    within(async {
+        loop {
+            client
+                .send(json!({ "type": "SERVICE_FETCH", "service": "echo" }))
+                .await;
+            let service = client.expect(json!({})).await["service"].take();
+            let owner = service["owner_uuid"]
+                .as_str()
+                .and_then(|uuid| uuid.parse().ok());
+            if let Some(owner) = owner.filter(|&owner| Some(owner) != previous) {
+                if service["subscribers"] == json!([owner]) {
+                    return owner;
+                }
+            }
+            delay_for(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+}
+
+#[actix_rt::test]
+async fn subscribers() {
+    let server = TestServer::start().await;
+    let mut calls = host(ClientBuilder::new(server.ws_url(), "echo_bot")).await;
+    let mut alice = server.connect().await.identify("alice").await;
+    let alice_uuid = alice.uuid;
+
+    // The subscription of the bot itself is not reported.
+    subscribe(&mut alice, 0).await;
+    assert_eq!(next(&mut calls).await, Call::Subscribe(alice_uuid));
+
+    // Payloads reach the bot in order, whether they are messages or not.
+    alice
+        .send(json!({
+            "type": "MESSAGE",
+            "target": { "type": "SERVICE", "service": "echo" },
+            "data": { "n": 1 },
+        }))
+        .await;
+    alice.expect(json!({ "type": "OK", "seq": 1 })).await;
+    alice
+        .send(json!({ "type": "SELF_UNSUBSCRIBE", "service": "echo" }))
+        .await;
+    alice
+        .expect(json!({ "type": "SELF_UNSUBSCRIBE_RESULT", "seq": 2, "successful": true }))
+        .await;
+    assert_eq!(
+        next(&mut calls).await,
+        Call::Message(alice_uuid, json!({ "n": 1 }))
+    );
+    assert_eq!(next(&mut calls).await, Call::Unsubscribe(alice_uuid));
+}
+
+#[actix_rt::test]
+async fn reconnection() {
+    let server = TestServer::start().await;
+    let proxy = Proxy::start(server.addr).await;
+    let builder =
+        ClientBuilder::new(proxy.ws_url(), "echo_bot").reconnect(Duration::from_millis(50));
+    let mut calls = host(builder).await;
+    let mut alice = server.connect().await.identify("alice").await;
+    let alice_uuid = alice.uuid;
+    let bot_uuid = hosted(&mut alice, None).await;
+    subscribe(&mut alice, 1).await;
+    assert_eq!(next(&mut calls).await, Call::Subscribe(alice_uuid));
+
+    // The concierge removes the service along with the bot, which is told
+    // that every subscriber is gone.
+    proxy.sever();
+    assert_eq!(next(&mut calls).await, Call::Unsubscribe(alice_uuid));
+
+    // The service is created again once the bot is back.
+    let reconnected_uuid = hosted(&mut alice, Some(bot_uuid)).await;
+    assert_ne!(reconnected_uuid, bot_uuid);
+    alice
+        .send(json!({ "type": "SELF_SUBSCRIBE", "service": "echo" }))
+        .await;
+    alice
+        .expect(json!({ "type": "SELF_SUBSCRIBE_RESULT", "successful": true }))
+        .await;
+    assert_eq!(next(&mut calls).await, Call::Subscribe(alice_uuid));
+}
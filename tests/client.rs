@@ -0,0 +1,151 @@
+//! Scenarios covering the Rust client crate.
+
+mod harness;
+
+use concierge_api_rs::{PayloadIn, PayloadOut};
+use concierge_client::{ClientBuilder, Connection, Error};
+use futures::future;
+use harness::{within, Proxy, TestServer, SILENCE};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::{sync::mpsc::unbounded_channel, time::delay_for};
+
+#[actix_rt::test]
+async fn responses_follow_their_requests() {
+    let server = TestServer::start().await;
+    let Connection { client, .. } = within(ClientBuilder::new(server.ws_url(), "alice").connect())
+        .await
+        .expect("Connection");
+
+    // Nobody waits for the response of a payload sent without an id.
+    client.send(&PayloadIn::SelfFetch).expect("Send");
+
+    // Requests in flight at once are told apart by their ids.
+    let requests = (0..16).map(|index| {
+        let client = client.clone();
+        async move {
+            client
+                .request(&PayloadIn::TimeSync {
+                    client_time: f64::from(index),
+                })
+                .await
+        }
+    });
+    let responses = within(future::join_all(requests)).await;
+    for (index, response) in responses.into_iter().enumerate() {
+        let response = response.expect("Response");
+        assert_eq!(response.seq(), Some(index + 1));
+        match response.payload().expect("Payload") {
+            PayloadOut::TimeSyncResult { client_time, .. } => {
+                assert_eq!(client_time, index as f64)
+            }
+            payload => panic!("Expected TIME_SYNC_RESULT but received {:?}", payload),
+        }
+    }
+}
+
+#[actix_rt::test]
+async fn messages_are_kept_apart_from_events() {
+    let server = TestServer::start().await;
+    let Connection {
+        client,
+        mut messages,
+        mut events,
+    } = within(ClientBuilder::new(server.ws_url(), "alice").connect())
+        .await
+        .expect("Connection");
+
+    let mut bob = server.connect().await.identify("bob").await;
+    let joined = within(events.recv()).await.expect("Event");
+    match joined.payload().expect("Payload") {
+        PayloadOut::ClientJoined { client } => assert_eq!(client.name, "bob"),
+        payload => panic!("Expected CLIENT_JOINED but received {:?}", payload),
+    }
+
+    bob.send(json!({
+        "type": "MESSAGE",
+        "target": { "type": "UUID", "uuid": client.uuid() },
+        "data": { "text": "hi" },
+    }))
+    .await;
+    bob.expect(json!({ "type": "OK", "seq": 0 })).await;
+    let message = within(messages.recv())
+        .await
+        .expect("Message")
+        .service_message::<Value>()
+        .expect("Message data");
+    assert_eq!(message.data, json!({ "text": "hi" }));
+    assert_eq!(message.origin.expect("Origin").client.uuid, bob.uuid);
+}
+
+#[actix_rt::test]
+async fn pending_requests_fail_on_disconnect() {
+    let server = TestServer::start().await;
+    let proxy = Proxy::start(server.addr).await;
+    let Connection { client, .. } = within(ClientBuilder::new(proxy.ws_url(), "alice").connect())
+        .await
+        .expect("Connection");
+
+    // The request reaches the concierge, but not its response.
+    proxy.stall();
+    let sever = async {
+        delay_for(SILENCE).await;
+        proxy.sever();
+    };
+    let (response, ()) = within(future::join(client.request(&PayloadIn::SelfFetch), sever)).await;
+    assert!(
+        matches!(response, Err(Error::Disconnected)),
+        "{:?}",
+        response
+    );
+
+    // Without reconnecting, the client stays disconnected.
+    assert!(!client.is_connected());
+    assert!(matches!(
+        client.send(&PayloadIn::SelfFetch),
+        Err(Error::Disconnected)
+    ));
+}
+
+#[actix_rt::test]
+async fn clients_reconnect() {
+    let server = TestServer::start().await;
+    let proxy = Proxy::start(server.addr).await;
+    let (connected_tx, mut connected) = unbounded_channel();
+    let (disconnected_tx, mut disconnected) = unbounded_channel();
+    let Connection { client, .. } = within(
+        ClientBuilder::new(proxy.ws_url(), "alice")
+            .reconnect(Duration::from_millis(50))
+            .on_connect(move |client| {
+                let _ = connected_tx.send(client.uuid());
+            })
+            .on_disconnect(move |_| {
+                let _ = disconnected_tx.send(());
+            })
+            .connect(),
+    )
+    .await
+    .expect("Connection");
+    let first = within(connected.recv()).await.expect("Connected");
+
+    // The concierge forgets about the client, which identifies again.
+    proxy.sever();
+    within(disconnected.recv()).await.expect("Disconnected");
+    let second = within(connected.recv()).await.expect("Reconnected");
+    assert_ne!(first, second);
+    assert_eq!(client.uuid(), second);
+
+    let response = within(client.request(&PayloadIn::SelfFetch))
+        .await
+        .expect("Response");
+    match response.payload().expect("Payload") {
+        PayloadOut::SelfFetchResult { client, .. } => assert_eq!(client.uuid, second),
+        payload => panic!("Expected SELF_FETCH_RESULT but received {:?}", payload),
+    }
+
+    // Closing the connection on purpose does not reconnect.
+    client.close().expect("Close");
+    within(disconnected.recv()).await.expect("Disconnected");
+    delay_for(SILENCE).await;
+    assert!(!client.is_connected());
+}
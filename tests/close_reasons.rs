@@ -0,0 +1,113 @@
+//! Scenarios covering every path through which the concierge closes a
+//! connection with a `CloseReason`. `FATAL_DECODE` is never sent by the
+//! concierge, which answers undecodable payloads with `ERROR_PROTOCOL`.
+
+mod harness;
+
+use concierge_api_rs::CloseReason;
+use harness::TestServer;
+use serde_json::json;
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::Message;
+
+#[actix_rt::test]
+async fn undecodable_identification() {
+    let server = TestServer::start().await;
+    let mut client = server.connect().await;
+    client.send_frame(Message::Text("{ not json".into())).await;
+    client.expect_close(CloseReason::UNKNOWN).await;
+}
+
+#[actix_rt::test]
+async fn payload_before_identification() {
+    let server = TestServer::start().await;
+    let mut client = server.connect().await;
+    client.send(json!({ "type": "SELF_FETCH" })).await;
+    client.expect_close(CloseReason::NO_AUTH).await;
+
+    let mut client = server.connect().await;
+    client.send_frame(Message::Binary(vec![1, 2, 3])).await;
+    client.expect_close(CloseReason::NO_AUTH).await;
+}
+
+#[actix_rt::test]
+async fn identification_timeout() {
+    let server = TestServer::with_config(|config| {
+        config.heartbeat_interval = Duration::from_millis(100);
+    })
+    .await;
+    let client = server.connect().await;
+    client.expect_close(CloseReason::AUTH_FAILED).await;
+}
+
+#[actix_rt::test]
+async fn duplicate_name() {
+    let server = TestServer::start().await;
+    let _alice = server.connect().await.identify("alice").await;
+    let mut impostor = server.connect().await;
+    impostor
+        .send(json!({ "type": "IDENTIFY", "name": "alice", "version": ert_concierge::VERSION }))
+        .await;
+    impostor.expect_close(CloseReason::DUPLICATE_AUTH).await;
+}
+
+#[actix_rt::test]
+async fn secret_mismatch() {
+    let server = TestServer::with_config(|config| {
+        config.secret = Some("hunter2".to_owned());
+    })
+    .await;
+    let mut client = server.connect().await;
+    client
+        .send(json!({
+            "type": "IDENTIFY",
+            "name": "alice",
+            "version": ert_concierge::VERSION,
+            "secret": "hunter3",
+        }))
+        .await;
+    client.expect_close(CloseReason::BAD_SECRET).await;
+
+    server
+        .connect()
+        .await
+        .identify_with(json!({ "name": "alice", "secret": "hunter2" }))
+        .await;
+}
+
+#[actix_rt::test]
+async fn version_mismatch() {
+    let server = TestServer::start().await;
+    for version in &["0.1.0", "0.5.0", "latest"] {
+        let mut client = server.connect().await;
+        client
+            .send(json!({ "type": "IDENTIFY", "name": "alice", "version": version }))
+            .await;
+        client.expect_close(CloseReason::BAD_VERSION).await;
+    }
+}
+
+#[actix_rt::test]
+async fn invalid_name() {
+    let server = TestServer::start().await;
+    let mut client = server.connect().await;
+    client
+        .send(
+            json!({ "type": "IDENTIFY", "name": "not alice!", "version": ert_concierge::VERSION }),
+        )
+        .await;
+    client.expect_close(CloseReason::BAD_AUTH).await;
+}
+
+#[actix_rt::test]
+async fn heartbeat_timeout() {
+    // The heartbeat is checked before pinging, so the connection times out
+    // at the first interval, before the client had a chance to answer.
+    let server = TestServer::with_config(|config| {
+        config.heartbeat_interval = Duration::from_millis(300);
+        config.client_timeout = Duration::from_millis(100);
+    })
+    .await;
+    let client = server.connect().await.identify("alice").await;
+    client.expect_close(CloseReason::HB_FAILED).await;
+}
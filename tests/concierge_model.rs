@@ -0,0 +1,257 @@
+//! Stateful property tests of the `Concierge` state machine. Random
+//! sequences of operations are applied to the handlers directly, checking
+//! after every step that the bookkeeping of clients, services and
+//! subscriptions stays consistent.
+
+use actix::prelude::*;
+use ert_concierge::concierge::{
+    Concierge, Disconnect, IdentifyPackage, IncomingMessage, OutgoingMessage,
+};
+use proptest::prelude::*;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+/// Names are drawn from small pools, so that operations collide often.
+const NAMES: &[&str] = &["alice", "bob", "carol", "dave"];
+const SERVICES: &[&str] = &["room/a", "room/b", "lobby", "room/+", "#"];
+
+/// Receives the payloads sent to the clients, discarding them.
+struct Sink;
+
+impl Actor for Sink {
+    type Context = Context<Self>;
+}
+
+impl Handler<OutgoingMessage> for Sink {
+    type Result = ();
+
+    fn handle(&mut self, _: OutgoingMessage, _: &mut Self::Context) {}
+}
+
+#[derive(Clone, Debug)]
+enum Op {
+    Identify(usize),
+    Disconnect(usize),
+    Subscribe(usize, usize, bool),
+    Unsubscribe(usize, usize),
+    Create(usize, usize),
+    Delete(usize, usize),
+    MessageService(usize, usize),
+    MessageClient(usize, usize),
+    QueueClient(usize, usize),
+    MessageAll(usize),
+    Raw(usize, String),
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    let client = 0..NAMES.len();
+    let service = 0..SERVICES.len();
+    prop_oneof![
+        client.clone().prop_map(Op::Identify),
+        client.clone().prop_map(Op::Disconnect),
+        (client.clone(), service.clone(), any::<bool>())
+            .prop_map(|(c, s, filter)| Op::Subscribe(c, s, filter)),
+        (client.clone(), service.clone()).prop_map(|(c, s)| Op::Unsubscribe(c, s)),
+        (client.clone(), service.clone()).prop_map(|(c, s)| Op::Create(c, s)),
+        (client.clone(), service.clone()).prop_map(|(c, s)| Op::Delete(c, s)),
+        (client.clone(), service).prop_map(|(c, s)| Op::MessageService(c, s)),
+        (client.clone(), client.clone()).prop_map(|(c, t)| Op::MessageClient(c, t)),
+        (client.clone(), client.clone()).prop_map(|(c, t)| Op::QueueClient(c, t)),
+        client.clone().prop_map(Op::MessageAll),
+        (client, ".{0,64}").prop_map(|(c, text)| Op::Raw(c, text)),
+    ]
+}
+
+/// A concierge driven by direct calls to its handlers.
+struct Model {
+    concierge: Concierge,
+    ctx: Context<Concierge>,
+    sink: Context<Sink>,
+    /// Last uuid of every client slot, kept after disconnection so that
+    /// messages still in flight from disconnected clients are exercised.
+    uuids: Vec<Option<Uuid>>,
+}
+
+impl Model {
+    fn new() -> Self {
+        let fs_root = std::env::temp_dir().join(format!("concierge-model-{}", Uuid::new_v4()));
+        Self {
+            concierge: Concierge::new(fs_root),
+            ctx: Context::new(),
+            sink: Context::new(),
+            uuids: vec![None; NAMES.len()],
+        }
+    }
+
+    fn send(&mut self, client: usize, payload: Value) {
+        self.send_text(client, payload.to_string());
+    }
+
+    fn send_text(&mut self, client: usize, text: String) {
+        if let Some(uuid) = self.uuids[client] {
+            let message = IncomingMessage {
+                uuid,
+                text,
+                received: 0.0,
+            };
+            self.concierge.handle(message, &mut self.ctx);
+        }
+    }
+
+    fn apply(&mut self, op: Op) {
+        match op {
+            Op::Identify(client) => {
+                let package = IdentifyPackage {
+                    name: NAMES[client].to_owned(),
+                    nickname: None,
+                    tags: Vec::new(),
+                    events: None,
+                    capabilities: Vec::new(),
+                    addr: self.sink.address().recipient(),
+                };
+                if let Some(uuid) = self.concierge.handle(package, &mut self.ctx).0 {
+                    self.uuids[client] = Some(uuid);
+                }
+            }
+            Op::Disconnect(client) => {
+                if let Some(uuid) = self.uuids[client] {
+                    self.concierge.handle(Disconnect { uuid }, &mut self.ctx);
+                }
+            }
+            Op::Subscribe(client, service, filter) => {
+                let filter = if filter {
+                    json!({ "op": "EQ", "pointer": "/x", "value": 1 })
+                } else {
+                    Value::Null
+                };
+                self.send(
+                    client,
+                    json!({ "type": "SELF_SUBSCRIBE", "service": SERVICES[service], "filter": filter }),
+                );
+            }
+            Op::Unsubscribe(client, service) => self.send(
+                client,
+                json!({ "type": "SELF_UNSUBSCRIBE", "service": SERVICES[service] }),
+            ),
+            Op::Create(client, service) => self.send(
+                client,
+                json!({ "type": "SERVICE_CREATE", "service": SERVICES[service] }),
+            ),
+            Op::Delete(client, service) => self.send(
+                client,
+                json!({ "type": "SERVICE_DELETE", "service": SERVICES[service] }),
+            ),
+            Op::MessageService(client, service) => self.send(
+                client,
+                json!({
+                    "type": "MESSAGE",
+                    "target": { "type": "SERVICE", "service": SERVICES[service] },
+                    "data": { "x": 1 },
+                }),
+            ),
+            Op::MessageClient(client, target) => self.send(
+                client,
+                json!({
+                    "type": "MESSAGE",
+                    "target": { "type": "NAME", "name": NAMES[target] },
+                    "data": null,
+                }),
+            ),
+            Op::QueueClient(client, target) => self.send(
+                client,
+                json!({
+                    "type": "MESSAGE",
+                    "target": { "type": "NAME", "name": NAMES[target] },
+                    "data": null,
+                    "queue": true,
+                }),
+            ),
+            Op::MessageAll(client) => self.send(
+                client,
+                json!({ "type": "MESSAGE", "target": { "type": "ALL" }, "data": [] }),
+            ),
+            Op::Raw(client, text) => self.send_text(client, text),
+        }
+    }
+
+    fn check_invariants(&self) {
+        let Concierge {
+            services,
+            namespace,
+            clients,
+            patterns,
+            queues,
+            ..
+        } = &self.concierge;
+
+        // The namespace indexes every client by name.
+        assert_eq!(namespace.len(), clients.len());
+        for (name, uuid) in namespace {
+            let client = clients.get(uuid).expect("Namespace entry without a client");
+            assert_eq!(&client.name, name);
+        }
+
+        // Subscriptions are mirrored by clients and services.
+        for client in clients.values() {
+            for service_name in &client.subscriptions {
+                let service = services
+                    .get(service_name)
+                    .expect("Subscription to a missing service");
+                assert!(service.subscribers.contains(&client.uuid));
+            }
+        }
+        for (name, service) in services {
+            assert_eq!(&service.name, name);
+            // No dangling owners.
+            assert!(clients.contains_key(&service.owner_uuid));
+            for uuid in &service.subscribers {
+                let client = clients.get(uuid).expect("Missing subscriber");
+                assert!(client.subscriptions.contains(name));
+            }
+            for uuid in service.filters.keys() {
+                assert!(service.subscribers.contains(uuid));
+            }
+        }
+
+        // Messages are only held for offline clients, up to the capacity.
+        for name in NAMES {
+            let held = queues.held(name);
+            assert!(held <= queues.capacity);
+            assert!(held == 0 || !namespace.contains_key(*name));
+        }
+
+        // Pattern subscriptions are mirrored by clients and the index.
+        for client in clients.values() {
+            for pattern in client.patterns.keys() {
+                assert!(patterns
+                    .get(pattern)
+                    .map_or(false, |uuids| uuids.contains(&client.uuid)));
+            }
+        }
+        for client in clients.values() {
+            assert!(client
+                .pattern_subscriptions
+                .is_subset(&client.subscriptions));
+        }
+        for (pattern, uuids) in patterns {
+            assert!(!uuids.is_empty());
+            for uuid in uuids {
+                let client = clients.get(uuid).expect("Missing pattern subscriber");
+                assert!(client.patterns.contains_key(pattern));
+            }
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(256))]
+
+    #[test]
+    fn invariants_hold(ops in prop::collection::vec(op(), 1..64)) {
+        let mut model = Model::new();
+        for op in ops {
+            model.apply(op);
+            model.check_invariants();
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! Scenarios covering the file system routes, served from a temporary root.
+
+mod harness;
+
+use ert_concierge::fs::FS_KEY_HEADER;
+use harness::{within, TestServer, TIMEOUT};
+use reqwest::StatusCode;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+#[actix_rt::test]
+async fn files_of_clients() {
+    let server = TestServer::start().await;
+    let alice = server.connect().await.identify("alice").await;
+    let bob = server.connect().await.identify("bob").await;
+    let http = reqwest::Client::new();
+
+    let path = server.file_path("alice", "notes.txt");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, "remember the milk").unwrap();
+    let url = server.url("/fs/alice/notes.txt");
+
+    // Any identified client can download the files of others.
+    let response = within(
+        http.get(&url)
+            .header(FS_KEY_HEADER, bob.uuid.to_string())
+            .send(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text().await.unwrap(), "remember the milk");
+
+    let response = within(http.get(&url).send()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PRECONDITION_REQUIRED);
+    let response = within(
+        http.get(&url)
+            .header(FS_KEY_HEADER, Uuid::new_v4().to_string())
+            .send(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // Only the client can delete its own files.
+    let response = within(
+        http.delete(&url)
+            .header(FS_KEY_HEADER, bob.uuid.to_string())
+            .send(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(path.exists());
+    let response = within(
+        http.delete(&url)
+            .header(FS_KEY_HEADER, alice.uuid.to_string())
+            .send(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!path.exists());
+
+    // The files of a client are removed once it leaves.
+    let dir = server.config.fs_root.join("alice");
+    std::fs::write(dir.join("draft.txt"), "").unwrap();
+    alice.close().await;
+    let start = Instant::now();
+    while dir.exists() {
+        assert!(start.elapsed() < TIMEOUT, "Files of alice were kept");
+        tokio::time::delay_for(Duration::from_millis(20)).await;
+    }
+}
@@ -0,0 +1,393 @@
+//! Scripted clients of a concierge served in-process on an ephemeral port.
+//!
+//! Scenarios are written as steps, each of which fails the test if the
+//! concierge does not respond as expected within `TIMEOUT`:
+//!
+//! ```ignore
+//! let server = TestServer::start().await;
+//! let mut alice = server.connect().await.identify("alice").await;
+//! alice.send(json!({ "type": "SELF_FETCH" })).await;
+//! alice.expect(json!({ "type": "SELF_FETCH_RESULT", "seq": 0 })).await;
+//! ```
+
+// Every test file uses a different subset of the harness.
+#![allow(dead_code)]
+
+use actix::prelude::*;
+use actix_web::{dev::Server, web, App, HttpServer};
+use concierge_api_rs::CloseReason;
+use ert_concierge::{
+    concierge::Concierge, http::Sessions, plugin, raw::Acceptor, record::Recorder, Config, VERSION,
+};
+use futures::{
+    future::{self, AbortHandle},
+    Future, SinkExt, StreamExt,
+};
+use serde_json::{json, Value};
+use std::{
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tokio_tungstenite::{tungstenite::Message, WebSocketStream};
+use uuid::Uuid;
+
+/// How long a step waits for the concierge.
+pub const TIMEOUT: Duration = Duration::from_secs(2);
+/// How long the concierge must stay quiet for `expect_silence`.
+pub const SILENCE: Duration = Duration::from_millis(200);
+
+/// Await a step, failing the test if it takes longer than `TIMEOUT`.
+pub async fn within<F: Future>(step: F) -> F::Output {
+    tokio::time::timeout(TIMEOUT, step)
+        .await
+        .expect("Step timed out")
+}
+
+/// Check that a value matches a pattern. Objects match if every field of
+/// the pattern matches, so fields that a scenario does not care about, such
+/// as generated uuids, can be left out. Other values must be equal.
+pub fn matches(pattern: &Value, value: &Value) -> bool {
+    match (pattern, value) {
+        (Value::Object(pattern), Value::Object(value)) => pattern.iter().all(|(key, pattern)| {
+            value
+                .get(key)
+                .map_or(false, |value| matches(pattern, value))
+        }),
+        (Value::Array(pattern), Value::Array(value)) => {
+            pattern.len() == value.len()
+                && pattern
+                    .iter()
+                    .zip(value)
+                    .all(|(pattern, value)| matches(pattern, value))
+        }
+        _ => pattern == value,
+    }
+}
+
+/// A concierge served on an ephemeral port, storing files under a
+/// temporary directory that is removed along with the server.
+pub struct TestServer {
+    pub addr: SocketAddr,
+    /// Address of the raw transport over TCP.
+    pub raw_addr: SocketAddr,
+    /// Path of the raw transport over a Unix domain socket.
+    pub raw_path: PathBuf,
+    pub config: Config,
+    server: Server,
+}
+
+impl TestServer {
+    /// Serve a concierge with the default settings.
+    pub async fn start() -> Self {
+        Self::with_config(|_| {}).await
+    }
+
+    /// Serve a concierge with adjusted settings.
+    pub async fn with_config(adjust: impl FnOnce(&mut Config)) -> Self {
+        let mut config = Config {
+            fs_root: std::env::temp_dir().join(format!("concierge-{}", Uuid::new_v4())),
+            ..Config::default()
+        };
+        adjust(&mut config);
+        std::fs::create_dir_all(&config.fs_root).expect("Temporary fs root");
+
+        let concierge = Concierge::new(config.fs_root.clone())
+            .with_queues(config.queue_ttl, config.queue_capacity)
+            .with_webhooks(config.webhook_retries, config.webhook_backoff)
+            .with_private_webhooks(config.webhook_private)
+            .start();
+        for name in &config.plugins {
+            assert!(plugin::start(name, concierge.clone()), "Unknown plugin");
+        }
+        if let Some(dir) = &config.scripts {
+            plugin::Scripts::new(dir.clone(), concierge.clone()).start();
+        }
+        let recorder = config
+            .record
+            .as_ref()
+            .map(|path| Recorder::create(path).expect("Recording").start());
+        let acceptor = Acceptor {
+            server: concierge.clone(),
+            recorder: recorder.clone(),
+            config: web::Data::new(config.clone()),
+        };
+        let raw_addr = acceptor
+            .clone()
+            .bind_tcp(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .await
+            .expect("Ephemeral port");
+        let raw_path = config.fs_root.with_extension("sock");
+        acceptor.bind_unix(&raw_path).expect("Unix socket");
+        let sessions = Sessions::default();
+        let routes = config.clone();
+        let server = HttpServer::new(move || {
+            App::new().configure(ert_concierge::routes(
+                concierge.clone(),
+                recorder.clone(),
+                sessions.clone(),
+                routes.clone(),
+            ))
+        })
+        .workers(1)
+        .disable_signals()
+        .bind("127.0.0.1:0")
+        .expect("Ephemeral port");
+        let addr = server.addrs()[0];
+
+        Self {
+            addr,
+            raw_addr,
+            raw_path,
+            config,
+            server: server.run(),
+        }
+    }
+
+    /// Url of an HTTP route of the server.
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// Url of the websocket endpoint of the server.
+    pub fn ws_url(&self) -> String {
+        format!("ws://{}/ws", self.addr)
+    }
+
+    /// Path of a file of a client under the fs root.
+    pub fn file_path(&self, name: &str, file_name: &str) -> PathBuf {
+        self.config.fs_root.join(name).join(file_name)
+    }
+
+    /// Open a websocket connection, without identifying.
+    pub async fn connect(&self) -> Script {
+        let stream = within(TcpStream::connect(self.addr))
+            .await
+            .expect("Connection");
+        let url = self.ws_url();
+        let (ws, _) = within(tokio_tungstenite::client_async(url.as_str(), stream))
+            .await
+            .expect("Websocket handshake");
+        Script {
+            ws,
+            uuid: Uuid::nil(),
+        }
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        // The stop command is sent eagerly, there is no need to await it.
+        let _ = self.server.stop(false);
+        let _ = std::fs::remove_dir_all(&self.config.fs_root);
+        let _ = std::fs::remove_file(&self.raw_path);
+    }
+}
+
+/// A scripted websocket client.
+pub struct Script {
+    ws: WebSocketStream<TcpStream>,
+    /// Uuid assigned by the concierge, nil until identified.
+    pub uuid: Uuid,
+}
+
+impl Script {
+    /// Identify with a name and expect `HELLO`. The client does not receive
+    /// global events, so that scenarios only see the payloads they expect.
+    pub async fn identify(self, name: &str) -> Self {
+        self.identify_with(json!({ "name": name, "events": [] }))
+            .await
+    }
+
+    /// Identify with these fields of `IDENTIFY` and expect `HELLO`.
+    /// The `version` defaults to the one of the server.
+    pub async fn identify_with(mut self, fields: Value) -> Self {
+        let mut payload = json!({ "type": "IDENTIFY", "version": VERSION });
+        if let (Some(object), Value::Object(fields)) = (payload.as_object_mut(), fields) {
+            object.extend(fields);
+        }
+        self.send(payload).await;
+        let hello = self.expect(json!({ "type": "HELLO" })).await;
+        self.uuid = hello["uuid"]
+            .as_str()
+            .and_then(|uuid| uuid.parse().ok())
+            .expect("Uuid in HELLO");
+        self
+    }
+
+    /// Send a payload.
+    pub async fn send(&mut self, payload: Value) {
+        self.send_frame(Message::Text(payload.to_string())).await
+    }
+
+    /// Send a frame verbatim, such as malformed text or binary data.
+    pub async fn send_frame(&mut self, frame: Message) {
+        within(self.ws.send(frame)).await.expect("Send frame")
+    }
+
+    /// Receive the next payload, which must match the pattern (see `matches`).
+    pub async fn expect(&mut self, pattern: Value) -> Value {
+        let payload = within(self.next_payload()).await;
+        assert!(
+            matches(&pattern, &payload),
+            "Expected {} but received {}",
+            pattern,
+            payload
+        );
+        payload
+    }
+
+    /// Expect that no payload arrives for a while.
+    pub async fn expect_silence(&mut self) {
+        if let Ok(payload) = tokio::time::timeout(SILENCE, self.next_payload()).await {
+            panic!("Expected silence but received {}", payload);
+        }
+    }
+
+    /// Expect the concierge to close the connection with that reason.
+    pub async fn expect_close(mut self, reason: CloseReason<'_>) {
+        loop {
+            match within(self.ws.next()).await {
+                Some(Ok(Message::Close(Some(frame)))) => {
+                    assert_eq!(
+                        u16::from(frame.code),
+                        reason.code,
+                        "Closed with {:?}",
+                        frame
+                    );
+                    return;
+                }
+                Some(Ok(Message::Text(text))) => {
+                    panic!("Expected close {} but received {}", reason.code, text)
+                }
+                Some(Ok(Message::Ping(_))) | Some(Ok(Message::Pong(_))) => {}
+                frame => panic!("Expected close {} but received {:?}", reason.code, frame),
+            }
+        }
+    }
+
+    /// Send a close frame of the client.
+    pub async fn close(mut self) {
+        within(self.ws.close(None)).await.expect("Close");
+    }
+
+    /// The next text payload, skipping heartbeats.
+    async fn next_payload(&mut self) -> Value {
+        loop {
+            match self.ws.next().await {
+                Some(Ok(Message::Text(text))) => {
+                    return serde_json::from_str(&text).expect("JSON payload")
+                }
+                Some(Ok(Message::Ping(_))) | Some(Ok(Message::Pong(_))) => {}
+                frame => panic!("Expected a payload but received {:?}", frame),
+            }
+        }
+    }
+}
+
+/// A TCP proxy in front of a server, whose connections can be held up or
+/// dropped as if the network failed, without the concierge being told.
+pub struct Proxy {
+    pub addr: SocketAddr,
+    /// Whether the bytes from the server are held up.
+    stalled: Arc<AtomicBool>,
+    links: Arc<Mutex<Vec<AbortHandle>>>,
+    accept: AbortHandle,
+}
+
+impl Proxy {
+    /// Relay the connections accepted on an ephemeral port to `target`.
+    pub async fn start(target: SocketAddr) -> Self {
+        let mut listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .await
+            .expect("Ephemeral port");
+        let addr = listener.local_addr().expect("Proxy address");
+        let stalled = Arc::new(AtomicBool::new(false));
+        let links = Arc::new(Mutex::new(Vec::new()));
+
+        let accept_loop = {
+            let stalled = stalled.clone();
+            let links = links.clone();
+            async move {
+                while let Ok((mut client, _)) = listener.accept().await {
+                    let mut server = match TcpStream::connect(target).await {
+                        Ok(server) => server,
+                        Err(_) => continue,
+                    };
+                    let stalled = stalled.clone();
+                    let (link, handle) = future::abortable(async move {
+                        let (client_read, client_write) = client.split();
+                        let (server_read, server_write) = server.split();
+                        let up = pump(client_read, server_write, None);
+                        let down = pump(server_read, client_write, Some(&*stalled));
+                        future::select(Box::pin(up), Box::pin(down)).await;
+                    });
+                    links.lock().expect("Links lock").push(handle);
+                    tokio::spawn(link);
+                }
+            }
+        };
+        let (accept_loop, accept) = future::abortable(accept_loop);
+        tokio::spawn(accept_loop);
+
+        Self {
+            addr,
+            stalled,
+            links,
+            accept,
+        }
+    }
+
+    /// Url of the websocket endpoint of the server through the proxy.
+    pub fn ws_url(&self) -> String {
+        format!("ws://{}/ws", self.addr)
+    }
+
+    /// Hold up everything that the server sends, until the connections are severed.
+    pub fn stall(&self) {
+        self.stalled.store(true, Ordering::SeqCst);
+    }
+
+    /// Drop every connection without closing it. New connections are relayed again.
+    pub fn sever(&self) {
+        for link in self.links.lock().expect("Links lock").drain(..) {
+            link.abort();
+        }
+        self.stalled.store(false, Ordering::SeqCst);
+    }
+}
+
+impl Drop for Proxy {
+    fn drop(&mut self) {
+        self.accept.abort();
+        self.sever();
+    }
+}
+
+/// Copy bytes from one side of a link to the other, waiting while `stalled`.
+async fn pump(
+    mut from: impl AsyncRead + Unpin,
+    mut to: impl AsyncWrite + Unpin,
+    stalled: Option<&AtomicBool>,
+) {
+    let mut buf = [0; 4096];
+    while let Ok(len) = from.read(&mut buf).await {
+        if len == 0 {
+            return;
+        }
+        while stalled.map_or(false, |stalled| stalled.load(Ordering::SeqCst)) {
+            tokio::time::delay_for(Duration::from_millis(10)).await;
+        }
+        if to.write_all(&buf[..len]).await.is_err() {
+            return;
+        }
+    }
+}
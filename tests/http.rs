@@ -0,0 +1,310 @@
+//! Scenarios covering clients connected over the HTTP transport.
+
+mod harness;
+
+use ert_concierge::{http::SESSION_HEADER, VERSION};
+use futures::{Stream, StreamExt};
+use harness::{matches, within, TestServer};
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+fn identify(name: &str) -> Value {
+    json!({ "type": "IDENTIFY", "name": name, "version": VERSION, "events": [] })
+}
+
+fn message(name: &str, data: Value) -> Value {
+    json!({
+        "type": "MESSAGE",
+        "target": { "type": "NAME", "name": name },
+        "data": data,
+    })
+}
+
+/// Identify over HTTP, returning the token of the session.
+async fn open(server: &TestServer, http: &Client, name: &str) -> String {
+    let response = within(
+        http.post(&server.url("/http/session"))
+            .body(identify(name).to_string())
+            .send(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let token = response.headers()[SESSION_HEADER]
+        .to_str()
+        .expect("Session token")
+        .to_owned();
+    let hello: Value = response.json().await.unwrap();
+    assert!(matches(
+        &json!({ "type": "HELLO", "version": VERSION }),
+        &hello
+    ));
+    token
+}
+
+async fn post(server: &TestServer, http: &Client, token: &str, payload: Value) -> StatusCode {
+    within(
+        http.post(&server.url(&format!("/http/session/{}", token)))
+            .body(payload.to_string())
+            .send(),
+    )
+    .await
+    .unwrap()
+    .status()
+}
+
+async fn poll(server: &TestServer, http: &Client, token: &str) -> Vec<Value> {
+    let response = within(
+        http.get(&server.url(&format!("/http/session/{}/poll", token)))
+            .send(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    response.json().await.unwrap()
+}
+
+/// Read the payload of the next event from a stream, skipping comments.
+async fn next_event<S, B>(body: &mut S, received: &mut String) -> Value
+where
+    S: Stream<Item = reqwest::Result<B>> + Unpin,
+    B: AsRef<[u8]>,
+{
+    loop {
+        if let Some(end) = received.find("\n\n") {
+            let event: String = received.drain(..end + 2).collect();
+            let data: String = event
+                .lines()
+                .filter(|line| line.starts_with("data: "))
+                .map(|line| &line["data: ".len()..])
+                .collect();
+            if !data.is_empty() {
+                return serde_json::from_str(&data).expect("JSON payload");
+            }
+            continue;
+        }
+        let chunk = body.next().await.expect("Event").expect("Event stream");
+        received.push_str(std::str::from_utf8(chunk.as_ref()).unwrap());
+    }
+}
+
+#[actix_rt::test]
+async fn long_polling() {
+    let server = TestServer::start().await;
+    let http = Client::new();
+    let mut alice = server.connect().await.identify("alice").await;
+    let bob = open(&server, &http, "bob").await;
+
+    // Payloads are held until polled.
+    alice.send(message("bob", json!(1))).await;
+    alice.expect(json!({ "type": "OK", "seq": 0 })).await;
+    alice.send(message("bob", json!(2))).await;
+    alice.expect(json!({ "type": "OK", "seq": 1 })).await;
+    let payloads = poll(&server, &http, &bob).await;
+    assert!(matches(
+        &json!([
+            { "type": "MESSAGE", "origin": { "name": "alice" }, "data": 1 },
+            { "type": "MESSAGE", "data": 2 },
+        ]),
+        &Value::Array(payloads)
+    ));
+
+    // Polls wait for the next payload.
+    let waiting = poll(&server, &http, &bob);
+    futures::pin_mut!(waiting);
+    assert!(
+        tokio::time::timeout(Duration::from_millis(100), &mut waiting)
+            .await
+            .is_err()
+    );
+    alice.send(message("bob", json!(3))).await;
+    let payloads = waiting.await;
+    assert!(matches(
+        &json!([{ "type": "MESSAGE", "data": 3 }]),
+        &Value::Array(payloads)
+    ));
+
+    // Payloads posted by the client are answered through the session.
+    let status = post(&server, &http, &bob, message("alice", json!(4))).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    alice
+        .expect(json!({
+            "type": "MESSAGE",
+            "origin": { "name": "bob" },
+            "data": 4,
+        }))
+        .await;
+    let payloads = poll(&server, &http, &bob).await;
+    assert!(matches(
+        &json!([{ "type": "OK", "seq": 0 }]),
+        &Value::Array(payloads)
+    ));
+}
+
+#[actix_rt::test]
+async fn server_sent_events() {
+    let server = TestServer::start().await;
+    let http = Client::new();
+    let mut alice = server.connect().await.identify("alice").await;
+    let bob = open(&server, &http, "bob").await;
+
+    alice.send(message("bob", json!(1))).await;
+    alice.expect(json!({ "type": "OK", "seq": 0 })).await;
+    let response = within(
+        http.get(&server.url(&format!("/http/session/{}/events", bob)))
+            .send(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["content-type"].to_str().unwrap(),
+        "text/event-stream"
+    );
+
+    // Every payload is one event, starting with those held before attaching.
+    let mut body = Box::pin(response.bytes_stream());
+    let mut received = String::new();
+    let event = within(next_event(&mut body, &mut received)).await;
+    assert!(matches(&json!({ "type": "MESSAGE", "data": 1 }), &event));
+    alice.send(message("bob", json!(2))).await;
+    let event = within(next_event(&mut body, &mut received)).await;
+    assert!(matches(&json!({ "type": "MESSAGE", "data": 2 }), &event));
+}
+
+#[actix_rt::test]
+async fn rejected_identification() {
+    let server = TestServer::with_config(|config| config.secret = Some("hunter2".into())).await;
+    let http = Client::new();
+    let identify_with = |fields: Value| {
+        let mut payload = identify("bob");
+        payload["secret"] = json!("hunter2");
+        if let (Some(object), Value::Object(fields)) = (payload.as_object_mut(), fields) {
+            object.extend(fields);
+        }
+        within(
+            http.post(&server.url("/http/session"))
+                .body(payload.to_string())
+                .send(),
+        )
+    };
+
+    let response = identify_with(json!({ "version": "0.1.0" })).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = identify_with(json!({ "name": "b o b" })).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = identify_with(json!({ "secret": "hunter3" })).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = identify_with(json!({ "type": "SELF_FETCH" }))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = identify_with(json!({})).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = identify_with(json!({})).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let unknown = uuid::Uuid::new_v4().to_string();
+    let status = post(&server, &http, &unknown, json!({ "type": "SELF_FETCH" })).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[actix_rt::test]
+async fn sessions_are_secret() {
+    let server = TestServer::start().await;
+    let http = Client::new();
+    let bob = open(&server, &http, "bob").await;
+    let mut alice = server.connect().await.identify("alice").await;
+    alice.send(json!({ "type": "CLIENT_FETCH_ALL" })).await;
+    let clients = alice
+        .expect(json!({
+            "type": "CLIENT_FETCH_ALL_RESULT",
+            "clients": [{ "name": "alice" }, { "name": "bob" }],
+        }))
+        .await;
+    let uuid = clients["clients"][1]["uuid"].as_str().unwrap().to_owned();
+    assert_ne!(uuid, bob);
+
+    // Other clients know the uuid of bob, but cannot attach to his session with it.
+    for route in &["events", "poll"] {
+        let response = within(
+            http.get(&server.url(&format!("/http/session/{}/{}", uuid, route)))
+                .send(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+    let status = post(&server, &http, &uuid, message("alice", json!(1))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let response = within(
+        http.delete(&server.url(&format!("/http/session/{}", uuid)))
+            .send(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    alice.expect_silence().await;
+
+    // The session is left to bob.
+    let status = post(&server, &http, &bob, json!({ "type": "SELF_FETCH" })).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let payloads = poll(&server, &http, &bob).await;
+    assert!(matches(
+        &json!([{ "type": "SELF_FETCH_RESULT", "client": { "name": "bob", "uuid": uuid } }]),
+        &Value::Array(payloads)
+    ));
+}
+
+#[actix_rt::test]
+async fn disconnects() {
+    let server = TestServer::with_config(|config| {
+        config.heartbeat_interval = Duration::from_millis(50);
+        config.client_timeout = Duration::from_millis(300);
+    })
+    .await;
+    let http = Client::new();
+    let mut alice = server
+        .connect()
+        .await
+        .identify_with(json!({ "name": "alice", "events": ["CLIENT_JOINED", "CLIENT_LEFT"] }))
+        .await;
+
+    // Clients can close their session.
+    let bob = open(&server, &http, "bob").await;
+    alice
+        .expect(json!({ "type": "CLIENT_JOINED", "client": { "name": "bob" } }))
+        .await;
+    let response = within(
+        http.delete(&server.url(&format!("/http/session/{}", bob)))
+            .send(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    alice
+        .expect(json!({ "type": "CLIENT_LEFT", "client": { "name": "bob" } }))
+        .await;
+    let status = post(&server, &http, &bob, json!({ "type": "SELF_FETCH" })).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Polling keeps a session alive, like heartbeats do.
+    let bob = open(&server, &http, "bob").await;
+    alice
+        .expect(json!({ "type": "CLIENT_JOINED", "client": { "name": "bob" } }))
+        .await;
+    for _ in 0..8 {
+        poll(&server, &http, &bob).await;
+    }
+    let status = post(&server, &http, &bob, json!({ "type": "SELF_FETCH" })).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+
+    // Sessions that are neither polled nor streamed time out.
+    alice
+        .expect(json!({ "type": "CLIENT_LEFT", "client": { "name": "bob" } }))
+        .await;
+    let status = post(&server, &http, &bob, json!({ "type": "SELF_FETCH" })).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,93 @@
+//! Scenarios covering latency measurement and clock synchronisation.
+
+mod harness;
+
+use harness::TestServer;
+use serde_json::json;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+fn now_millis() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs_f64()
+        * 1000.0
+}
+
+#[actix_rt::test]
+async fn time_sync() {
+    let server = TestServer::start().await;
+    let mut alice = server.connect().await.identify("alice").await;
+
+    let before = now_millis();
+    alice
+        .send(json!({ "type": "TIME_SYNC", "client_time": 1234.5 }))
+        .await;
+    let result = alice
+        .expect(json!({ "type": "TIME_SYNC_RESULT", "seq": 0, "client_time": 1234.5 }))
+        .await;
+    let after = now_millis();
+
+    // The server shares the clock of the test.
+    let receive = result["server_receive"].as_f64().expect("Receive time");
+    let transmit = result["server_transmit"].as_f64().expect("Transmit time");
+    assert!(before <= receive && receive <= transmit && transmit <= after);
+}
+
+#[actix_rt::test]
+async fn received() {
+    let server = TestServer::start().await;
+    let mut alice = server.connect().await.identify("alice").await;
+    let mut bob = server.connect().await.identify("bob").await;
+
+    let before = now_millis();
+    alice
+        .send(json!({
+            "type": "MESSAGE",
+            "target": { "type": "NAME", "name": "bob" },
+            "data": "hi",
+        }))
+        .await;
+    alice.expect(json!({ "type": "OK", "seq": 0 })).await;
+    let message = bob
+        .expect(json!({ "type": "MESSAGE", "origin": { "name": "alice" } }))
+        .await;
+    let after = now_millis();
+
+    let received = message["origin"]["received"]
+        .as_f64()
+        .expect("Receive time");
+    assert!(before <= received && received <= after);
+}
+
+#[actix_rt::test]
+async fn latency() {
+    let server = TestServer::with_config(|config| {
+        config.heartbeat_interval = Duration::from_millis(300);
+    })
+    .await;
+    let mut alice = server.connect().await.identify("alice").await;
+
+    // Waiting for payloads lets the client answer the pings of the server.
+    alice.expect_silence().await;
+    alice.expect_silence().await;
+
+    alice
+        .send(json!({ "type": "CLIENT_FETCH_ALL", "fields": { "latency": true } }))
+        .await;
+    let result = alice
+        .expect(json!({ "type": "CLIENT_FETCH_ALL_RESULT", "seq": 0 }))
+        .await;
+    let latency = &result["clients"][0]["latency"];
+    let rtt = latency["rtt"].as_f64().expect("Round-trip time");
+    let jitter = latency["jitter"].as_f64().expect("Jitter");
+    assert!(rtt >= 0.0 && rtt < 1000.0);
+    assert!(jitter >= 0.0);
+
+    // The latency is only listed on request.
+    alice.send(json!({ "type": "CLIENT_FETCH_ALL" })).await;
+    let result = alice
+        .expect(json!({ "type": "CLIENT_FETCH_ALL_RESULT", "seq": 1 }))
+        .await;
+    assert!(result["clients"][0].get("latency").is_none());
+}
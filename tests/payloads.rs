@@ -0,0 +1,541 @@
+//! Scenarios covering every `PayloadIn` variant.
+
+mod harness;
+
+use harness::TestServer;
+use serde_json::json;
+use uuid::Uuid;
+
+#[actix_rt::test]
+async fn self_payloads() {
+    let server = TestServer::start().await;
+    let mut alice = server.connect().await.identify("alice").await;
+    let uuid = alice.uuid;
+
+    alice.send(json!({ "type": "SELF_FETCH" })).await;
+    alice
+        .expect(json!({
+            "type": "SELF_FETCH_RESULT",
+            "seq": 0,
+            "client": { "uuid": uuid, "name": "alice" },
+            "subscriptions": [],
+        }))
+        .await;
+
+    alice
+        .send(json!({
+            "type": "SELF_UPDATE",
+            "nickname": "Alice",
+            "tags": ["admin"],
+            "presence": { "status": "BUSY" },
+        }))
+        .await;
+    alice
+        .expect(json!({
+            "type": "CLIENT_UPDATED",
+            "seq": 1,
+            "client": {
+                "nickname": "Alice",
+                "tags": ["admin"],
+                "presence": { "status": "BUSY" },
+            },
+        }))
+        .await;
+
+    alice
+        .send(json!({ "type": "SELF_SET_SEQ", "seq": 10 }))
+        .await;
+    alice.expect(json!({ "type": "OK", "seq": 10 })).await;
+    alice.send(json!({ "type": "SELF_FETCH" })).await;
+    alice
+        .expect(json!({ "type": "SELF_FETCH_RESULT", "seq": 11 }))
+        .await;
+}
+
+#[actix_rt::test]
+async fn self_set_events() {
+    let server = TestServer::start().await;
+    let mut alice = server.connect().await.identify("alice").await;
+
+    alice
+        .send(json!({ "type": "SELF_SET_EVENTS", "events": ["CLIENT_JOINED", "CLIENT_LEFT"] }))
+        .await;
+    alice.expect(json!({ "type": "OK", "seq": 0 })).await;
+
+    let mut bob = server.connect().await.identify("bob").await;
+    alice
+        .expect(json!({ "type": "CLIENT_JOINED", "client": { "name": "bob" } }))
+        .await;
+    bob.send(json!({ "type": "SELF_UPDATE", "nickname": "Bob" }))
+        .await;
+    bob.expect(json!({ "type": "CLIENT_UPDATED", "seq": 0 }))
+        .await;
+    bob.close().await;
+    alice
+        .expect(json!({ "type": "CLIENT_LEFT", "client": { "name": "bob" } }))
+        .await;
+    alice.expect_silence().await;
+}
+
+#[actix_rt::test]
+async fn services() {
+    let server = TestServer::start().await;
+    let mut alice = server.connect().await.identify("alice").await;
+    let mut bob = server.connect().await.identify("bob").await;
+    let (alice_uuid, bob_uuid) = (alice.uuid, bob.uuid);
+
+    alice
+        .send(json!({
+            "type": "SERVICE_CREATE",
+            "service": "chat",
+            "nickname": "Chat",
+            "schemas": { "inbound": { "type": "object" } },
+        }))
+        .await;
+    alice
+        .expect(json!({
+            "type": "SERVICE_CREATE_RESULT",
+            "seq": 0,
+            "successful": true,
+            "service": { "name": "chat", "nickname": "Chat", "owner_uuid": alice_uuid },
+        }))
+        .await;
+    bob.send(json!({ "type": "SERVICE_CREATE", "service": "chat" }))
+        .await;
+    bob.expect(json!({
+        "type": "SERVICE_CREATE_RESULT",
+        "seq": 0,
+        "successful": false,
+        "service": { "owner_uuid": alice_uuid },
+    }))
+    .await;
+    bob.send(json!({ "type": "SERVICE_CREATE", "service": "chat/+" }))
+        .await;
+    bob.expect(json!({ "type": "INVALID_SERVICE", "seq": 1, "service": "chat/+" }))
+        .await;
+    bob.send(json!({
+        "type": "SERVICE_CREATE",
+        "service": "news",
+        "schemas": { "outbound": 5 },
+    }))
+    .await;
+    bob.expect(json!({ "type": "ERROR_SCHEMA", "seq": 2, "path": "/schemas/outbound" }))
+        .await;
+
+    bob.send(json!({ "type": "SERVICE_FETCH", "service": "chat" }))
+        .await;
+    bob.expect(json!({
+        "type": "SERVICE_FETCH_RESULT",
+        "seq": 3,
+        "service": { "name": "chat", "owner_uuid": alice_uuid, "subscribers": [] },
+    }))
+    .await;
+    bob.send(json!({ "type": "SERVICE_FETCH_SCHEMAS", "service": "chat" }))
+        .await;
+    bob.expect(json!({
+        "type": "SERVICE_FETCH_SCHEMAS_RESULT",
+        "seq": 4,
+        "service": "chat",
+        "schemas": { "inbound": { "type": "object" } },
+    }))
+    .await;
+
+    alice
+        .send(json!({ "type": "SERVICE_CREATE", "service": "news" }))
+        .await;
+    alice
+        .expect(json!({ "type": "SERVICE_CREATE_RESULT", "seq": 1, "successful": true }))
+        .await;
+    bob.send(json!({ "type": "SERVICE_FETCH_ALL" })).await;
+    bob.expect(json!({
+        "type": "SERVICE_FETCH_ALL_RESULT",
+        "seq": 5,
+        "services": [{ "name": "chat" }, { "name": "news" }],
+    }))
+    .await;
+    bob.send(json!({
+        "type": "SERVICE_FETCH_ALL",
+        "owner_uuid": alice_uuid,
+        "limit": 1,
+        "fields": { "subscribers": "COUNT" },
+    }))
+    .await;
+    bob.expect(json!({
+        "type": "SERVICE_FETCH_ALL_RESULT",
+        "seq": 6,
+        "services": [{ "name": "chat", "subscriber_count": 0 }],
+        "next": "chat",
+    }))
+    .await;
+    bob.send(json!({ "type": "SERVICE_FETCH_ALL", "name_prefix": "ne" }))
+        .await;
+    bob.expect(json!({
+        "type": "SERVICE_FETCH_ALL_RESULT",
+        "seq": 7,
+        "services": [{ "name": "news" }],
+    }))
+    .await;
+
+    // Subscribers, including the owner, learn about every subscription.
+    alice
+        .send(json!({ "type": "SELF_SUBSCRIBE", "service": "chat" }))
+        .await;
+    alice
+        .expect(json!({ "type": "SELF_SUBSCRIBE_RESULT", "seq": 2, "successful": true }))
+        .await;
+    alice
+        .expect(json!({ "type": "SERVICE_CLIENT_SUBSCRIBED", "client": { "uuid": alice_uuid } }))
+        .await;
+    bob.send(json!({ "type": "SELF_SUBSCRIBE", "service": "chat" }))
+        .await;
+    bob.expect(json!({
+        "type": "SELF_SUBSCRIBE_RESULT",
+        "seq": 8,
+        "successful": true,
+        "service": { "name": "chat" },
+    }))
+    .await;
+    bob.expect(json!({ "type": "SERVICE_CLIENT_SUBSCRIBED", "client": { "uuid": bob_uuid } }))
+        .await;
+    alice
+        .expect(json!({
+            "type": "SERVICE_CLIENT_SUBSCRIBED",
+            "client": { "uuid": bob_uuid },
+            "service": { "name": "chat" },
+        }))
+        .await;
+    bob.send(json!({ "type": "SELF_SUBSCRIBE", "service": "missing" }))
+        .await;
+    bob.expect(json!({ "type": "INVALID_SERVICE", "seq": 9, "service": "missing" }))
+        .await;
+
+    bob.send(json!({ "type": "SELF_UNSUBSCRIBE", "service": "chat" }))
+        .await;
+    bob.expect(json!({ "type": "SELF_UNSUBSCRIBE_RESULT", "seq": 10, "successful": true }))
+        .await;
+    alice
+        .expect(json!({ "type": "SERVICE_CLIENT_UNSUBSCRIBED", "client": { "uuid": bob_uuid } }))
+        .await;
+    bob.send(json!({ "type": "SELF_UNSUBSCRIBE", "service": "chat" }))
+        .await;
+    bob.expect(json!({ "type": "SELF_UNSUBSCRIBE_RESULT", "seq": 11, "successful": false }))
+        .await;
+    bob.send(json!({ "type": "SELF_UNSUBSCRIBE", "service": "missing" }))
+        .await;
+    bob.expect(json!({ "type": "INVALID_SERVICE", "seq": 12 }))
+        .await;
+
+    bob.send(json!({ "type": "SERVICE_DELETE", "service": "chat" }))
+        .await;
+    bob.expect(json!({ "type": "BAD", "seq": 13 })).await;
+    alice
+        .send(json!({ "type": "SERVICE_DELETE", "service": "chat" }))
+        .await;
+    alice
+        .expect(json!({
+            "type": "SERVICE_DELETE_RESULT",
+            "seq": 3,
+            "service": { "name": "chat" },
+        }))
+        .await;
+    bob.send(json!({ "type": "SERVICE_DELETE", "service": "chat" }))
+        .await;
+    bob.expect(json!({ "type": "INVALID_SERVICE", "seq": 14 }))
+        .await;
+    bob.send(json!({ "type": "SERVICE_FETCH", "service": "chat" }))
+        .await;
+    bob.expect(json!({ "type": "INVALID_SERVICE", "seq": 15 }))
+        .await;
+    bob.send(json!({ "type": "SERVICE_FETCH_SCHEMAS", "service": "chat" }))
+        .await;
+    bob.expect(json!({ "type": "INVALID_SERVICE", "seq": 16 }))
+        .await;
+}
+
+#[actix_rt::test]
+async fn pattern_subscriptions() {
+    let server = TestServer::start().await;
+    let mut alice = server.connect().await.identify("alice").await;
+    let mut bob = server.connect().await.identify("bob").await;
+    let alice_uuid = alice.uuid;
+
+    alice
+        .send(json!({ "type": "SELF_SUBSCRIBE", "service": "chat/+" }))
+        .await;
+    alice
+        .expect(json!({
+            "type": "SELF_SUBSCRIBE_PATTERN_RESULT",
+            "seq": 0,
+            "successful": true,
+            "pattern": "chat/+",
+            "services": [],
+        }))
+        .await;
+    alice
+        .send(json!({ "type": "SELF_SUBSCRIBE", "service": "chat/a+" }))
+        .await;
+    alice
+        .expect(json!({ "type": "INVALID_SERVICE", "seq": 1, "service": "chat/a+" }))
+        .await;
+
+    // New services matching the pattern are subscribed to.
+    bob.send(json!({ "type": "SERVICE_CREATE", "service": "chat/general" }))
+        .await;
+    bob.expect(json!({ "type": "SERVICE_CREATE_RESULT", "seq": 0, "successful": true }))
+        .await;
+    alice
+        .expect(json!({
+            "type": "SELF_SUBSCRIBE_RESULT",
+            "successful": true,
+            "service": { "name": "chat/general" },
+        }))
+        .await;
+    alice
+        .expect(json!({ "type": "SERVICE_CLIENT_SUBSCRIBED", "client": { "uuid": alice_uuid } }))
+        .await;
+
+    alice
+        .send(json!({ "type": "SELF_UNSUBSCRIBE", "service": "chat/+" }))
+        .await;
+    alice
+        .expect(json!({
+            "type": "SELF_UNSUBSCRIBE_PATTERN_RESULT",
+            "seq": 2,
+            "successful": true,
+            "services": [{ "name": "chat/general" }],
+        }))
+        .await;
+    alice
+        .send(json!({ "type": "SELF_UNSUBSCRIBE", "service": "chat/#/x" }))
+        .await;
+    alice
+        .expect(json!({ "type": "INVALID_SERVICE", "seq": 3 }))
+        .await;
+}
+
+#[actix_rt::test]
+async fn pattern_and_direct_subscriptions() {
+    let server = TestServer::start().await;
+    let mut alice = server.connect().await.identify("alice").await;
+    let mut bob = server.connect().await.identify("bob").await;
+    let to_lobby = json!({ "type": "SERVICE", "service": "chat/lobby" });
+
+    bob.send(json!({ "type": "SERVICE_CREATE", "service": "chat/lobby" }))
+        .await;
+    bob.expect(json!({ "type": "SERVICE_CREATE_RESULT", "seq": 0, "successful": true }))
+        .await;
+    alice
+        .send(json!({
+            "type": "SELF_SUBSCRIBE",
+            "service": "chat/lobby",
+            "filter": { "op": "EQ", "pointer": "/room", "value": "lobby" },
+        }))
+        .await;
+    alice
+        .expect(json!({ "type": "SELF_SUBSCRIBE_RESULT", "seq": 0, "successful": true }))
+        .await;
+    alice
+        .expect(json!({ "type": "SERVICE_CLIENT_SUBSCRIBED" }))
+        .await;
+    bob.expect(json!({ "type": "SERVICE_CLIENT_SUBSCRIBED" }))
+        .await;
+
+    // A matching pattern keeps the direct subscription and its filter.
+    alice
+        .send(json!({ "type": "SELF_SUBSCRIBE", "service": "chat/+" }))
+        .await;
+    alice
+        .expect(json!({
+            "type": "SELF_SUBSCRIBE_PATTERN_RESULT",
+            "seq": 1,
+            "services": [{ "name": "chat/lobby" }],
+        }))
+        .await;
+    bob.expect_silence().await;
+    bob.send(json!({ "type": "MESSAGE", "target": to_lobby, "data": { "room": "den" } }))
+        .await;
+    bob.expect(json!({ "type": "OK", "seq": 1 })).await;
+    alice.expect_silence().await;
+
+    // Unsubscribing from the pattern leaves the direct subscription in place.
+    alice
+        .send(json!({ "type": "SELF_UNSUBSCRIBE", "service": "chat/+" }))
+        .await;
+    alice
+        .expect(json!({
+            "type": "SELF_UNSUBSCRIBE_PATTERN_RESULT",
+            "seq": 2,
+            "successful": true,
+            "services": [],
+        }))
+        .await;
+    bob.send(json!({ "type": "MESSAGE", "target": to_lobby, "data": { "room": "lobby" } }))
+        .await;
+    bob.expect(json!({ "type": "OK", "seq": 2 })).await;
+    alice
+        .expect(json!({ "type": "MESSAGE", "data": { "room": "lobby" } }))
+        .await;
+
+    // Services subscribed to through a pattern are kept while another pattern matches.
+    alice
+        .send(json!({ "type": "SELF_SUBSCRIBE", "service": "chat/#" }))
+        .await;
+    alice
+        .expect(json!({ "type": "SELF_SUBSCRIBE_PATTERN_RESULT", "seq": 3 }))
+        .await;
+    bob.send(json!({ "type": "SERVICE_CREATE", "service": "chat/den" }))
+        .await;
+    bob.expect(json!({ "type": "SERVICE_CREATE_RESULT", "seq": 3, "successful": true }))
+        .await;
+    alice
+        .expect(json!({ "type": "SELF_SUBSCRIBE_RESULT", "service": { "name": "chat/den" } }))
+        .await;
+    alice
+        .expect(json!({ "type": "SERVICE_CLIENT_SUBSCRIBED" }))
+        .await;
+    alice
+        .send(json!({ "type": "SELF_SUBSCRIBE", "service": "chat/+" }))
+        .await;
+    alice
+        .expect(json!({ "type": "SELF_SUBSCRIBE_PATTERN_RESULT", "seq": 4 }))
+        .await;
+    alice
+        .send(json!({ "type": "SELF_UNSUBSCRIBE", "service": "chat/#" }))
+        .await;
+    alice
+        .expect(json!({ "type": "SELF_UNSUBSCRIBE_PATTERN_RESULT", "seq": 5, "services": [] }))
+        .await;
+    alice
+        .send(json!({ "type": "SELF_UNSUBSCRIBE", "service": "chat/+" }))
+        .await;
+    alice
+        .expect(json!({
+            "type": "SELF_UNSUBSCRIBE_PATTERN_RESULT",
+            "seq": 6,
+            "services": [{ "name": "chat/den" }],
+        }))
+        .await;
+}
+
+#[actix_rt::test]
+async fn client_fetches() {
+    let server = TestServer::start().await;
+    let mut alice = server
+        .connect()
+        .await
+        .identify_with(json!({ "name": "alice", "tags": ["admin"], "events": [] }))
+        .await;
+    let bob = server.connect().await.identify("bob").await;
+
+    alice
+        .send(json!({ "type": "CLIENT_FETCH", "uuid": bob.uuid }))
+        .await;
+    alice
+        .expect(json!({
+            "type": "CLIENT_FETCH_RESULT",
+            "seq": 0,
+            "client": { "uuid": bob.uuid, "name": "bob" },
+            "subscriptions": [],
+        }))
+        .await;
+    let missing = Uuid::new_v4();
+    alice
+        .send(json!({ "type": "CLIENT_FETCH", "uuid": missing }))
+        .await;
+    alice
+        .expect(json!({ "type": "INVALID_UUID", "seq": 1, "uuid": missing }))
+        .await;
+
+    alice.send(json!({ "type": "CLIENT_FETCH_ALL" })).await;
+    alice
+        .expect(json!({
+            "type": "CLIENT_FETCH_ALL_RESULT",
+            "seq": 2,
+            "clients": [{ "name": "alice" }, { "name": "bob" }],
+        }))
+        .await;
+    alice
+        .send(json!({ "type": "CLIENT_FETCH_ALL", "limit": 1 }))
+        .await;
+    alice
+        .expect(json!({
+            "type": "CLIENT_FETCH_ALL_RESULT",
+            "seq": 3,
+            "clients": [{ "name": "alice" }],
+            "next": "alice",
+        }))
+        .await;
+    alice
+        .send(json!({ "type": "CLIENT_FETCH_ALL", "cursor": "alice" }))
+        .await;
+    alice
+        .expect(json!({
+            "type": "CLIENT_FETCH_ALL_RESULT",
+            "seq": 4,
+            "clients": [{ "name": "bob" }],
+        }))
+        .await;
+    alice
+        .send(json!({ "type": "CLIENT_FETCH_ALL", "tag": "admin" }))
+        .await;
+    alice
+        .expect(json!({
+            "type": "CLIENT_FETCH_ALL_RESULT",
+            "seq": 5,
+            "clients": [{ "name": "alice", "tags": ["admin"] }],
+        }))
+        .await;
+}
+
+#[actix_rt::test]
+async fn invalid_payloads() {
+    let server = TestServer::start().await;
+    let mut alice = server.connect().await.identify("alice").await;
+
+    alice.send(json!({ "type": "NOT_A_PAYLOAD" })).await;
+    alice
+        .expect(json!({ "type": "ERROR_PROTOCOL", "seq": 0 }))
+        .await;
+
+    // Identification is only handled once per connection.
+    alice
+        .send(json!({ "type": "IDENTIFY", "name": "eve", "version": ert_concierge::VERSION }))
+        .await;
+    alice
+        .expect(json!({ "type": "ERROR_UNSUPPORTED", "seq": 1 }))
+        .await;
+}
+
+#[actix_rt::test]
+async fn negotiated_capabilities() {
+    let server = TestServer::start().await;
+    let mut alice = server
+        .connect()
+        .await
+        .identify_with(json!({
+            "name": "alice",
+            "events": [],
+            "capabilities": ["BATCHING", "ERRORS", "RESUME"],
+        }))
+        .await;
+
+    // Payloads of a batch are handled in order, each with its own sequence number.
+    alice
+        .send(json!([
+            { "type": "SELF_FETCH", "id": 1 },
+            { "type": "SERVICE_DELETE", "service": "missing", "id": "delete \"missing\"" },
+        ]))
+        .await;
+    alice
+        .expect(json!({ "type": "SELF_FETCH_RESULT", "seq": 0, "id": 1 }))
+        .await;
+    alice
+        .expect(json!({
+            "type": "ERROR",
+            "seq": 1,
+            "id": "delete \"missing\"",
+            "code": "INVALID_SERVICE",
+            "ref": "missing",
+        }))
+        .await;
+}
@@ -0,0 +1,99 @@
+//! Scenarios covering the plugins hosted in the concierge process.
+
+mod harness;
+
+use concierge_api_rs::CloseReason;
+use harness::{Script, TestServer};
+use serde_json::{json, Value};
+
+fn to_service(service: &str, data: Value) -> Value {
+    json!({
+        "type": "MESSAGE",
+        "target": { "type": "SERVICE", "service": service },
+        "data": data,
+    })
+}
+
+/// Subscribe to a service, with nobody else subscribed yet.
+async fn subscribe(client: &mut Script, service: &str, seq: usize) {
+    client
+        .send(json!({ "type": "SELF_SUBSCRIBE", "service": service }))
+        .await;
+    client
+        .expect(json!({ "type": "SELF_SUBSCRIBE_RESULT", "seq": seq }))
+        .await;
+    client
+        .expect(json!({ "type": "SERVICE_CLIENT_SUBSCRIBED" }))
+        .await;
+}
+
+#[actix_rt::test]
+async fn chat() {
+    let server = TestServer::with_config(|config| config.plugins = vec!["chat".into()]).await;
+    let mut alice = server.connect().await.identify("alice").await;
+    let mut bob = server.connect().await.identify("bob").await;
+
+    subscribe(&mut alice, "chat", 0).await;
+    subscribe(&mut bob, "chat", 0).await;
+    alice
+        .expect(json!({ "type": "SERVICE_CLIENT_SUBSCRIBED", "client": { "name": "bob" } }))
+        .await;
+
+    // Text is relayed to every subscriber by the plugin, which owns the service.
+    alice
+        .send(to_service("chat", json!({ "text": "hi" })))
+        .await;
+    alice.expect(json!({ "type": "OK", "seq": 1 })).await;
+    let relayed = json!({
+        "type": "MESSAGE",
+        "origin": { "name": "chat", "service": { "name": "chat" } },
+        "data": { "name": "alice", "text": "hi" },
+    });
+    alice.expect(relayed.clone()).await;
+    bob.expect(relayed).await;
+
+    // Messages are checked against the schemas of the plugin.
+    bob.send(to_service("chat", json!({ "words": 2 }))).await;
+    bob.expect(json!({ "type": "ERROR_SCHEMA", "seq": 1, "path": "" }))
+        .await;
+    alice.expect_silence().await;
+}
+
+#[actix_rt::test]
+async fn dice() {
+    let server = TestServer::with_config(|config| config.plugins = vec!["dice".into()]).await;
+    let mut alice = server.connect().await.identify("alice").await;
+
+    subscribe(&mut alice, "dice", 0).await;
+    alice
+        .send(to_service("dice", json!({ "sides": 20, "count": 3 })))
+        .await;
+    alice.expect(json!({ "type": "OK", "seq": 1 })).await;
+    let rolled = alice
+        .expect(json!({
+            "type": "MESSAGE",
+            "origin": { "name": "dice" },
+            "data": { "name": "alice", "sides": 20 },
+        }))
+        .await;
+    let rolls = rolled["data"]["rolls"].as_array().expect("Rolls");
+    assert_eq!(rolls.len(), 3);
+    assert!(rolls
+        .iter()
+        .all(|roll| roll.as_u64().map_or(false, |roll| (1..=20).contains(&roll))));
+
+    alice.send(to_service("dice", json!({ "sides": 1 }))).await;
+    alice
+        .expect(json!({ "type": "ERROR_SCHEMA", "seq": 2, "path": "/sides" }))
+        .await;
+}
+
+#[actix_rt::test]
+async fn name_taken() {
+    let server = TestServer::with_config(|config| config.plugins = vec!["chat".into()]).await;
+    let mut impostor = server.connect().await;
+    impostor
+        .send(json!({ "type": "IDENTIFY", "name": "chat", "version": ert_concierge::VERSION }))
+        .await;
+    impostor.expect_close(CloseReason::DUPLICATE_AUTH).await;
+}
@@ -0,0 +1,139 @@
+//! Scenarios covering messages queued for offline clients.
+
+mod harness;
+
+use harness::TestServer;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+fn queued_message(name: &str, data: Value) -> Value {
+    json!({
+        "type": "MESSAGE",
+        "target": { "type": "NAME", "name": name },
+        "data": data,
+        "queue": true,
+    })
+}
+
+#[actix_rt::test]
+async fn delivers_in_order_on_identify() {
+    let server = TestServer::start().await;
+    let mut alice = server.connect().await.identify("alice").await;
+
+    let mut first = queued_message("bob", json!(1));
+    first["id"] = json!("first");
+    alice.send(first).await;
+    alice
+        .expect(json!({
+            "type": "MESSAGE_STATUS",
+            "seq": 0,
+            "id": "first",
+            "name": "bob",
+            "status": "QUEUED",
+            "message_seq": 0,
+            "message_id": "first",
+        }))
+        .await;
+    alice.send(queued_message("bob", json!(2))).await;
+    alice
+        .expect(json!({ "type": "MESSAGE_STATUS", "seq": 1, "status": "QUEUED" }))
+        .await;
+
+    // Messages are only queued if asked to.
+    alice
+        .send(json!({
+            "type": "MESSAGE",
+            "target": { "type": "NAME", "name": "bob" },
+            "data": 3,
+        }))
+        .await;
+    alice
+        .expect(json!({ "type": "INVALID_NAME", "seq": 2, "name": "bob" }))
+        .await;
+
+    let mut bob = server.connect().await.identify("bob").await;
+    for data in 1..=2 {
+        let message = bob
+            .expect(json!({
+                "type": "MESSAGE",
+                "origin": { "name": "alice" },
+                "data": data,
+            }))
+            .await;
+        assert!(message.get("queue").is_none());
+    }
+    bob.expect_silence().await;
+    alice
+        .expect(json!({
+            "type": "MESSAGE_STATUS",
+            "name": "bob",
+            "status": "DELIVERED",
+            "message_seq": 0,
+            "message_id": "first",
+        }))
+        .await;
+    alice
+        .expect(json!({ "type": "MESSAGE_STATUS", "status": "DELIVERED", "message_seq": 1 }))
+        .await;
+
+    // Online clients receive queued messages right away.
+    alice.send(queued_message("bob", json!(4))).await;
+    alice.expect(json!({ "type": "OK", "seq": 3 })).await;
+    bob.expect(json!({ "type": "MESSAGE", "data": 4 })).await;
+
+    // Delivered messages are not delivered again.
+    bob.close().await;
+    let mut bob = server.connect().await.identify("bob").await;
+    bob.expect_silence().await;
+}
+
+#[actix_rt::test]
+async fn queues_are_capped() {
+    let server = TestServer::with_config(|config| config.queue_capacity = 1).await;
+    let mut alice = server
+        .connect()
+        .await
+        .identify_with(json!({ "name": "alice", "events": [], "capabilities": ["ERRORS"] }))
+        .await;
+    let mut carol = server.connect().await.identify("carol").await;
+
+    alice.send(queued_message("bob", json!(1))).await;
+    alice
+        .expect(json!({ "type": "MESSAGE_STATUS", "seq": 0, "status": "QUEUED" }))
+        .await;
+    alice.send(queued_message("bob", json!(2))).await;
+    alice
+        .expect(json!({ "type": "ERROR", "seq": 1, "code": "QUEUE_FULL", "ref": "bob" }))
+        .await;
+    carol.send(queued_message("bob", json!(3))).await;
+    carol
+        .expect(json!({ "type": "INVALID_NAME", "seq": 0, "name": "bob" }))
+        .await;
+
+    let mut bob = server.connect().await.identify("bob").await;
+    bob.expect(json!({ "type": "MESSAGE", "data": 1 })).await;
+    bob.expect_silence().await;
+}
+
+#[actix_rt::test]
+async fn messages_expire() {
+    let server =
+        TestServer::with_config(|config| config.queue_ttl = Duration::from_millis(100)).await;
+    let mut alice = server.connect().await.identify("alice").await;
+
+    alice.send(queued_message("bob", json!(1))).await;
+    alice
+        .expect(json!({ "type": "MESSAGE_STATUS", "seq": 0, "status": "QUEUED" }))
+        .await;
+    alice
+        .expect(json!({
+            "type": "MESSAGE_STATUS",
+            "name": "bob",
+            "status": "EXPIRED",
+            "message_seq": 0,
+        }))
+        .await;
+
+    let mut bob = server.connect().await.identify("bob").await;
+    bob.expect_silence().await;
+}
@@ -0,0 +1,187 @@
+//! Scenarios covering clients connected over the raw transport.
+
+mod harness;
+
+use concierge_api_rs::CloseReason;
+use ert_concierge::VERSION;
+use futures::{SinkExt, StreamExt};
+use harness::{matches, within, TestServer};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpStream, UnixStream},
+};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::codec::{Framed, LinesCodec};
+
+/// A scripted client of the raw transport.
+struct Lines<S> {
+    framed: Framed<S, LinesCodec>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Lines<S> {
+    fn new(stream: S) -> Self {
+        Self {
+            framed: Framed::new(stream, LinesCodec::new()),
+        }
+    }
+
+    async fn identify(mut self, name: &str) -> Self {
+        self.send(json!({ "type": "IDENTIFY", "name": name, "version": VERSION, "events": [] }))
+            .await;
+        self.expect(json!({ "type": "HELLO", "version": VERSION }))
+            .await;
+        self
+    }
+
+    async fn send(&mut self, payload: Value) {
+        self.send_line(payload.to_string()).await
+    }
+
+    async fn send_line(&mut self, line: String) {
+        within(self.framed.send(line)).await.expect("Send line")
+    }
+
+    /// The next line, which is empty for heartbeats.
+    async fn next_line(&mut self) -> Option<String> {
+        within(self.framed.next())
+            .await
+            .map(|line| line.expect("Line"))
+    }
+
+    /// Receive the next payload, skipping heartbeats.
+    async fn expect(&mut self, pattern: Value) -> Value {
+        loop {
+            match self.next_line().await {
+                Some(line) if line.is_empty() => {}
+                Some(line) => {
+                    let payload = serde_json::from_str(&line).expect("JSON payload");
+                    assert!(
+                        matches(&pattern, &payload),
+                        "Expected {} but received {}",
+                        pattern,
+                        payload
+                    );
+                    return payload;
+                }
+                None => panic!("Expected {} but the connection closed", pattern),
+            }
+        }
+    }
+
+    /// Expect the concierge to close the connection with that reason.
+    async fn expect_close(mut self, reason: CloseReason<'_>) {
+        let payload = self.expect(json!({})).await;
+        assert_eq!(
+            payload["code"],
+            json!(reason.code),
+            "Closed with {}",
+            payload
+        );
+        assert_eq!(self.next_line().await, None);
+    }
+}
+
+#[actix_rt::test]
+async fn over_tcp() {
+    let server = TestServer::start().await;
+    let mut alice = server.connect().await.identify("alice").await;
+    let stream = within(TcpStream::connect(server.raw_addr)).await.unwrap();
+    let mut bob = Lines::new(stream).identify("bob").await;
+
+    bob.send(json!({
+        "type": "MESSAGE",
+        "target": { "type": "NAME", "name": "alice" },
+        "data": { "bodies": 3 },
+    }))
+    .await;
+    alice
+        .expect(json!({
+            "type": "MESSAGE",
+            "origin": { "name": "bob" },
+            "data": { "bodies": 3 },
+        }))
+        .await;
+
+    // Data relayed with line breaks still arrives on one line.
+    alice
+        .send_frame(Message::Text(
+            "{\"type\":\"MESSAGE\",\"target\":{\"type\":\"NAME\",\"name\":\"bob\"},\"data\":{\n  \"bodies\": 4\n}}"
+                .into(),
+        ))
+        .await;
+    bob.expect(json!({
+        "type": "MESSAGE",
+        "origin": { "name": "alice" },
+        "data": { "bodies": 4 },
+    }))
+    .await;
+
+    bob.send(json!({ "type": "SELF_FETCH" })).await;
+    bob.expect(json!({ "type": "SELF_FETCH_RESULT", "seq": 1 }))
+        .await;
+}
+
+#[actix_rt::test]
+async fn over_unix_socket() {
+    let server = TestServer::start().await;
+    let stream = within(UnixStream::connect(&server.raw_path)).await.unwrap();
+    let mut alice = Lines::new(stream).identify("alice").await;
+
+    alice
+        .send(json!({ "type": "SERVICE_CREATE", "service": "planets" }))
+        .await;
+    alice
+        .expect(json!({ "type": "SERVICE_CREATE_RESULT", "seq": 0, "successful": true }))
+        .await;
+}
+
+#[actix_rt::test]
+async fn rejected_identification() {
+    let server = TestServer::with_config(|config| config.secret = Some("hunter2".into())).await;
+
+    let stream = within(TcpStream::connect(server.raw_addr)).await.unwrap();
+    let mut client = Lines::new(stream);
+    client
+        .send(json!({ "type": "IDENTIFY", "name": "alice", "version": VERSION }))
+        .await;
+    client.expect_close(CloseReason::BAD_SECRET).await;
+
+    let stream = within(TcpStream::connect(server.raw_addr)).await.unwrap();
+    let mut client = Lines::new(stream);
+    client.send(json!({ "type": "SELF_FETCH" })).await;
+    client.expect_close(CloseReason::NO_AUTH).await;
+}
+
+#[actix_rt::test]
+async fn heartbeats() {
+    let server = TestServer::with_config(|config| {
+        config.heartbeat_interval = Duration::from_millis(100);
+        config.client_timeout = Duration::from_millis(250);
+    })
+    .await;
+
+    // Clients that answer heartbeats stay connected.
+    let stream = within(TcpStream::connect(server.raw_addr)).await.unwrap();
+    let mut alice = Lines::new(stream).identify("alice").await;
+    for _ in 0..4 {
+        assert_eq!(alice.next_line().await, Some(String::new()));
+        alice.send_line(String::new()).await;
+    }
+    alice.send(json!({ "type": "SELF_FETCH" })).await;
+    alice
+        .expect(json!({ "type": "SELF_FETCH_RESULT", "seq": 0 }))
+        .await;
+
+    // Clients that do not are dropped.
+    let stream = within(TcpStream::connect(server.raw_addr)).await.unwrap();
+    let bob = Lines::new(stream).identify("bob").await;
+    bob.expect_close(CloseReason::HB_FAILED).await;
+
+    // Clients that never identify are dropped after one heartbeat.
+    let stream = within(TcpStream::connect(server.raw_addr)).await.unwrap();
+    Lines::new(stream)
+        .expect_close(CloseReason::AUTH_FAILED)
+        .await;
+}
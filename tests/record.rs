@@ -0,0 +1,136 @@
+//! Scenarios covering the recording of frames.
+
+mod harness;
+
+use concierge_api_rs::{
+    record::{Entry, Event},
+    CloseReason,
+};
+use ert_concierge::{http::SESSION_HEADER, VERSION};
+use harness::{within, TestServer, TIMEOUT};
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+/// Wait until the recording is flushed up to the close of `closed`
+/// connections, and read its entries.
+async fn recorded(path: &Path, closed: usize) -> Vec<Entry<'static>> {
+    // The recording is flushed periodically.
+    let start = Instant::now();
+    let lines = loop {
+        let text = std::fs::read_to_string(path).unwrap_or_default();
+        if text.matches(r#""kind":"CLOSE""#).count() >= closed {
+            break text;
+        }
+        assert!(start.elapsed() < TIMEOUT * 2, "Recording was not flushed");
+        tokio::time::delay_for(Duration::from_millis(50)).await;
+    };
+    let _ = std::fs::remove_file(path);
+
+    let entries = lines
+        .lines()
+        .map(|line| serde_json::from_str::<Entry>(line).expect("Entry").owned())
+        .collect::<Vec<_>>();
+    assert!(entries.windows(2).all(|pair| pair[0].time <= pair[1].time));
+    entries
+}
+
+fn kinds<'a>(entries: impl IntoIterator<Item = &'a Entry<'static>>) -> Vec<&'static str> {
+    entries
+        .into_iter()
+        .map(|entry| match &entry.event {
+            Event::Open => "OPEN",
+            Event::In { .. } => "IN",
+            Event::Out { .. } => "OUT",
+            Event::Close { .. } => "CLOSE",
+        })
+        .collect()
+}
+
+#[actix_rt::test]
+async fn records_every_frame() {
+    let path = std::env::temp_dir().join(format!("concierge-{}.jsonl", Uuid::new_v4()));
+    let server = TestServer::with_config(|config| config.record = Some(path.clone())).await;
+    let mut alice = server.connect().await.identify("alice").await;
+    let alice_uuid = alice.uuid;
+    alice.send(json!({ "type": "SELF_FETCH" })).await;
+    alice
+        .expect(json!({ "type": "SELF_FETCH_RESULT", "seq": 0 }))
+        .await;
+    alice.close().await;
+
+    let entries = recorded(&path, 1).await;
+    assert!(entries.iter().all(|entry| entry.conn == entries[0].conn));
+    assert_eq!(kinds(&entries), ["OPEN", "IN", "OUT", "IN", "OUT", "CLOSE"]);
+    match &entries[2].event {
+        Event::Out { text } => {
+            assert!(text.contains("HELLO") && text.contains(&alice_uuid.to_string()))
+        }
+        event => panic!("Expected HELLO, recorded {:?}", event),
+    }
+}
+
+#[actix_rt::test]
+async fn records_http_sessions() {
+    let path = std::env::temp_dir().join(format!("concierge-{}.jsonl", Uuid::new_v4()));
+    let server = TestServer::with_config(|config| config.record = Some(path.clone())).await;
+    let http = Client::new();
+    let identify = |name: &str| {
+        let payload = json!({ "type": "IDENTIFY", "name": name, "version": VERSION });
+        within(
+            http.post(&server.url("/http/session"))
+                .body(payload.to_string())
+                .send(),
+        )
+    };
+
+    // Rejected identifications are recorded like closed websockets.
+    let response = identify("b o b").await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = identify("bob").await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let session = server.url(&format!(
+        "/http/session/{}",
+        response.headers()[SESSION_HEADER].to_str().unwrap()
+    ));
+    let response = within(
+        http.post(&session)
+            .body(json!({ "type": "SELF_FETCH" }).to_string())
+            .send(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let response = within(http.get(&format!("{}/poll", session)).send())
+        .await
+        .unwrap();
+    let payloads: Value = response.json().await.unwrap();
+    assert_eq!(payloads[0]["type"], "SELF_FETCH_RESULT");
+    let response = within(http.delete(&session).send()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let entries = recorded(&path, 2).await;
+    let (rejected, identified): (Vec<_>, Vec<_>) = entries
+        .iter()
+        .partition(|entry| entry.conn == entries[0].conn);
+    assert_eq!(kinds(rejected.iter().copied()), ["OPEN", "IN", "CLOSE"]);
+    assert_eq!(
+        rejected[2].event,
+        Event::Close {
+            code: Some(CloseReason::BAD_AUTH.code)
+        }
+    );
+    assert_eq!(
+        kinds(identified.iter().copied()),
+        ["OPEN", "IN", "OUT", "IN", "OUT", "CLOSE"]
+    );
+    match &identified[4].event {
+        Event::Out { text } => assert!(text.contains("SELF_FETCH_RESULT")),
+        event => panic!("Expected SELF_FETCH_RESULT, recorded {:?}", event),
+    }
+}
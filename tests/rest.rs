@@ -0,0 +1,181 @@
+//! Scenarios covering messages published over the REST route.
+
+mod harness;
+
+use ert_concierge::fs::FS_KEY_HEADER;
+use harness::{matches, within, TestServer};
+use reqwest::{header::AUTHORIZATION, Client, RequestBuilder, StatusCode};
+use serde_json::{json, Value};
+
+fn service_message(service: &str, data: Value) -> Value {
+    json!({
+        "type": "MESSAGE",
+        "target": { "type": "SERVICE", "service": service },
+        "data": data,
+    })
+}
+
+/// Publish a payload, returning the status and body of the response.
+async fn publish(request: RequestBuilder, payload: Value) -> (StatusCode, Value) {
+    let response = within(request.body(payload.to_string()).send())
+        .await
+        .unwrap();
+    let status = response.status();
+    (status, response.json().await.unwrap_or(Value::Null))
+}
+
+#[actix_rt::test]
+async fn published_as_client() {
+    let server = TestServer::start().await;
+    let http = Client::new();
+    let url = server.url("/api/message");
+    let mut alice = server.connect().await.identify("alice").await;
+    let mut bob = server.connect().await.identify("bob").await;
+    let bob_key = bob.uuid.to_string();
+
+    alice
+        .send(json!({
+            "type": "SERVICE_CREATE",
+            "service": "planets",
+            "schemas": { "inbound": { "type": "object" } },
+        }))
+        .await;
+    alice
+        .expect(json!({ "type": "SERVICE_CREATE_RESULT", "seq": 0, "successful": true }))
+        .await;
+
+    // Messages are routed like those of the client, and answered in the response.
+    let message = service_message("planets", json!({ "system": "sol" }));
+    let (status, body) = publish(
+        http.post(&url).header(FS_KEY_HEADER, &bob_key),
+        message.clone(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert!(matches(
+        &json!({ "type": "ERROR", "seq": 0, "code": "NOT_SUBSCRIBED", "ref": "planets" }),
+        &body
+    ));
+
+    bob.send(json!({ "type": "SELF_SUBSCRIBE", "service": "planets" }))
+        .await;
+    bob.expect(json!({ "type": "SELF_SUBSCRIBE_RESULT", "seq": 0 }))
+        .await;
+    bob.expect(json!({ "type": "SERVICE_CLIENT_SUBSCRIBED" }))
+        .await;
+    let mut request = message.clone();
+    request["id"] = json!("load");
+    let (status, body) = publish(http.post(&url).header(FS_KEY_HEADER, &bob_key), request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(matches(
+        &json!({ "type": "OK", "seq": 1, "id": "load" }),
+        &body
+    ));
+    alice
+        .expect(json!({
+            "type": "MESSAGE",
+            "origin": { "name": "bob", "service": { "name": "planets" } },
+            "data": { "system": "sol" },
+        }))
+        .await;
+
+    let (status, body) = publish(
+        http.post(&url).header(FS_KEY_HEADER, &bob_key),
+        service_message("planets", json!(5)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(matches(&json!({ "code": "SCHEMA", "seq": 2 }), &body));
+    let (status, body) = publish(
+        http.post(&url).header(FS_KEY_HEADER, &bob_key),
+        json!({ "type": "SELF_FETCH" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(matches(&json!({ "code": "PROTOCOL", "seq": 3 }), &body));
+
+    // Published messages leave the sequence number of the connection alone.
+    bob.send(json!({ "type": "SELF_FETCH" })).await;
+    bob.expect(json!({ "type": "SELF_FETCH_RESULT", "seq": 1 }))
+        .await;
+}
+
+#[actix_rt::test]
+async fn published_with_token() {
+    let server = TestServer::with_config(|config| config.api_token = Some("ci".into())).await;
+    let http = Client::new();
+    let url = server.url("/api/message");
+    let mut alice = server.connect().await.identify("alice").await;
+
+    alice
+        .send(json!({ "type": "SERVICE_CREATE", "service": "planets" }))
+        .await;
+    alice
+        .expect(json!({ "type": "SERVICE_CREATE_RESULT", "seq": 0, "successful": true }))
+        .await;
+
+    // The token may reach any service owner, without subscribing.
+    let (status, body) = publish(
+        http.post(&url).header(AUTHORIZATION, "Bearer ci"),
+        service_message("planets", json!("load")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(matches(&json!({ "type": "OK" }), &body));
+    alice
+        .expect(json!({
+            "type": "MESSAGE",
+            "origin": { "name": "api", "service": { "name": "planets" } },
+            "data": "load",
+        }))
+        .await;
+
+    let (status, body) = publish(
+        http.post(&url).header(AUTHORIZATION, "Bearer ci"),
+        json!({
+            "type": "MESSAGE",
+            "target": { "type": "NAME", "name": "bob" },
+            "data": 1,
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(matches(
+        &json!({ "type": "ERROR", "code": "INVALID_NAME", "ref": "bob" }),
+        &body
+    ));
+}
+
+#[actix_rt::test]
+async fn unauthorized() {
+    let server = TestServer::with_config(|config| config.api_token = Some("ci".into())).await;
+    let http = Client::new();
+    let url = server.url("/api/message");
+    let message = service_message("planets", json!(1));
+
+    let (status, _) = publish(http.post(&url), message.clone()).await;
+    assert_eq!(status, StatusCode::PRECONDITION_REQUIRED);
+    let (status, _) = publish(
+        http.post(&url).header(AUTHORIZATION, "Bearer cd"),
+        message.clone(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = publish(
+        http.post(&url)
+            .header(FS_KEY_HEADER, uuid::Uuid::new_v4().to_string()),
+        message.clone(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Without a token configured, none is accepted.
+    let server = TestServer::start().await;
+    let (status, _) = publish(
+        http.post(&server.url("/api/message"))
+            .header(AUTHORIZATION, "Bearer "),
+        message,
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,233 @@
+//! Scenarios covering the services scripted in Rhai.
+
+mod harness;
+
+use harness::{Script, TestServer};
+use serde_json::{json, Value};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+fn to_service(service: &str, data: Value) -> Value {
+    json!({
+        "type": "MESSAGE",
+        "target": { "type": "SERVICE", "service": service },
+        "data": data,
+    })
+}
+
+/// Serve a concierge hosting the scripts of an empty directory.
+async fn start() -> (TestServer, PathBuf) {
+    let server = TestServer::with_config(|config| {
+        let dir = config.fs_root.join("scripts");
+        std::fs::create_dir_all(&dir).expect("Scripts directory");
+        config.scripts = Some(dir);
+    })
+    .await;
+    let dir = server.config.scripts.clone().unwrap();
+    (server, dir)
+}
+
+/// Identify a client that is told about services, write a script, and
+/// subscribe to its service once it is created.
+async fn load(server: &TestServer, dir: &Path, name: &str, source: &str) -> Script {
+    let mut alice = server
+        .connect()
+        .await
+        .identify_with(json!({ "name": "alice", "events": ["SERVICE_CREATED", "SERVICE_DELETED"] }))
+        .await;
+    std::fs::write(dir.join(format!("{}.rhai", name)), source).expect("Script");
+    alice
+        .expect(json!({ "type": "SERVICE_CREATE_RESULT", "service": { "name": name } }))
+        .await;
+    alice
+        .send(json!({ "type": "SELF_SUBSCRIBE", "service": name }))
+        .await;
+    alice
+        .expect(json!({ "type": "SELF_SUBSCRIBE_RESULT", "seq": 0 }))
+        .await;
+    alice
+        .expect(json!({ "type": "SERVICE_CLIENT_SUBSCRIBED" }))
+        .await;
+    alice
+}
+
+const COUNTER: &str = r#"
+fn on_subscribe(client) {
+    send(client.uuid, #{ welcome: client.name });
+}
+
+fn on_message(origin, data) {
+    let count = if has_state("count") { get_state("count") } else { 0 };
+    count += 1;
+    set_state("count", count);
+    broadcast(#{ name: origin.name, count: count, echo: data });
+}
+"#;
+
+#[actix_rt::test]
+async fn hooks() {
+    let (server, dir) = start().await;
+    let mut alice = load(&server, &dir, "counter", COUNTER).await;
+    alice
+        .expect(json!({
+            "type": "MESSAGE",
+            "origin": { "name": "counter" },
+            "data": { "welcome": "alice" },
+        }))
+        .await;
+
+    for count in 1..=2 {
+        alice
+            .send(to_service("counter", json!({ "text": "hi" })))
+            .await;
+        alice.expect(json!({ "type": "OK", "seq": count })).await;
+        alice
+            .expect(json!({
+                "type": "MESSAGE",
+                "data": { "name": "alice", "count": count, "echo": { "text": "hi" } },
+            }))
+            .await;
+    }
+}
+
+#[actix_rt::test]
+async fn subscriptions() {
+    let (server, dir) = start().await;
+    let source = r#"
+        fn on_subscribe(client) { broadcast(#{ joined: client.name }); }
+        fn on_unsubscribe(client) { broadcast(#{ left: client.name }); }
+    "#;
+    let mut alice = load(&server, &dir, "lobby", source).await;
+    alice
+        .expect(json!({ "type": "MESSAGE", "data": { "joined": "alice" } }))
+        .await;
+
+    let mut bob = server.connect().await.identify("bob").await;
+    bob.send(json!({ "type": "SELF_SUBSCRIBE", "service": "lobby" }))
+        .await;
+    bob.expect(json!({ "type": "SELF_SUBSCRIBE_RESULT", "seq": 0 }))
+        .await;
+    alice
+        .expect(json!({ "type": "SERVICE_CLIENT_SUBSCRIBED", "client": { "name": "bob" } }))
+        .await;
+    alice
+        .expect(json!({ "type": "MESSAGE", "data": { "joined": "bob" } }))
+        .await;
+
+    // Scripts are told of clients that leave, like of those that unsubscribe.
+    bob.close().await;
+    alice
+        .expect(json!({ "type": "SERVICE_CLIENT_UNSUBSCRIBED", "client": { "name": "bob" } }))
+        .await;
+    alice
+        .expect(json!({ "type": "MESSAGE", "data": { "left": "bob" } }))
+        .await;
+}
+
+#[actix_rt::test]
+async fn hot_reload() {
+    let (server, dir) = start().await;
+    let mut alice = load(&server, &dir, "counter", COUNTER).await;
+    alice.expect(json!({ "type": "MESSAGE" })).await;
+    alice
+        .send(to_service("counter", json!({ "text": "hi" })))
+        .await;
+    alice.expect(json!({ "type": "OK", "seq": 1 })).await;
+    alice
+        .expect(json!({ "type": "MESSAGE", "data": { "count": 1 } }))
+        .await;
+
+    // A script that fails to compile leaves the running one in place.
+    let path = dir.join("counter.rhai");
+    std::fs::write(&path, "fn on_message(origin, data) {").expect("Script");
+    tokio::time::delay_for(Duration::from_millis(1500)).await;
+    alice.expect_silence().await;
+
+    // The state is kept across reloads.
+    std::fs::write(
+        &path,
+        r#"fn on_load() { broadcast(#{ reloaded: get_state("count") }); }"#,
+    )
+    .expect("Script");
+    alice
+        .expect(json!({ "type": "MESSAGE", "data": { "reloaded": 1 } }))
+        .await;
+
+    // The service is deleted along with the script.
+    std::fs::remove_file(&path).expect("Script");
+    alice
+        .expect(json!({ "type": "SERVICE_DELETE_RESULT", "service": { "name": "counter" } }))
+        .await;
+}
+
+#[actix_rt::test]
+async fn limits() {
+    let (server, dir) = start().await;
+    let source = r#"
+        fn on_message(origin, data) {
+            if data.spin { loop {} }
+            broadcast(data);
+        }
+    "#;
+    let mut alice = load(&server, &dir, "spinner", source).await;
+
+    // Runaway scripts are aborted, and handle the next message.
+    alice
+        .send(to_service("spinner", json!({ "spin": true })))
+        .await;
+    alice.expect(json!({ "type": "OK", "seq": 1 })).await;
+    alice.expect_silence().await;
+    alice
+        .send(to_service("spinner", json!({ "spin": false })))
+        .await;
+    alice.expect(json!({ "type": "OK", "seq": 2 })).await;
+    alice
+        .expect(json!({ "type": "MESSAGE", "data": { "spin": false } }))
+        .await;
+}
+
+#[actix_rt::test]
+async fn messages_to_all() {
+    let (server, dir) = start().await;
+    let source =
+        r#"fn on_message(origin, data) { broadcast(#{ from: origin.name, echo: data }); }"#;
+    let mut alice = load(&server, &dir, "echo", source).await;
+
+    // Scripts are handed the messages sent to every client, like any other.
+    alice
+        .send(json!({ "type": "MESSAGE", "target": { "type": "ALL" }, "data": "hi" }))
+        .await;
+    alice
+        .expect(json!({ "type": "MESSAGE", "origin": { "name": "alice" }, "data": "hi" }))
+        .await;
+    alice.expect(json!({ "type": "OK", "seq": 1 })).await;
+    alice
+        .expect(json!({
+            "type": "MESSAGE",
+            "origin": { "name": "echo" },
+            "data": { "from": "alice", "echo": "hi" },
+        }))
+        .await;
+}
+
+#[actix_rt::test]
+async fn invalid_names() {
+    let (server, dir) = start().await;
+    let mut alice = server
+        .connect()
+        .await
+        .identify_with(json!({ "name": "alice", "events": ["SERVICE_CREATED"] }))
+        .await;
+
+    // Scripts are named like clients, and their service can not be a pattern.
+    for name in &["chat room", "chat-room", "chat#", "chat+"] {
+        std::fs::write(dir.join(format!("{}.rhai", name)), "").expect("Script");
+    }
+    std::fs::write(dir.join("chat.rhai"), "").expect("Script");
+    alice
+        .expect(json!({ "type": "SERVICE_CREATE_RESULT", "service": { "name": "chat" } }))
+        .await;
+    alice.expect_silence().await;
+}
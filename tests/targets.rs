@@ -0,0 +1,239 @@
+//! Scenarios covering every message `Target`.
+
+mod harness;
+
+use harness::TestServer;
+use serde_json::json;
+use uuid::Uuid;
+
+#[actix_rt::test]
+async fn client_targets() {
+    let server = TestServer::start().await;
+    let mut alice = server.connect().await.identify("alice").await;
+    let mut bob = server.connect().await.identify("bob").await;
+    let alice_uuid = alice.uuid;
+
+    alice
+        .send(json!({
+            "type": "MESSAGE",
+            "target": { "type": "NAME", "name": "bob" },
+            "data": { "text": "hi" },
+        }))
+        .await;
+    alice.expect(json!({ "type": "OK", "seq": 0 })).await;
+    bob.expect(json!({
+        "type": "MESSAGE",
+        "origin": { "uuid": alice_uuid, "name": "alice" },
+        "target": { "type": "NAME", "name": "bob" },
+        "data": { "text": "hi" },
+    }))
+    .await;
+
+    alice
+        .send(json!({
+            "type": "MESSAGE",
+            "target": { "type": "UUID", "uuid": bob.uuid },
+            "data": [1, 2, 3],
+        }))
+        .await;
+    alice.expect(json!({ "type": "OK", "seq": 1 })).await;
+    bob.expect(json!({
+        "type": "MESSAGE",
+        "origin": { "name": "alice" },
+        "data": [1, 2, 3],
+    }))
+    .await;
+
+    alice
+        .send(json!({
+            "type": "MESSAGE",
+            "target": { "type": "NAME", "name": "nobody" },
+            "data": null,
+        }))
+        .await;
+    alice
+        .expect(json!({ "type": "INVALID_NAME", "seq": 2, "name": "nobody" }))
+        .await;
+    let missing = Uuid::new_v4();
+    alice
+        .send(json!({
+            "type": "MESSAGE",
+            "target": { "type": "UUID", "uuid": missing },
+            "data": null,
+        }))
+        .await;
+    alice
+        .expect(json!({ "type": "INVALID_UUID", "seq": 3, "uuid": missing }))
+        .await;
+    bob.expect_silence().await;
+}
+
+#[actix_rt::test]
+async fn service_targets() {
+    let server = TestServer::start().await;
+    let mut alice = server.connect().await.identify("alice").await;
+    let mut bob = server.connect().await.identify("bob").await;
+    let mut carol = server
+        .connect()
+        .await
+        .identify_with(json!({ "name": "carol", "events": [], "capabilities": ["ERRORS"] }))
+        .await;
+    let (bob_uuid, carol_uuid) = (bob.uuid, carol.uuid);
+    let to_chat = json!({ "type": "SERVICE", "service": "chat" });
+
+    alice
+        .send(json!({
+            "type": "SERVICE_CREATE",
+            "service": "chat",
+            "schemas": { "inbound": { "type": "object" } },
+        }))
+        .await;
+    alice
+        .expect(json!({ "type": "SERVICE_CREATE_RESULT", "seq": 0, "successful": true }))
+        .await;
+
+    // Only subscribers can send messages to the service.
+    bob.send(json!({ "type": "MESSAGE", "target": to_chat, "data": {} }))
+        .await;
+    bob.expect(json!({ "type": "BAD", "seq": 0 })).await;
+    carol
+        .send(json!({ "type": "MESSAGE", "target": to_chat, "data": {} }))
+        .await;
+    carol
+        .expect(json!({ "type": "ERROR", "seq": 0, "code": "NOT_SUBSCRIBED", "ref": "chat" }))
+        .await;
+
+    bob.send(json!({ "type": "SELF_SUBSCRIBE", "service": "chat" }))
+        .await;
+    bob.expect(json!({ "type": "SELF_SUBSCRIBE_RESULT", "seq": 1 }))
+        .await;
+    bob.expect(json!({ "type": "SERVICE_CLIENT_SUBSCRIBED" }))
+        .await;
+    carol
+        .send(json!({
+            "type": "SELF_SUBSCRIBE",
+            "service": "chat",
+            "filter": { "op": "EQ", "pointer": "/room", "value": "lobby" },
+        }))
+        .await;
+    carol
+        .expect(json!({ "type": "SELF_SUBSCRIBE_RESULT", "seq": 1 }))
+        .await;
+    carol
+        .expect(json!({ "type": "SERVICE_CLIENT_SUBSCRIBED" }))
+        .await;
+    bob.expect(json!({ "type": "SERVICE_CLIENT_SUBSCRIBED", "client": { "uuid": carol_uuid } }))
+        .await;
+
+    // Subscribers send to the owner, and the data must satisfy the inbound schema.
+    bob.send(json!({ "type": "MESSAGE", "target": to_chat, "data": { "text": "hi" } }))
+        .await;
+    bob.expect(json!({ "type": "OK", "seq": 2 })).await;
+    alice
+        .expect(json!({
+            "type": "MESSAGE",
+            "origin": { "uuid": bob_uuid, "service": { "name": "chat" } },
+            "data": { "text": "hi" },
+        }))
+        .await;
+    bob.send(json!({ "type": "MESSAGE", "target": to_chat, "data": 5 }))
+        .await;
+    bob.expect(json!({ "type": "ERROR_SCHEMA", "seq": 3, "path": "" }))
+        .await;
+
+    // The owner broadcasts to the subscribers whose filter is satisfied.
+    alice
+        .send(json!({ "type": "MESSAGE", "target": to_chat, "data": { "room": "lobby" } }))
+        .await;
+    alice.expect(json!({ "type": "OK", "seq": 1 })).await;
+    bob.expect(json!({ "type": "MESSAGE", "data": { "room": "lobby" } }))
+        .await;
+    carol
+        .expect(json!({ "type": "MESSAGE", "data": { "room": "lobby" } }))
+        .await;
+    alice
+        .send(json!({ "type": "MESSAGE", "target": to_chat, "data": { "room": "den" } }))
+        .await;
+    alice.expect(json!({ "type": "OK", "seq": 2 })).await;
+    bob.expect(json!({ "type": "MESSAGE", "data": { "room": "den" } }))
+        .await;
+    carol.expect_silence().await;
+
+    // Only the owner can message a single subscriber.
+    alice
+        .send(json!({
+            "type": "MESSAGE",
+            "target": { "type": "SERVICE_CLIENT_UUID", "service": "chat", "uuid": carol_uuid },
+            "data": { "text": "welcome" },
+        }))
+        .await;
+    alice.expect(json!({ "type": "OK", "seq": 3 })).await;
+    carol
+        .expect(json!({
+            "type": "MESSAGE",
+            "origin": { "service": { "name": "chat" } },
+            "data": { "text": "welcome" },
+        }))
+        .await;
+    bob.expect_silence().await;
+    carol
+        .send(json!({
+            "type": "MESSAGE",
+            "target": { "type": "SERVICE_CLIENT_UUID", "service": "chat", "uuid": bob_uuid },
+            "data": {},
+        }))
+        .await;
+    carol
+        .expect(json!({ "type": "ERROR", "seq": 2, "code": "NOT_OWNER", "ref": "chat" }))
+        .await;
+    let missing = Uuid::new_v4();
+    alice
+        .send(json!({
+            "type": "MESSAGE",
+            "target": { "type": "SERVICE_CLIENT_UUID", "service": "chat", "uuid": missing },
+            "data": {},
+        }))
+        .await;
+    alice
+        .expect(json!({ "type": "INVALID_UUID", "seq": 4, "uuid": missing }))
+        .await;
+
+    for target in &[
+        json!({ "type": "SERVICE", "service": "missing" }),
+        json!({ "type": "SERVICE_CLIENT_UUID", "service": "missing", "uuid": bob_uuid }),
+    ] {
+        carol
+            .send(json!({ "type": "MESSAGE", "target": target, "data": {} }))
+            .await;
+        carol
+            .expect(json!({ "type": "ERROR", "code": "INVALID_SERVICE", "ref": "missing" }))
+            .await;
+    }
+}
+
+#[actix_rt::test]
+async fn all_target() {
+    let server = TestServer::start().await;
+    let mut alice = server.connect().await.identify("alice").await;
+    let mut bob = server.connect().await.identify("bob").await;
+
+    // Every client receives the message, including its sender.
+    alice
+        .send(json!({
+            "type": "MESSAGE",
+            "target": { "type": "ALL" },
+            "data": { "text": "hello everyone" },
+        }))
+        .await;
+    alice
+        .expect(json!({ "type": "MESSAGE", "data": { "text": "hello everyone" } }))
+        .await;
+    alice.expect(json!({ "type": "OK", "seq": 0 })).await;
+    bob.expect(json!({
+        "type": "MESSAGE",
+        "origin": { "name": "alice" },
+        "target": { "type": "ALL" },
+        "data": { "text": "hello everyone" },
+    }))
+    .await;
+}
@@ -0,0 +1,381 @@
+//! Scenarios covering webhooks, delivered to a stand-in endpoint.
+
+mod harness;
+
+use actix_web::{dev::Server, web, App, HttpRequest, HttpResponse, HttpServer};
+use concierge_api_rs::webhook::{EVENT_HEADER, SIGNATURE_HEADER};
+use ert_concierge::{
+    fs::FS_KEY_HEADER,
+    webhook::{is_public, sign},
+};
+use harness::{matches, within, TestServer, SILENCE};
+use reqwest::{header::AUTHORIZATION, Client, RequestBuilder, StatusCode};
+use serde_json::{json, Value};
+use std::{net::SocketAddr, sync::Mutex, time::Duration};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+/// A delivery received by the endpoint.
+#[derive(Debug)]
+struct Received {
+    event: String,
+    signature: String,
+    body: String,
+}
+
+struct State {
+    deliveries: UnboundedSender<Received>,
+    /// How many more deliveries to fail.
+    failures: Mutex<usize>,
+}
+
+/// An HTTP server on an ephemeral port that records what it is posted.
+struct Endpoint {
+    addr: SocketAddr,
+    deliveries: UnboundedReceiver<Received>,
+    server: Server,
+}
+
+impl Endpoint {
+    /// Serve an endpoint that fails the first `failures` deliveries.
+    fn start(failures: usize) -> Self {
+        let (sender, deliveries) = unbounded_channel();
+        let state = web::Data::new(State {
+            deliveries: sender,
+            failures: Mutex::new(failures),
+        });
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(state.clone())
+                .route("/hook", web::post().to(receive))
+        })
+        .workers(1)
+        .disable_signals()
+        .bind("127.0.0.1:0")
+        .expect("Ephemeral port");
+        let addr = server.addrs()[0];
+
+        Self {
+            addr,
+            deliveries,
+            server: server.run(),
+        }
+    }
+
+    fn url(&self) -> String {
+        format!("http://{}/hook", self.addr)
+    }
+
+    /// Expect a delivery with a body matching the pattern.
+    async fn expect(&mut self, pattern: Value) -> Received {
+        let received = within(self.deliveries.recv())
+            .await
+            .expect("Endpoint stopped");
+        let body = serde_json::from_str(&received.body).expect("JSON body");
+        assert!(
+            matches(&pattern, &body),
+            "Expected a delivery matching {} but received {}",
+            pattern,
+            body
+        );
+        received
+    }
+
+    /// Expect no delivery for a while.
+    async fn expect_silence(&mut self) {
+        if let Ok(received) = tokio::time::timeout(SILENCE, self.deliveries.recv()).await {
+            panic!("Expected silence but received {:?}", received);
+        }
+    }
+}
+
+impl Drop for Endpoint {
+    fn drop(&mut self) {
+        drop(self.server.stop(false));
+    }
+}
+
+async fn receive(req: HttpRequest, body: String, state: web::Data<State>) -> HttpResponse {
+    let header = |name: &str| {
+        req.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string()
+    };
+    let _ = state.deliveries.send(Received {
+        event: header(EVENT_HEADER),
+        signature: header(SIGNATURE_HEADER),
+        body,
+    });
+    let mut failures = state.failures.lock().unwrap();
+    if *failures > 0 {
+        *failures -= 1;
+        HttpResponse::InternalServerError().finish()
+    } else {
+        HttpResponse::Ok().finish()
+    }
+}
+
+/// Send a request, returning the status and body of the response.
+async fn request(request: RequestBuilder) -> (StatusCode, Value) {
+    let response = within(request.send()).await.unwrap();
+    let status = response.status();
+    (status, response.json().await.unwrap_or(Value::Null))
+}
+
+#[actix_rt::test]
+async fn client_events() {
+    // The endpoint listens on the loopback interface.
+    let server = TestServer::with_config(|config| {
+        config.api_token = Some("ci".into());
+        config.webhook_private = true;
+    })
+    .await;
+    let mut endpoint = Endpoint::start(0);
+    let http = Client::new();
+
+    let (status, webhook) = request(
+        http.post(&server.url("/api/webhooks"))
+            .header(AUTHORIZATION, "Bearer ci")
+            .json(&json!({
+                "url": endpoint.url(),
+                "events": ["CLIENT_JOINED", "CLIENT_LEFT", "SERVICE_CREATED"],
+            })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let secret = webhook["secret"].as_str().expect("Secret").to_string();
+
+    let mut alice = server.connect().await.identify("alice").await;
+    let received = endpoint
+        .expect(json!({ "event": "CLIENT_JOINED", "client": { "name": "alice" } }))
+        .await;
+    assert_eq!(received.event, "CLIENT_JOINED");
+    assert_eq!(received.signature, sign(&secret, &received.body));
+
+    alice
+        .send(json!({ "type": "SERVICE_CREATE", "service": "planets" }))
+        .await;
+    alice
+        .expect(json!({ "type": "SERVICE_CREATE_RESULT", "seq": 0, "successful": true }))
+        .await;
+    endpoint
+        .expect(json!({ "event": "SERVICE_CREATED", "service": { "name": "planets" } }))
+        .await;
+
+    alice.close().await;
+    endpoint
+        .expect(json!({ "event": "CLIENT_LEFT", "client": { "name": "alice" } }))
+        .await;
+    endpoint.expect_silence().await;
+}
+
+#[actix_rt::test]
+async fn service_owners() {
+    let server = TestServer::with_config(|config| config.webhook_private = true).await;
+    let mut endpoint = Endpoint::start(0);
+    let http = Client::new();
+    let url = server.url("/api/webhooks");
+    let mut alice = server.connect().await.identify("alice").await;
+    let mut bob = server.connect().await.identify("bob").await;
+    let alice_key = alice.uuid.to_string();
+    let bob_key = bob.uuid.to_string();
+
+    alice
+        .send(json!({ "type": "SERVICE_CREATE", "service": "planets" }))
+        .await;
+    alice
+        .expect(json!({ "type": "SERVICE_CREATE_RESULT", "seq": 0, "successful": true }))
+        .await;
+
+    // Owners may only register for the events of their own services.
+    let registration = json!({
+        "url": endpoint.url(),
+        "events": ["SERVICE_SUBSCRIBED", "SERVICE_UNSUBSCRIBED"],
+        "service": "planets",
+    });
+    let (status, _) = request(
+        http.post(&url)
+            .header(FS_KEY_HEADER, &bob_key)
+            .json(&registration),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = request(http.post(&url).header(FS_KEY_HEADER, &alice_key).json(
+        &json!({ "url": endpoint.url(), "events": ["CLIENT_JOINED"], "service": "planets" }),
+    ))
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = request(
+        http.post(&url)
+            .header(FS_KEY_HEADER, &alice_key)
+            .json(&json!({ "url": endpoint.url(), "events": [], "service": "moons" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = request(
+        http.post(&url)
+            .header(FS_KEY_HEADER, &alice_key)
+            .json(&json!({ "url": "ftp://example.com", "events": [], "service": "planets" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, webhook) = request(
+        http.post(&url)
+            .header(FS_KEY_HEADER, &alice_key)
+            .json(&registration),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let id = webhook["id"].as_str().expect("Id").to_string();
+
+    bob.send(json!({ "type": "SELF_SUBSCRIBE", "service": "planets" }))
+        .await;
+    bob.expect(json!({ "type": "SELF_SUBSCRIBE_RESULT", "seq": 0 }))
+        .await;
+    endpoint
+        .expect(json!({
+            "event": "SERVICE_SUBSCRIBED",
+            "client": { "name": "bob" },
+            "service": { "name": "planets" },
+        }))
+        .await;
+
+    // Webhooks are listed without their secret, and only to who registered them.
+    let (status, list) = request(http.get(&url).header(FS_KEY_HEADER, &alice_key)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(matches(
+        &json!([{ "id": id, "events": ["SERVICE_SUBSCRIBED", "SERVICE_UNSUBSCRIBED"] }]),
+        &list
+    ));
+    assert!(list[0].get("secret").is_none());
+    let (_, list) = request(http.get(&url).header(FS_KEY_HEADER, &bob_key)).await;
+    assert_eq!(list, json!([]));
+
+    let hook_url = server.url(&format!("/api/webhooks/{}", id));
+    let (status, _) = request(http.delete(&hook_url).header(FS_KEY_HEADER, &bob_key)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = request(http.delete(&hook_url).header(FS_KEY_HEADER, &alice_key)).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    bob.send(json!({ "type": "SELF_UNSUBSCRIBE", "service": "planets" }))
+        .await;
+    bob.expect(json!({ "type": "SELF_UNSUBSCRIBE_RESULT", "seq": 1 }))
+        .await;
+    endpoint.expect_silence().await;
+}
+
+#[actix_rt::test]
+async fn retried() {
+    let server = TestServer::with_config(|config| {
+        config.api_token = Some("ci".into());
+        config.webhook_retries = 2;
+        config.webhook_backoff = Duration::from_millis(50);
+        config.webhook_private = true;
+    })
+    .await;
+    let mut endpoint = Endpoint::start(2);
+    let http = Client::new();
+
+    let (status, _) = request(
+        http.post(&server.url("/api/webhooks"))
+            .header(AUTHORIZATION, "Bearer ci")
+            .json(&json!({ "url": endpoint.url(), "events": ["CLIENT_JOINED"] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let _alice = server.connect().await.identify("alice").await;
+    let first = endpoint
+        .expect(json!({ "event": "CLIENT_JOINED", "client": { "name": "alice" } }))
+        .await;
+    // Retries deliver the same event, until the endpoint accepts it.
+    for _ in 0..2 {
+        let retry = endpoint.expect(json!({})).await;
+        assert_eq!(retry.body, first.body);
+    }
+    endpoint.expect_silence().await;
+}
+
+#[actix_rt::test]
+async fn private_addresses() {
+    let server = TestServer::with_config(|config| config.api_token = Some("ci".into())).await;
+    let endpoint = Endpoint::start(0);
+    let http = Client::new();
+    let url = server.url("/api/webhooks");
+
+    // Webhooks can not reach the host or its network, unless allowed to.
+    let urls = [
+        endpoint.url(),
+        format!("http://localhost:{}/hook", endpoint.addr.port()),
+        "http://[::1]/hook".to_string(),
+        "http://169.254.169.254/latest/meta-data".to_string(),
+        "http://10.0.0.1/hook".to_string(),
+        "http://[::ffff:192.168.0.1]/hook".to_string(),
+    ];
+    for hook_url in &urls {
+        let (status, _) = request(
+            http.post(&url)
+                .header(AUTHORIZATION, "Bearer ci")
+                .json(&json!({ "url": hook_url, "events": ["CLIENT_JOINED"] })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", hook_url);
+    }
+
+    let (status, webhooks) = request(http.get(&url).header(AUTHORIZATION, "Bearer ci")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(webhooks, json!([]));
+}
+
+#[test]
+fn special_purpose_addresses() {
+    let private = [
+        "0.1.2.3",
+        "10.0.0.1",
+        "100.64.0.1",
+        "127.0.0.1",
+        "169.254.169.254",
+        "172.16.0.1",
+        "192.0.0.8",
+        "192.0.2.1",
+        "192.168.0.1",
+        "198.18.0.1",
+        "198.19.255.255",
+        "198.51.100.1",
+        "203.0.113.1",
+        "224.0.0.1",
+        "239.255.255.250",
+        "240.0.0.1",
+        "255.255.255.255",
+        "::",
+        "::1",
+        "fc00::1",
+        "fe80::1",
+        "ff02::1",
+        "::ffff:10.0.0.1",
+        "64:ff9b::7f00:1",
+        "64:ff9b::a9fe:a9fe",
+        "2002:c0a8:1::1",
+        "2002:7f00:1::",
+    ];
+    let public = [
+        "1.1.1.1",
+        "100.128.0.1",
+        "192.0.1.1",
+        "198.20.0.1",
+        "203.0.114.1",
+        "223.255.255.255",
+        "2001:4860:4860::8888",
+        "::ffff:1.1.1.1",
+        "64:ff9b::101:101",
+        "2002:101:101::1",
+    ];
+    for ip in &private {
+        assert!(!is_public(ip.parse().unwrap()), "{} is private", ip);
+    }
+    for ip in &public {
+        assert!(is_public(ip.parse().unwrap()), "{} is public", ip);
+    }
+}
@@ -0,0 +1,359 @@
+//! Load generation for the concierge.
+//!
+//! Connects simulated viewers and services, drives a seeded mix of traffic
+//! at a fixed rate, and reports the latency of deliveries, the throughput
+//! and, if its pid is given, the memory of the concierge.
+
+mod options;
+mod stats;
+
+use concierge_api_rs::{PayloadIn, PayloadOut, Target};
+use concierge_client::{Client, ClientBuilder, Connection, Error, Frame, Result};
+use futures::{future, stream, StreamExt};
+use options::{Kind, Options, USAGE};
+use serde::{Deserialize, Serialize};
+use stats::{Memory, Percentiles, Recorder};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{sync::mpsc::UnboundedReceiver, task::JoinHandle, time::delay_for};
+
+/// How often the traffic is driven.
+const TICK: Duration = Duration::from_millis(10);
+/// How long deliveries are awaited after the traffic stops.
+const DRAIN: Duration = Duration::from_secs(2);
+/// How many clients connect at the same time.
+const CONNECT_CONCURRENCY: usize = 64;
+
+/// Data of the messages sent by the load tool.
+#[derive(Serialize, Deserialize)]
+struct Probe<'a> {
+    kind: Kind,
+    /// Microseconds since the start of the run.
+    sent: u64,
+    #[serde(default)]
+    pad: &'a str,
+}
+
+/// Deterministic random numbers (SplitMix64), so that runs can be repeated.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..bound`.
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+/// A simulated service, hosted by its own client.
+struct Service {
+    name: String,
+    owner: Client,
+    subscribers: usize,
+}
+
+/// State shared by the tasks of a load run.
+struct Run {
+    options: Options,
+    start: Instant,
+    recorder: Mutex<Recorder>,
+    pad: String,
+    file: Vec<u8>,
+}
+
+impl Run {
+    fn builder(&self, name: String) -> ClientBuilder {
+        let builder = ClientBuilder::new(&self.options.url, name).events(Vec::new());
+        match &self.options.secret {
+            Some(secret) => builder.secret(secret),
+            None => builder,
+        }
+    }
+
+    fn probe(&self, kind: Kind) -> Probe<'_> {
+        Probe {
+            kind,
+            sent: self.start.elapsed().as_micros() as u64,
+            pad: &self.pad,
+        }
+    }
+
+    /// Record the latency of every probe received by a client.
+    fn receive(self: &Arc<Self>, mut messages: UnboundedReceiver<Frame>) {
+        let run = self.clone();
+        tokio::spawn(async move {
+            while let Some(frame) = messages.next().await {
+                if let Ok(message) = frame.message::<Probe>() {
+                    let sent = Duration::from_micros(message.data.sent);
+                    let latency = run.start.elapsed().checked_sub(sent).unwrap_or_default();
+                    let mut recorder = run.recorder.lock().unwrap();
+                    recorder.kind(message.data.kind).latencies.push(latency);
+                }
+            }
+        });
+    }
+
+    async fn connect(self: &Arc<Self>, name: String) -> Result<Client> {
+        let started = Instant::now();
+        let Connection {
+            client, messages, ..
+        } = self.builder(name).connect().await?;
+        self.recorder
+            .lock()
+            .unwrap()
+            .connects
+            .push(started.elapsed());
+        self.receive(messages);
+        Ok(client)
+    }
+
+    async fn create_service(self: &Arc<Self>, index: usize) -> Result<Service> {
+        let name = format!("load_{}", index);
+        let owner = self.connect(format!("load_service_{}", index)).await?;
+        let response = owner
+            .request(&PayloadIn::ServiceCreate {
+                service: &name,
+                nickname: None,
+                schemas: Default::default(),
+            })
+            .await?;
+        match response.payload()? {
+            PayloadOut::ServiceCreateResult {
+                successful: true, ..
+            } => {}
+            _ => return Err(Error::Refused(response.text().to_owned())),
+        }
+        Ok(Service {
+            name,
+            owner,
+            subscribers: 0,
+        })
+    }
+
+    async fn connect_viewer(self: &Arc<Self>, index: usize, service: &str) -> Result<Client> {
+        let viewer = self.connect(format!("load_viewer_{}", index)).await?;
+        let response = viewer
+            .request(&PayloadIn::SelfSubscribe {
+                service,
+                filter: None,
+            })
+            .await?;
+        match response.payload()? {
+            PayloadOut::SelfSubscribeResult {
+                successful: true, ..
+            } => Ok(viewer),
+            _ => Err(Error::Refused(response.text().to_owned())),
+        }
+    }
+
+    /// Send one message of that kind, returning the upload task if any.
+    fn send(
+        self: &Arc<Self>,
+        kind: Kind,
+        rng: &mut Rng,
+        services: &[Service],
+        viewers: &[Client],
+    ) -> Option<JoinHandle<()>> {
+        let probe = self.probe(kind);
+        let (sent, expected) = match kind {
+            Kind::Fanout => {
+                let service = &services[rng.below(services.len())];
+                let target = Target::Service {
+                    service: &service.name,
+                };
+                (service.owner.message(target, &probe), service.subscribers)
+            }
+            Kind::Direct => {
+                let from = rng.below(viewers.len());
+                // Any other viewer.
+                let to = (from + 1 + rng.below(viewers.len() - 1)) % viewers.len();
+                let target = Target::Uuid {
+                    uuid: viewers[to].uuid(),
+                };
+                (viewers[from].message(target, &probe), 1)
+            }
+            Kind::All => {
+                let from = rng.below(viewers.len());
+                let everyone = viewers.len() + services.len();
+                (viewers[from].message(Target::All, &probe), everyone)
+            }
+            Kind::Upload => {
+                let fs = viewers[rng.below(viewers.len())].fs();
+                let run = self.clone();
+                return Some(tokio::spawn(async move {
+                    let started = Instant::now();
+                    let uploaded = fs.upload("load.bin", run.file.clone()).await;
+                    let mut recorder = run.recorder.lock().unwrap();
+                    let counters = recorder.kind(Kind::Upload);
+                    counters.sent += 1;
+                    counters.expected += 1;
+                    match uploaded {
+                        Ok(()) => counters.latencies.push(started.elapsed()),
+                        Err(_) => counters.errors += 1,
+                    }
+                }));
+            }
+        };
+
+        let mut recorder = self.recorder.lock().unwrap();
+        let counters = recorder.kind(kind);
+        counters.sent += 1;
+        match sent {
+            Ok(()) => counters.expected += expected,
+            Err(_) => counters.errors += 1,
+        }
+        None
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let run = Arc::new(Run {
+        pad: "x".repeat(options.payload),
+        file: vec![0; options.file_size],
+        start: Instant::now(),
+        recorder: Mutex::new(Recorder::default()),
+        options,
+    });
+    let options = &run.options;
+    let mut memory = Memory::default();
+    let sample = |memory: &mut Memory| {
+        if let Some(pid) = options.server_pid {
+            memory.sample(pid);
+        }
+    };
+    sample(&mut memory);
+
+    println!(
+        "Connecting {} viewers and {} services to {}",
+        options.clients, options.services, options.url
+    );
+    let connecting = Instant::now();
+    let mut services = Vec::with_capacity(options.services);
+    for index in 0..options.services {
+        services.push(run.create_service(index).await?);
+    }
+    // Viewers subscribe to the services in turn.
+    for index in 0..options.clients {
+        services[index % options.services].subscribers += 1;
+    }
+    let names: Vec<_> = services.iter().map(|service| &service.name).collect();
+    let viewers = stream::iter(0..options.clients)
+        .map(|index| run.connect_viewer(index, names[index % names.len()]))
+        .buffered(CONNECT_CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>>>()?;
+    let connected = connecting.elapsed();
+    sample(&mut memory);
+
+    println!(
+        "Driving {:.1} messages/s for {:.1}s",
+        options.rate,
+        options.duration.as_secs_f64()
+    );
+    let mut rng = Rng(options.seed);
+    let mut uploads = Vec::new();
+    let mut interval = tokio::time::interval(TICK);
+    let ticks = (options.duration.as_secs_f64() / TICK.as_secs_f64()).round() as u64;
+    let per_tick = options.rate * TICK.as_secs_f64();
+    let mut due = 0.0;
+    let driving = Instant::now();
+    for tick in 0..ticks {
+        interval.tick().await;
+        due += per_tick;
+        while due >= 1.0 {
+            due -= 1.0;
+            let kind = options
+                .mix
+                .pick(rng.below(options.mix.total() as usize) as u32);
+            uploads.extend(run.send(kind, &mut rng, &services, &viewers));
+        }
+        if tick % 25 == 0 {
+            sample(&mut memory);
+        }
+    }
+    let driven = driving.elapsed();
+
+    future::join_all(uploads).await;
+    delay_for(DRAIN).await;
+    sample(&mut memory);
+    for client in services
+        .iter()
+        .map(|service| &service.owner)
+        .chain(&viewers)
+    {
+        let _ = client.close();
+    }
+
+    let mut recorder = run.recorder.lock().unwrap();
+    println!();
+    match Percentiles::of(&mut recorder.connects) {
+        Some(percentiles) => println!(
+            "Connected in {:.2}s, identification {}",
+            connected.as_secs_f64(),
+            percentiles
+        ),
+        None => println!("Connected in {:.2}s", connected.as_secs_f64()),
+    }
+
+    let (mut sent, mut delivered) = (0, 0);
+    println!(
+        "{:<8} {:>8} {:>10} {:>10} {:>7}  latency",
+        "kind", "sent", "delivered", "expected", "errors"
+    );
+    for &kind in &Kind::ALL {
+        let counters = match recorder.kinds.get_mut(&kind) {
+            Some(counters) => counters,
+            None => continue,
+        };
+        sent += counters.sent;
+        delivered += counters.latencies.len();
+        let latency = match Percentiles::of(&mut counters.latencies) {
+            Some(percentiles) => percentiles.to_string(),
+            None => "-".to_owned(),
+        };
+        println!(
+            "{:<8} {:>8} {:>10} {:>10} {:>7}  {}",
+            kind.name(),
+            counters.sent,
+            counters.latencies.len(),
+            counters.expected,
+            counters.errors,
+            latency
+        );
+    }
+
+    let seconds = driven.as_secs_f64();
+    println!(
+        "Throughput: {:.1} sent/s, {:.1} delivered/s",
+        sent as f64 / seconds,
+        delivered as f64 / seconds
+    );
+    if options.server_pid.is_some() {
+        println!("Concierge memory: {}", memory);
+    }
+    Ok(())
+}
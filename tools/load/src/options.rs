@@ -0,0 +1,197 @@
+use serde::{Deserialize, Serialize};
+use std::{str::FromStr, time::Duration};
+
+pub const USAGE: &str = "\
+Usage: concierge_load [OPTIONS] [URL]
+
+Connects simulated clients and services to the concierge at URL
+(default: ws://127.0.0.1:64209/ws) and reports how it copes with the traffic.
+
+Options:
+    --clients N          Simulated viewers, subscribed to the services in turn (default: 50)
+    --services M         Simulated services, each hosted by its own client (default: 2)
+    --duration SECS      How long traffic is driven (default: 10)
+    --rate N             Messages sent per second, across all clients (default: 100)
+    --mix KIND=WEIGHT,.. Share of each kind of traffic, among FANOUT, DIRECT,
+                         ALL and UPLOAD (default: fanout=4,direct=4,all=1,upload=1)
+    --payload BYTES      Padding added to the data of every message (default: 64)
+    --file-size BYTES    Size of uploaded files (default: 65536)
+    --seed N             Seed of the traffic, so that runs can be repeated (default: 0)
+    --secret SECRET      Secret of the concierge
+    --server-pid PID     Sample the memory of the concierge process (Linux only)
+    -h, --help           Print this message";
+
+/// Kind of traffic driven by the load tool.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Kind {
+    /// A service owner broadcasts to the subscribers of its service.
+    Fanout,
+    /// A viewer messages another viewer by uuid.
+    Direct,
+    /// A viewer messages every client with `Target::All`.
+    All,
+    /// A viewer uploads a file to its folder.
+    Upload,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 4] = [Kind::Fanout, Kind::Direct, Kind::All, Kind::Upload];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Fanout => "FANOUT",
+            Kind::Direct => "DIRECT",
+            Kind::All => "ALL",
+            Kind::Upload => "UPLOAD",
+        }
+    }
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Kind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Unknown kind of traffic: {}", s))
+    }
+}
+
+/// Relative weights of each kind of traffic.
+#[derive(Clone, Debug)]
+pub struct Mix {
+    weights: Vec<(Kind, u32)>,
+    total: u32,
+}
+
+impl Mix {
+    /// Pick the kind of traffic that a roll in `0..self.total()` falls in.
+    pub fn pick(&self, mut roll: u32) -> Kind {
+        for &(kind, weight) in &self.weights {
+            if roll < weight {
+                return kind;
+            }
+            roll -= weight;
+        }
+        unreachable!("Roll out of range")
+    }
+
+    /// Sum of the weights.
+    pub fn total(&self) -> u32 {
+        self.total
+    }
+}
+
+impl FromStr for Mix {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = Vec::new();
+        for entry in s.split(',').filter(|entry| !entry.is_empty()) {
+            let mut parts = entry.splitn(2, '=');
+            let kind = parts.next().unwrap_or_default().parse::<Kind>()?;
+            let weight = match parts.next() {
+                Some(weight) => weight
+                    .parse::<u32>()
+                    .map_err(|_| format!("Invalid weight: {}", entry))?,
+                None => 1,
+            };
+            weights.retain(|&(other, _)| other != kind);
+            if weight > 0 {
+                weights.push((kind, weight));
+            }
+        }
+        weights.sort();
+        let total = weights.iter().map(|&(_, weight)| weight).sum();
+        if total == 0 {
+            return Err("The mix has no traffic".to_owned());
+        }
+        Ok(Mix { weights, total })
+    }
+}
+
+/// Settings of a load run.
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub url: String,
+    pub secret: Option<String>,
+    pub clients: usize,
+    pub services: usize,
+    pub duration: Duration,
+    pub rate: f64,
+    pub mix: Mix,
+    pub payload: usize,
+    pub file_size: usize,
+    pub seed: u64,
+    pub server_pid: Option<u32>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            url: "ws://127.0.0.1:64209/ws".to_owned(),
+            secret: None,
+            clients: 50,
+            services: 2,
+            duration: Duration::from_secs(10),
+            rate: 100.0,
+            mix: "fanout=4,direct=4,all=1,upload=1".parse().unwrap(),
+            payload: 64,
+            file_size: 64 * 1024,
+            seed: 0,
+            server_pid: None,
+        }
+    }
+}
+
+impl Options {
+    /// Parse the command line arguments, excluding the program name.
+    /// Returns `Ok(None)` if help was requested.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Self>, String> {
+        fn value<T: FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+            let value = value.ok_or_else(|| format!("Missing value of {}", flag))?;
+            value
+                .parse()
+                .map_err(|_| format!("Invalid value of {}: {}", flag, value))
+        }
+
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "--clients" => options.clients = value(&arg, args.next())?,
+                "--services" => options.services = value(&arg, args.next())?,
+                "--duration" => {
+                    options.duration = Duration::from_secs_f64(value(&arg, args.next())?)
+                }
+                "--rate" => options.rate = value(&arg, args.next())?,
+                "--mix" => {
+                    let mix = args.next().ok_or("Missing value of --mix")?;
+                    options.mix = mix.parse()?;
+                }
+                "--payload" => options.payload = value(&arg, args.next())?,
+                "--file-size" => options.file_size = value(&arg, args.next())?,
+                "--seed" => options.seed = value(&arg, args.next())?,
+                "--secret" => options.secret = Some(value(&arg, args.next())?),
+                "--server-pid" => options.server_pid = Some(value(&arg, args.next())?),
+                flag if flag.starts_with('-') => return Err(format!("Unknown option: {}", flag)),
+                _ => options.url = arg,
+            }
+        }
+
+        if options.clients < 2 {
+            return Err("At least 2 clients are needed".to_owned());
+        }
+        if options.services == 0 {
+            return Err("At least 1 service is needed".to_owned());
+        }
+        if options.rate.is_nan() || options.rate <= 0.0 {
+            return Err("The rate must be positive".to_owned());
+        }
+        Ok(Some(options))
+    }
+}
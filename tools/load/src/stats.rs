@@ -0,0 +1,114 @@
+use crate::options::Kind;
+use std::{collections::BTreeMap, fmt, time::Duration};
+
+/// Counters and latencies of one kind of traffic.
+#[derive(Default, Debug)]
+pub struct Counters {
+    /// Messages sent, or uploads started.
+    pub sent: usize,
+    /// Deliveries expected from the messages sent.
+    pub expected: usize,
+    /// Latencies of the deliveries received, or uploads completed.
+    pub latencies: Vec<Duration>,
+    /// Sends or uploads that failed.
+    pub errors: usize,
+}
+
+/// Everything recorded during a load run.
+#[derive(Default, Debug)]
+pub struct Recorder {
+    pub kinds: BTreeMap<Kind, Counters>,
+    /// Time taken by each client to connect and identify.
+    pub connects: Vec<Duration>,
+}
+
+impl Recorder {
+    pub fn kind(&mut self, kind: Kind) -> &mut Counters {
+        self.kinds.entry(kind).or_default()
+    }
+}
+
+/// Percentiles of a set of latencies.
+pub struct Percentiles {
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl Percentiles {
+    /// Summarize the latencies, returning `None` if there are none.
+    pub fn of(latencies: &mut [Duration]) -> Option<Self> {
+        if latencies.is_empty() {
+            return None;
+        }
+        latencies.sort();
+        let rank = |percent: usize| latencies[(latencies.len() - 1) * percent / 100];
+        Some(Self {
+            p50: rank(50),
+            p90: rank(90),
+            p99: rank(99),
+            max: latencies[latencies.len() - 1],
+        })
+    }
+}
+
+impl fmt::Display for Percentiles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "p50 {:>9} p90 {:>9} p99 {:>9} max {:>9}",
+            millis(self.p50),
+            millis(self.p90),
+            millis(self.p99),
+            millis(self.max)
+        )
+    }
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.2}ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// Resident memory of a process in bytes, read from `/proc`.
+/// Returns `None` if it can not be read, such as on other platforms than Linux.
+pub fn resident_memory(pid: u32) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes = line.split_whitespace().nth(1)?.parse::<u64>().ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// Samples of the memory of the concierge process.
+#[derive(Default, Debug)]
+pub struct Memory {
+    pub start: Option<u64>,
+    pub peak: Option<u64>,
+    pub end: Option<u64>,
+}
+
+impl Memory {
+    pub fn sample(&mut self, pid: u32) {
+        if let Some(bytes) = resident_memory(pid) {
+            self.start = self.start.or(Some(bytes));
+            self.peak = self.peak.max(Some(bytes));
+            self.end = Some(bytes);
+        }
+    }
+}
+
+impl fmt::Display for Memory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mebibytes = |bytes: Option<u64>| match bytes {
+            Some(bytes) => format!("{:.1}MiB", bytes as f64 / (1024.0 * 1024.0)),
+            None => "-".to_owned(),
+        };
+        write!(
+            f,
+            "start {} peak {} end {}",
+            mebibytes(self.start),
+            mebibytes(self.peak),
+            mebibytes(self.end)
+        )
+    }
+}
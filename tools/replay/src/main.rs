@@ -0,0 +1,318 @@
+//! Replays recordings of the concierge, made by running it with
+//! `CONCIERGE_RECORD` set. See `concierge_api_rs::record` for the format.
+
+use concierge_api_rs::{
+    record::{Entry, Event},
+    PayloadIn, PayloadMessage, PayloadOut, Target,
+};
+use futures::{
+    future,
+    stream::{SplitSink, SplitStream},
+    SinkExt, StreamExt,
+};
+use serde_json::value::RawValue;
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{net::TcpStream, sync::oneshot, time::delay_until};
+use tokio_tungstenite::{tungstenite::Message, WebSocketStream};
+use uuid::Uuid;
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+type Socket = WebSocketStream<TcpStream>;
+type Sink = SplitSink<Socket, Message>;
+
+const USAGE: &str = "\
+Usage:
+    concierge_replay drive RECORDING [URL] [OPTIONS]
+    concierge_replay service RECORDING SERVICE [URL] [OPTIONS]
+
+drive    Re-drive the concierge at URL (default: ws://127.0.0.1:64209/ws) with
+         every connection of the recording, sending what their clients sent.
+service  Act as the owner of SERVICE, re-emitting the messages that its owner
+         broadcast to its subscribers in the recording.
+
+Options:
+    --speed FACTOR   Replay faster or slower than recorded (default: 1)
+    --loop           Keep re-emitting the service messages (service only)
+    -h, --help       Print this message";
+
+/// How long the concierge has to answer an identification.
+const HELLO_TIMEOUT: Duration = Duration::from_secs(5);
+
+enum Mode {
+    Drive,
+    Service(String),
+}
+
+struct Options {
+    mode: Mode,
+    recording: PathBuf,
+    url: String,
+    speed: f64,
+    looping: bool,
+}
+
+impl Options {
+    /// Parse the command line arguments, excluding the program name.
+    /// Returns `Ok(None)` if help was requested.
+    fn parse(args: impl IntoIterator<Item = String>) -> std::result::Result<Option<Self>, String> {
+        let mut positional = Vec::new();
+        let (mut speed, mut looping) = (1.0, false);
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "--speed" => {
+                    speed = args
+                        .next()
+                        .and_then(|speed| speed.parse::<f64>().ok())
+                        .filter(|speed| *speed > 0.0)
+                        .ok_or("The speed must be a positive number")?;
+                }
+                "--loop" => looping = true,
+                flag if flag.starts_with('-') => return Err(format!("Unknown option: {}", flag)),
+                _ => positional.push(arg),
+            }
+        }
+
+        let mut positional = positional.into_iter();
+        let mode = positional.next().ok_or("Missing mode")?;
+        let recording = positional.next().ok_or("Missing recording")?.into();
+        let mode = match mode.as_str() {
+            "drive" => Mode::Drive,
+            "service" => Mode::Service(positional.next().ok_or("Missing service")?),
+            mode => return Err(format!("Unknown mode: {}", mode)),
+        };
+        let url = positional
+            .next()
+            .unwrap_or_else(|| "ws://127.0.0.1:64209/ws".to_owned());
+        Ok(Some(Self {
+            mode,
+            recording,
+            url,
+            speed,
+            looping,
+        }))
+    }
+
+    /// The instant at which something recorded at `time` is replayed.
+    fn at(&self, start: Instant, time: u64) -> tokio::time::Instant {
+        let offset = Duration::from_secs_f64(time as f64 / 1_000_000.0 / self.speed);
+        tokio::time::Instant::from_std(start + offset)
+    }
+}
+
+/// Split a text frame into its payloads, since clients may batch them.
+fn payloads(text: &str) -> Vec<&str> {
+    if text.trim_start().starts_with('[') {
+        if let Ok(batch) = serde_json::from_str::<Vec<&RawValue>>(text) {
+            return batch.into_iter().map(RawValue::get).collect();
+        }
+    }
+    vec![text]
+}
+
+/// The uuid assigned in a `HELLO` payload.
+fn hello_uuid(text: &str) -> Option<Uuid> {
+    match serde_json::from_str(text) {
+        Ok(PayloadOut::Hello { uuid, .. }) => Some(uuid),
+        _ => None,
+    }
+}
+
+/// Read the frames of the concierge until the connection closes, sending
+/// the uuid of its `HELLO` through `hello`.
+async fn read(mut stream: SplitStream<Socket>, hello: oneshot::Sender<Uuid>) {
+    let mut hello = Some(hello);
+    while let Some(Ok(message)) = stream.next().await {
+        if let Message::Text(text) = message {
+            if let Some(uuid) = hello_uuid(&text) {
+                if let Some(hello) = hello.take() {
+                    let _ = hello.send(uuid);
+                }
+            }
+        }
+    }
+}
+
+/// Connect to the concierge, reading its frames in the background.
+async fn connect(url: &str) -> Result<(Sink, oneshot::Receiver<Uuid>)> {
+    let (socket, _) = tokio_tungstenite::connect_async(url).await?;
+    let (sink, stream) = socket.split();
+    let (hello_tx, hello_rx) = oneshot::channel();
+    tokio::spawn(read(stream, hello_tx));
+    Ok((sink, hello_rx))
+}
+
+/// Await the uuid of the `HELLO` payload.
+async fn hello(hello: oneshot::Receiver<Uuid>) -> Result<Uuid> {
+    match tokio::time::timeout(HELLO_TIMEOUT, hello).await {
+        Ok(Ok(uuid)) => Ok(uuid),
+        _ => Err("The concierge did not identify the client".into()),
+    }
+}
+
+/// Re-drive every connection of the recording.
+async fn drive(options: &Options, entries: Vec<Entry<'static>>) -> Result<()> {
+    let mut connections = BTreeMap::<u64, Vec<Entry<'static>>>::new();
+    for entry in entries {
+        connections.entry(entry.conn).or_default().push(entry);
+    }
+    println!("Replaying {} connections", connections.len());
+
+    // Uuids are assigned anew, so the recorded ones are replaced in the frames.
+    let uuids = Arc::new(Mutex::new(HashMap::new()));
+    let start = Instant::now();
+    let replays = connections.into_iter().map(|(conn, entries)| {
+        let uuids = uuids.clone();
+        async move {
+            if let Err(err) = replay_connection(options, start, entries, &uuids).await {
+                eprintln!("Connection {} failed: {}", conn, err);
+            }
+        }
+    });
+    future::join_all(replays).await;
+    Ok(())
+}
+
+async fn replay_connection(
+    options: &Options,
+    start: Instant,
+    entries: Vec<Entry<'static>>,
+    uuids: &Mutex<HashMap<String, String>>,
+) -> Result<()> {
+    let mut socket = None;
+    for entry in entries {
+        delay_until(options.at(start, entry.time)).await;
+        match entry.event {
+            Event::Open => socket = Some(connect(&options.url).await?),
+            Event::In { text } => {
+                let (sink, _) = socket.as_mut().ok_or("Frame sent before opening")?;
+                let text = uuids
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .fold(text.into_owned(), |text, (recorded, live)| {
+                        text.replace(recorded, live)
+                    });
+                sink.send(Message::Text(text)).await?;
+            }
+            Event::Out { text } => {
+                if let (Some(recorded), Some((sink, hello_rx))) = (hello_uuid(&text), socket.take())
+                {
+                    let live = hello(hello_rx).await?;
+                    uuids
+                        .lock()
+                        .unwrap()
+                        .insert(recorded.to_string(), live.to_string());
+                    // The receiver has been used, so it is replaced by one that never resolves.
+                    socket = Some((sink, oneshot::channel().1));
+                }
+            }
+            Event::Close { .. } => {
+                if let Some((mut sink, _)) = socket.take() {
+                    sink.close().await?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Act as the owner of a service, re-emitting what it broadcast.
+async fn serve(options: &Options, service: &str, entries: Vec<Entry<'static>>) -> Result<()> {
+    // Every payload sent by a client, in order.
+    let sent = entries
+        .iter()
+        .filter_map(|entry| match &entry.event {
+            Event::In { text } => Some((entry.conn, entry.time, text.as_ref())),
+            _ => None,
+        })
+        .flat_map(|(conn, time, text)| {
+            payloads(text)
+                .into_iter()
+                .map(move |payload| (conn, time, payload))
+        })
+        .collect::<Vec<_>>();
+
+    let (owner, create) = sent
+        .iter()
+        .find(|(_, _, payload)| match serde_json::from_str(payload) {
+            Ok(PayloadIn::ServiceCreate { service: name, .. }) => name == service,
+            _ => false,
+        })
+        .map(|&(conn, _, payload)| (conn, payload))
+        .ok_or_else(|| format!("No client created {} in the recording", service))?;
+    let sent = sent.into_iter().filter(|&(conn, _, _)| conn == owner);
+    let mut identify = None;
+    let mut broadcasts = Vec::new();
+    for (_, time, payload) in sent {
+        match serde_json::from_str::<PayloadIn>(payload) {
+            Ok(PayloadIn::Identify { .. }) if identify.is_none() => identify = Some(payload),
+            _ => match serde_json::from_str::<PayloadMessage<&RawValue>>(payload) {
+                Ok(PayloadMessage {
+                    target: Target::Service { service: name },
+                    ..
+                }) if name == service => broadcasts.push((time, payload)),
+                _ => {}
+            },
+        }
+    }
+    let identify = identify.ok_or("The owner did not identify in the recording")?;
+
+    let (mut sink, hello_rx) = connect(&options.url).await?;
+    sink.send(Message::Text(identify.to_owned())).await?;
+    hello(hello_rx).await?;
+    sink.send(Message::Text(create.to_owned())).await?;
+    println!(
+        "Serving {} with {} recorded messages",
+        service,
+        broadcasts.len()
+    );
+
+    let first = broadcasts.first().map_or(0, |&(time, _)| time);
+    loop {
+        let start = Instant::now();
+        for &(time, payload) in &broadcasts {
+            delay_until(options.at(start, time - first)).await;
+            sink.send(Message::Text(payload.to_owned())).await?;
+        }
+        if !options.looping || broadcasts.is_empty() {
+            break;
+        }
+    }
+    sink.close().await?;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let recording = std::fs::read_to_string(&options.recording)?;
+    let entries = recording
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str::<Entry>(line).map(|entry| entry.owned()))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    match &options.mode {
+        Mode::Drive => drive(&options, entries).await,
+        Mode::Service(service) => serve(&options, service, entries).await,
+    }
+}